@@ -22,11 +22,17 @@ pub struct EvmAccount {
     /// Little-endian bytes of an evm::U256
     pub balance: U256,
 
-    /// Read-write lock
+    /// Read-write lock, held by a transaction in progress that changed the account, see AccountLocks
     pub rw_blocked: bool,
 
-    /// Read-only lock counter
+    /// Read-only lock counter, the number of transactions in progress that read the account without changing it
     pub ro_blocked_count: u8,
+
+    /// Generation of the contract storage, part of the CONTRACT_STORAGE keys.\ 
+    /// Resetting or deleting the storage only moves on to a new generation, the entries of older ones are ignored
+    /// and removed later by PruneStorage, so the cost doesn't depend on the size of the storage
+    #[serde(default)]
+    pub storage_generation: u64,
 }
 
 impl EvmAccount {
@@ -43,6 +49,7 @@ impl EvmAccount {
             balance: U256::zero(),
             rw_blocked: false,
             ro_blocked_count: 0_u8,
+            storage_generation: 0_u64,
         }
     }
 }
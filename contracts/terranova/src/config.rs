@@ -1,9 +1,74 @@
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Uint128};
+use cw_storage_plus::Item;
+use evm::{H160, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::hardfork::Hardfork;
+
+/// EVM chain ID used when InstantiateMsg doesn't specify one, each network deployment gets its own
+#[cfg(feature = "mainnet")]
+pub const DEFAULT_CHAIN_ID: u64 = 789_789_001;
+#[cfg(feature = "testnet")]
+pub const DEFAULT_CHAIN_ID: u64 = 789_789_002;
+#[cfg(not(any(feature = "mainnet", feature = "testnet")))]
+pub const DEFAULT_CHAIN_ID: u64 = 789_789_789;
+
+#[cfg(all(feature = "mainnet", feature = "testnet"))]
+compile_error!("The mainnet and testnet features are mutually exclusive");
+
+/// EVM balances have 18 decimals like ether, while Terra native coins have 6,
+/// so one unit of the native denom is worth 10^12 units of EVM balance
+pub const NATIVE_DECIMALS_SCALE: u128 = 1_000_000_000_000;
+
+/// Maximum size of a transaction chunk stored with StoreTxChunk when InstantiateMsg doesn't specify one
+pub const DEFAULT_MAX_CHUNK_SIZE: u32 = 16 * 1024;
+
+/// Maximum total size of the chunks of a transaction when InstantiateMsg doesn't specify one
+pub const DEFAULT_MAX_CHUNKED_TX_SIZE: u32 = 128 * 1024;
+
+/// Convert an amount of the native denom to an EVM balance
+pub fn native_to_evm(amount: Uint128) -> U256 {
+    U256::from(amount.u128()) * U256::from(NATIVE_DECIMALS_SCALE)
+}
+
+/// Convert an EVM balance to an amount of the native denom, rounded down
+pub fn evm_to_native(value: U256) -> Uint128 {
+    let amount = value / U256::from(NATIVE_DECIMALS_SCALE);
+    Uint128::new(amount.min(U256::from(u128::MAX)).as_u128())
+}
+
+/// Contract wide settings of the Terranova EVM, set at instantiation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Config {
+    /// EVM chain ID, returned by CHAINID and required in the signature of EIP-155 transactions
+    pub chain_id: u64,
+    /// Denom of the native Terra coin backing EVM account balances
+    pub denom: String,
+    /// EVM account credited with the gas fees paid by every transaction
+    pub fee_collector: H160,
+    /// Whether the logs of every transaction are saved to TX_LOGS, they are always emitted as events
+    pub persist_logs: bool,
+    /// Ethereum hardfork whose rules the EVM follows, can be upgraded by migrating the contract
+    #[serde(default)]
+    pub hardfork: Hardfork,
+    /// Maximum size in bytes of a chunk stored with StoreTxChunk
+    #[serde(default = "default_max_chunk_size")]
+    pub max_chunk_size: u32,
+    /// Maximum total size in bytes of the stored chunks of a transaction
+    #[serde(default = "default_max_chunked_tx_size")]
+    pub max_chunked_tx_size: u32,
+}
+
+const fn default_max_chunk_size() -> u32 {
+    DEFAULT_MAX_CHUNK_SIZE
+}
+
+const fn default_max_chunked_tx_size() -> u32 {
+    DEFAULT_MAX_CHUNKED_TX_SIZE
+}
+
+pub const CONTRACT_CONFIG: Item<Config> = Item::new("config");
 
 pub fn token_mint_dummy() -> Addr {
     Addr::unchecked("NOVA_token_mint_dummy")
 }
-
-pub fn chain_id_dummy() -> u64 {
-    789_789_789_u64
-}
\ No newline at end of file
@@ -1,15 +1,18 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint256};
+use cosmwasm_std::{to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response, StdError, StdResult, Uint256};
 use cw2::set_contract_version;
-use evm::H160;
+use evm::{backend::Log, H160, H256};
 
 use crate::airdrop::airdrop_write_balance;
+use crate::config::{Config, CONTRACT_CONFIG, DEFAULT_CHAIN_ID, DEFAULT_MAX_CHUNK_SIZE, DEFAULT_MAX_CHUNKED_TX_SIZE};
 use crate::error::ContractError;
-use crate::message::{execute_simple_transaction, store_transaction_chunk, execute_chunked_transaction, raw_ethereum_query, EvmAccountResponse};
-use crate::message::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::storage::backend::ACCOUNTS;
-use crate::utils::{parse_h160, parse_hex};
+use crate::operator::{self, ADMIN};
+use crate::message::{execute_simple_transaction, execute_signed_transaction, store_transaction_chunk, execute_chunked_transaction, cancel_chunked_transaction, execute_iterative_transaction, continue_transaction, cancel_pending_transaction, raw_ethereum_query, eth_call, manage_operators, manage_wasm_execute_targets, deposit, withdraw, cw20_bridge, wasm_call_reply, prune_storage, migrate_legacy_storage, migrate as migrate_contract, EvmAccountResponse, OperatorsResponse, WasmExecuteTargetsResponse, EvmLogResponse, TxLogsResponse, TransactionReceiptResponse, Cw20FacadeResponse, TxChunkResponse, TxChunkStatusResponse};
+use crate::message::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::storage::backend::{ACCOUNTS, CW20_FACADES, RECEIPTS, TX_LOGS, WASM_EXECUTE_TARGETS};
+use crate::tx_chunk;
+use crate::utils::{keccak256_h256, parse_h160, parse_hex};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:terranova";
@@ -22,6 +25,26 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    CONTRACT_CONFIG.save(deps.storage, &Config {
+        chain_id: msg.chain_id.unwrap_or(DEFAULT_CHAIN_ID),
+        denom: msg.denom,
+        fee_collector: H160::from_slice(&msg.fee_collector),
+        persist_logs: msg.persist_logs,
+        hardfork: msg.hardfork,
+        max_chunk_size: msg.max_chunk_size.unwrap_or(DEFAULT_MAX_CHUNK_SIZE),
+        max_chunked_tx_size: msg.max_chunked_tx_size.unwrap_or(DEFAULT_MAX_CHUNKED_TX_SIZE),
+    })?;
+
+    let admin = match msg.admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => info.sender,
+    };
+    ADMIN.save(deps.storage, &admin)?;
+
+    for operator in msg.operators {
+        operator::add_operator(deps.storage, &deps.api.addr_validate(&operator)?)?;
+    }
+
     let addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
 
     airdrop_write_balance(deps, env, addr);
@@ -36,23 +59,90 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    // Only trusted operators can make calls
-    // TODO: First check that the message sender is an operator
-
+    // Only trusted operators can submit EVM transactions, the operators are managed by the admin
     match msg {
         ExecuteMsg::ExecuteRawEthereumTx { caller_evm_address, unsigned_tx } => {
+            operator::assert_operator(deps.as_ref(), &info.sender)?;
             execute_simple_transaction::process(deps, env, caller_evm_address, unsigned_tx)
         }
+        ExecuteMsg::ExecuteSignedEthereumTx { signed_tx } => {
+            operator::assert_operator(deps.as_ref(), &info.sender)?;
+            execute_signed_transaction::process(deps, env, signed_tx)
+        }
         ExecuteMsg::StoreTxChunk { caller_evm_address, full_tx_hash, chunk_index, chunk_data } => {
-            store_transaction_chunk::process(deps, caller_evm_address, full_tx_hash, chunk_index, chunk_data)
+            operator::assert_operator(deps.as_ref(), &info.sender)?;
+            store_transaction_chunk::process(deps, env, caller_evm_address, full_tx_hash, chunk_index, chunk_data)
         }
         ExecuteMsg::ExecuteChunkedEthereumTx { caller_evm_address, full_tx_hash, chunk_count } => {
+            operator::assert_operator(deps.as_ref(), &info.sender)?;
             execute_chunked_transaction::process(deps, env, caller_evm_address, full_tx_hash, chunk_count)
         }
+        ExecuteMsg::CancelChunkedTx { caller_evm_address, full_tx_hash } => {
+            cancel_chunked_transaction::process(deps, env, info, caller_evm_address, full_tx_hash)
+        }
+        ExecuteMsg::ExecuteIterativeEthereumTx { caller_evm_address, unsigned_tx, max_steps } => {
+            operator::assert_operator(deps.as_ref(), &info.sender)?;
+            execute_iterative_transaction::process(deps, env, caller_evm_address, unsigned_tx, max_steps)
+        }
+        ExecuteMsg::ContinueTx { tx_hash, max_steps } => {
+            operator::assert_operator(deps.as_ref(), &info.sender)?;
+            continue_transaction::process(deps, env, tx_hash, max_steps)
+        }
+        ExecuteMsg::CancelPendingTx { tx_hash } => {
+            cancel_pending_transaction::process(deps, env, info, tx_hash)
+        }
+        ExecuteMsg::Deposit { evm_address } => {
+            deposit::process(deps, env, info, evm_address)
+        }
+        ExecuteMsg::Withdraw { caller_evm_address, recipient, amount } => {
+            operator::assert_operator(deps.as_ref(), &info.sender)?;
+            withdraw::process(deps, env, caller_evm_address, recipient, amount)
+        }
+        ExecuteMsg::AddOperator { address } => {
+            manage_operators::add_operator(deps, info, address)
+        }
+        ExecuteMsg::RemoveOperator { address } => {
+            manage_operators::remove_operator(deps, info, address)
+        }
+        ExecuteMsg::UpdateAdmin { admin } => {
+            manage_operators::update_admin(deps, info, admin)
+        }
+        ExecuteMsg::RegisterCw20 { cw20_address } => {
+            cw20_bridge::register(deps, env, info, cw20_address)
+        }
+        ExecuteMsg::AllowWasmExecuteTarget { contract } => {
+            manage_wasm_execute_targets::allow(deps, env, info, contract)
+        }
+        ExecuteMsg::DisallowWasmExecuteTarget { contract } => {
+            manage_wasm_execute_targets::disallow(deps, info, contract)
+        }
+        ExecuteMsg::Receive(receive_msg) => {
+            cw20_bridge::receive(deps, env, info, receive_msg)
+        }
+        ExecuteMsg::PruneStorage { limit } => {
+            operator::assert_operator(deps.as_ref(), &info.sender)?;
+            prune_storage::process(deps, env, limit)
+        }
+        ExecuteMsg::MigrateLegacyStorage { limit } => {
+            operator::assert_operator(deps.as_ref(), &info.sender)?;
+            migrate_legacy_storage::process(deps, env, limit)
+        }
         _ => panic!("Not implemented")
     }
 }
 
+/// Only the CosmWasm messages sent by EVM contracts are sent as submessages with a reply
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    wasm_call_reply::process(deps, env, msg)
+}
+
+/// Only the admin of the contract on the chain can migrate it, the code is replaced before this is called
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    migrate_contract::process(deps, env, msg)
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
@@ -66,6 +156,41 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractErro
                 &query_account(deps, evm_address)?
             ).map_err(|e| e.into())
         }
+        QueryMsg::QueryTxLogs { tx_hash, address, topics } => {
+            to_binary(
+                &query_tx_logs(deps, tx_hash, address, topics)?
+            ).map_err(|e| e.into())
+        }
+        QueryMsg::QueryOperators {} => {
+            to_binary(
+                &query_operators(deps)?
+            ).map_err(|e| e.into())
+        }
+        QueryMsg::QueryWasmExecuteTargets {} => {
+            to_binary(
+                &query_wasm_execute_targets(deps)?
+            ).map_err(|e| e.into())
+        }
+        QueryMsg::GetTransactionReceipt { tx_hash } => {
+            to_binary(
+                &query_transaction_receipt(deps, tx_hash)?
+            ).map_err(|e| e.into())
+        }
+        QueryMsg::QueryCw20Facade { cw20_address } => {
+            to_binary(
+                &query_cw20_facade(deps, cw20_address)?
+            ).map_err(|e| e.into())
+        }
+        QueryMsg::TxChunkStatus { caller_evm_address, full_tx_hash } => {
+            to_binary(
+                &query_tx_chunk_status(deps, caller_evm_address, full_tx_hash)?
+            ).map_err(|e| e.into())
+        }
+        QueryMsg::EthCall(call_msg) => {
+            to_binary(
+                &eth_call::process(deps, env, call_msg)?
+            ).map_err(|e| e.into())
+        }
         _ => Ok(to_binary(&0_i32)?)
     }
 }
@@ -79,3 +204,104 @@ fn query_account(deps: Deps, address_bytes: [u8; 20]) -> Result<EvmAccountRespon
         nonce: acc.trx_count
     }).map_err(|e| e.into())
 }
+
+/// Logs of a transaction that emitted none, or that weren't saved, are returned as an empty list
+fn query_tx_logs(deps: Deps, tx_hash: [u8; 32], address: Option<[u8; 20]>, topics: Vec<Option<[u8; 32]>>) -> Result<TxLogsResponse, ContractError> {
+    let address = address.map(|bytes| H160::from_slice(&bytes));
+    let topics: Vec<Option<H256>> = topics.iter().map(|topic| topic.map(|bytes| H256::from_slice(&bytes))).collect();
+
+    let logs = TX_LOGS.may_load(deps.storage, &tx_hash)?.unwrap_or_default()
+        .into_iter()
+        .filter(|log| address.map_or(true, |address| log.address == address))
+        .filter(|log| {
+            log.topics.len() >= topics.len()
+                && topics.iter().zip(&log.topics).all(|(filter, topic)| filter.map_or(true, |filter| filter == *topic))
+        })
+        .map(log_response)
+        .collect();
+
+    Ok(TxLogsResponse { logs })
+}
+
+fn query_operators(deps: Deps) -> Result<OperatorsResponse, ContractError> {
+    Ok(OperatorsResponse {
+        admin: ADMIN.load(deps.storage)?.into_string(),
+        operators: operator::operators(deps.storage)?.into_iter().map(Addr::into_string).collect(),
+    })
+}
+
+fn query_wasm_execute_targets(deps: Deps) -> Result<WasmExecuteTargetsResponse, ContractError> {
+    let contracts = WASM_EXECUTE_TARGETS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(String::from_utf8)
+        .collect::<Result<_, _>>()
+        .map_err(StdError::from)?;
+
+    Ok(WasmExecuteTargetsResponse { contracts })
+}
+
+fn query_transaction_receipt(deps: Deps, tx_hash: [u8; 32]) -> Result<TransactionReceiptResponse, ContractError> {
+    RECEIPTS.load(
+        deps.storage,
+        &tx_hash
+    ).map(|receipt| TransactionReceiptResponse {
+        tx_hash: receipt.tx_hash.to_fixed_bytes(),
+        block_height: receipt.block_height,
+        from: receipt.from.to_fixed_bytes(),
+        to: receipt.to.map(|to| to.to_fixed_bytes()),
+        status: receipt.status,
+        gas_used: Uint256::from_be_bytes(receipt.gas_used.to_bytes()),
+        cumulative_gas_used: Uint256::from_be_bytes(receipt.cumulative_gas_used.to_bytes()),
+        logs: receipt.logs.into_iter().map(log_response).collect(),
+        logs_bloom: receipt.logs_bloom,
+        contract_address: receipt.contract_address.map(|address| address.to_fixed_bytes()),
+        revert_data: receipt.revert_data,
+    }).map_err(|e| e.into())
+}
+
+fn query_cw20_facade(deps: Deps, cw20_address: String) -> Result<Cw20FacadeResponse, ContractError> {
+    CW20_FACADES.load(
+        deps.storage,
+        &deps.api.addr_validate(&cw20_address)?
+    ).map(|facade| Cw20FacadeResponse {
+        evm_address: facade.to_fixed_bytes(),
+    }).map_err(|e| e.into())
+}
+
+fn query_tx_chunk_status(deps: Deps, caller_address_bytes: [u8; 20], full_tx_hash: [u8; 32]) -> Result<TxChunkStatusResponse, ContractError> {
+    let caller_address = H160::from_slice(&caller_address_bytes);
+    let tx_hash = H256::from_slice(&full_tx_hash);
+
+    let stored_chunks = tx_chunk::stored_chunks(deps.storage, caller_address, tx_hash)?;
+    let chunked_tx = tx_chunk::chunked_tx(deps.storage, caller_address, tx_hash)?;
+
+    let chunks = stored_chunks.iter()
+        .map(|(chunk_index, chunk)| TxChunkResponse {
+            chunk_index: *chunk_index,
+            length: chunk.len() as u32,
+        })
+        .collect();
+
+    // Chunks after a gap can't be part of the hash yet, the transaction is hashed in order
+    let contiguous: Vec<Vec<u8>> = stored_chunks.into_iter()
+        .enumerate()
+        .take_while(|(position, (chunk_index, _))| *position == *chunk_index as usize)
+        .map(|(_, (_, chunk))| chunk)
+        .collect();
+
+    Ok(TxChunkStatusResponse {
+        chunks,
+        total_size: chunked_tx.as_ref().map_or(0, |chunked_tx| chunked_tx.total_size),
+        contiguous_chunks: contiguous.len() as u32,
+        running_hash: keccak256_h256(&contiguous.concat()).to_fixed_bytes(),
+        expiry_height: chunked_tx.map(|chunked_tx| chunked_tx.expiry_height),
+    })
+}
+
+fn log_response(log: Log) -> EvmLogResponse {
+    EvmLogResponse {
+        address: log.address.to_fixed_bytes(),
+        topics: log.topics.iter().map(|topic| topic.to_fixed_bytes()).collect(),
+        data: log.data,
+    }
+}
@@ -0,0 +1,216 @@
+//! ERC-20 facades of CW20 tokens, so that EVM contracts can hold and trade existing Terra CW20 tokens.
+//!
+//! Each registered CW20 token gets a facade account in the EVM, at an address derived from the CW20 contract address.
+//! Calls to a facade are handled natively like a precompile, the facade only has placeholder code so that it looks like a contract.
+//! The CW20 tokens are held by the Terranova contract, the balances of EVM accounts are tracked in CW20_BALANCES:
+//! tokens enter the EVM with a CW20 Send to the Terranova contract, and leave it through `transferToTerra`,
+//! which is flushed as a CW20 Transfer message once the transaction state is applied.
+
+use cosmwasm_std::{Addr, Uint128};
+use evm::{ExitError, H160, H256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::executor_state::{Cw20Recipient, ExecutorState};
+use crate::precompiles::{charge, invalid_input, read_bytes, read_word, PrecompileOutput, PrecompileResult};
+use crate::storage::StorageInterface;
+use crate::utils::keccak256_h256_v;
+
+const NAME: [u8; 4] = [0x06, 0xfd, 0xde, 0x03];
+const SYMBOL: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41];
+const DECIMALS: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+const TOTAL_SUPPLY: [u8; 4] = [0x18, 0x16, 0x0d, 0xdd];
+const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+const TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+const TRANSFER_FROM: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+const APPROVE: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+const ALLOWANCE: [u8; 4] = [0xdd, 0x62, 0xed, 0x3e];
+/// transferToTerra(string recipient, uint256 amount), not part of ERC-20
+const TRANSFER_TO_TERRA: [u8; 4] = [0x32, 0x7d, 0x37, 0x70];
+
+/// event Transfer(address indexed from, address indexed to, uint256 value)
+const TRANSFER_EVENT: [u8; 32] = [
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+];
+/// event Approval(address indexed owner, address indexed spender, uint256 value)
+const APPROVAL_EVENT: [u8; 32] = [
+    0x8c, 0x5b, 0xe1, 0xe5, 0xeb, 0xec, 0x7d, 0x5b, 0xd1, 0x4f, 0x71, 0x42, 0x7d, 0x1e, 0x84, 0xf3,
+    0xdd, 0x03, 0x14, 0xc0, 0xf7, 0xb2, 0x29, 0x1e, 0x5b, 0x20, 0x0a, 0xc8, 0xc7, 0xc3, 0xb9, 0x25,
+];
+
+const G_READ: u64 = 800;
+const G_APPROVE: u64 = 25_000;
+const G_TRANSFER: u64 = 30_000;
+const G_TRANSFER_TO_TERRA: u64 = 40_000;
+
+/// Code deployed to every facade, a single INVALID opcode.\
+/// It's never executed, calls are intercepted by the executor, but it gives the facade a non zero EXTCODESIZE
+pub const FACADE_CODE: [u8; 1] = [0xfe];
+
+/// A CW20 token registered by the admin, its metadata is read from the CW20 contract once at registration
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Cw20Token {
+    pub contract: Addr,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// EVM address of the ERC-20 facade of a CW20 contract, the last 20 bytes of keccak256("cw20" ++ contract address)
+#[must_use]
+pub fn facade_address(contract: &Addr) -> H160 {
+    keccak256_h256_v(&[b"cw20", contract.as_bytes()]).into()
+}
+
+/// Runs a call to the ERC-20 facade at `facade` of the CW20 `token`, in the call frame entered for it.\
+/// Amounts are in the smallest unit of the CW20 token and can't exceed u128.
+/// Value can't be sent along, and DELEGATECALL and CALLCODE are rejected, like for the Terranova precompiles.
+/// Failed transfers consume all the gas given to the call instead of reverting
+pub fn call<B: StorageInterface>(
+    facade: H160,
+    token: &Cw20Token,
+    context: &evm::Context,
+    input: &[u8],
+    gas_limit: u64,
+    state: &mut ExecutorState<B>,
+) -> PrecompileResult {
+    if context.address != facade || !context.apparent_value.is_zero() || input.len() < 4 {
+        return Err(invalid_input());
+    }
+
+    let (selector, args) = input.split_at(4);
+    let selector: [u8; 4] = [selector[0], selector[1], selector[2], selector[3]];
+    let caller = context.caller;
+
+    let (output, cost) = match selector {
+        NAME => (encode_string(&token.name), G_READ),
+        SYMBOL => (encode_string(&token.symbol), G_READ),
+        DECIMALS => (encode_u256(U256::from(token.decimals)), G_READ),
+        TOTAL_SUPPLY => (encode_amount(state.cw20_supply(facade)), G_READ),
+        BALANCE_OF => {
+            let owner = read_address(args, 0)?;
+            (encode_amount(state.cw20_balance(facade, owner)), G_READ)
+        }
+        ALLOWANCE => {
+            let owner = read_address(args, 0)?;
+            let spender = read_address(args, 1)?;
+            (encode_amount(state.cw20_allowance(facade, owner, spender)), G_READ)
+        }
+        TRANSFER => {
+            charge_write(G_TRANSFER, gas_limit, state)?;
+            let target = read_address(args, 0)?;
+            let amount = read_amount(args, 1)?;
+
+            state.cw20_transfer(facade, caller, Cw20Recipient::Evm(target), amount)?;
+            log_transfer(state, facade, caller, target, amount);
+
+            (encode_u256(U256::one()), G_TRANSFER)
+        }
+        TRANSFER_FROM => {
+            charge_write(G_TRANSFER, gas_limit, state)?;
+            let source = read_address(args, 0)?;
+            let target = read_address(args, 1)?;
+            let amount = read_amount(args, 2)?;
+
+            let allowance = state.cw20_allowance(facade, source, caller)
+                .checked_sub(amount)
+                .map_err(|_| ExitError::OutOfFund)?;
+            state.cw20_approve(facade, source, caller, allowance);
+            state.cw20_transfer(facade, source, Cw20Recipient::Evm(target), amount)?;
+            log_transfer(state, facade, source, target, amount);
+
+            (encode_u256(U256::one()), G_TRANSFER)
+        }
+        APPROVE => {
+            charge_write(G_APPROVE, gas_limit, state)?;
+            let spender = read_address(args, 0)?;
+            let amount = read_amount(args, 1)?;
+
+            state.cw20_approve(facade, caller, spender, amount);
+            state.log(facade, vec![H256(APPROVAL_EVENT), H256::from(caller), H256::from(spender)], encode_amount(amount));
+
+            (encode_u256(U256::one()), G_APPROVE)
+        }
+        TRANSFER_TO_TERRA => {
+            charge_write(G_TRANSFER_TO_TERRA, gas_limit, state)?;
+            let recipient = read_string(args, 0)?;
+            let recipient = state.api().addr_validate(&recipient).map_err(|_| invalid_input())?;
+            let amount = read_amount(args, 1)?;
+
+            // A CW20 transfer of zero tokens fails, which would fail the whole transaction once applied
+            if amount.is_zero() {
+                return Err(invalid_input());
+            }
+
+            state.cw20_transfer(facade, caller, Cw20Recipient::Terra(recipient), amount)?;
+            log_transfer(state, facade, caller, H160::zero(), amount);
+
+            (encode_u256(U256::one()), G_TRANSFER_TO_TERRA)
+        }
+        _ => return Err(invalid_input()),
+    };
+
+    charge(cost, gas_limit)?;
+
+    Ok(PrecompileOutput { output, cost })
+}
+
+/// State changing functions are charged before any work, and can't be called in a static context
+fn charge_write<B: StorageInterface>(cost: u64, gas_limit: u64, state: &ExecutorState<B>) -> Result<(), ExitError> {
+    charge(cost, gas_limit)?;
+
+    if state.metadata().is_static() {
+        return Err(ExitError::StaticModeViolation);
+    }
+
+    Ok(())
+}
+
+fn log_transfer<B: StorageInterface>(state: &mut ExecutorState<B>, facade: H160, source: H160, target: H160, amount: Uint128) {
+    state.log(facade, vec![H256(TRANSFER_EVENT), H256::from(source), H256::from(target)], encode_amount(amount));
+}
+
+fn read_address(args: &[u8], index: usize) -> Result<H160, ExitError> {
+    let word = read_word(args, index)?;
+    if word[..12].iter().any(|byte| *byte != 0) {
+        return Err(invalid_input());
+    }
+
+    Ok(H160::from_slice(&word[12..]))
+}
+
+fn read_amount(args: &[u8], index: usize) -> Result<Uint128, ExitError> {
+    let amount = U256::from_big_endian(read_word(args, index)?);
+    if amount > U256::from(u128::MAX) {
+        return Err(invalid_input());
+    }
+
+    Ok(Uint128::from(amount.as_u128()))
+}
+
+fn read_string(args: &[u8], index: usize) -> Result<String, ExitError> {
+    String::from_utf8(read_bytes(args, index)?.to_vec()).map_err(|_| invalid_input())
+}
+
+fn encode_u256(value: U256) -> Vec<u8> {
+    let mut output = vec![0_u8; 32];
+    value.to_big_endian(&mut output);
+
+    output
+}
+
+fn encode_amount(amount: Uint128) -> Vec<u8> {
+    encode_u256(U256::from(amount.u128()))
+}
+
+/// ABI encoding of a single string return value: its offset, its length and its bytes right padded to a word
+fn encode_string(value: &str) -> Vec<u8> {
+    let padded_len = (value.len() + 31) / 32 * 32;
+
+    let mut output = encode_u256(U256::from(32));
+    output.extend(encode_u256(U256::from(value.len())));
+    output.extend(value.as_bytes());
+    output.resize(64 + padded_len, 0);
+
+    output
+}
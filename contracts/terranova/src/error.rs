@@ -15,20 +15,89 @@ pub enum ContractError {
     #[error("The unsigned transaction is invalid")]
     InvalidTransactionData,
 
+    #[error("The transaction signature is invalid")]
+    InvalidSignature,
+
+    #[error("The transaction chain ID does not match the chain ID of the Terranova EVM")]
+    InvalidChainId,
+
     #[error("An account balance overflowed")]
     BalanceOverflow,
 
     #[error("An account nonce overflowed")]
     NonceOverflow,
 
+    #[error("The transaction nonce is lower than the nonce of the caller account, it has already been executed")]
+    NonceTooLow,
+
+    #[error("The transaction nonce is higher than the nonce of the caller account")]
+    NonceTooHigh,
+
     #[error("The collected chunks of the transaction do not match the provided hash")]
     InvalidTxChunks,
 
     #[error("One or more of the chunks for this transaction are still missing")]
     TxChunksMissing,
 
+    #[error("A chunk is already stored at this index for this transaction")]
+    ChunkAlreadyStored,
+
+    #[error("The transaction chunk exceeds the maximum chunk size")]
+    ChunkTooLarge,
+
+    #[error("The chunks of the transaction exceed the maximum transaction size")]
+    ChunkedTxTooLarge,
+
+    #[error("No chunks are stored for this transaction")]
+    UnknownChunkedTx,
+
+    #[error("The chunks of the transaction can only be purged by an operator before they expire")]
+    ChunksNotExpired,
+
+    #[error("The chunks of the transaction have expired, they can only be purged")]
+    ChunksExpired,
+
+    #[error("The transaction gas limit does not cover the intrinsic gas of the transaction")]
+    GasLimitTooLow,
+
+    #[error("The gas fee of the transaction exceeds the fee prepaid for its gas limit")]
+    FeeExceedsPrepaid,
+
     #[error("The provided query is invalid because it tried to incur a state change")]
     QueryChangedState,
+
+    #[error("The message sender is not authorized to perform this action")]
+    Unauthorized,
+
+    #[error("A deposit must send a positive amount of the configured native denom, and no other coins")]
+    InvalidFunds,
+
+    #[error("The withdrawal exceeds the native coins deposited into the Terranova EVM")]
+    InsufficientSupply,
+
+    #[error("The CW20 token is already registered with the Terranova EVM")]
+    Cw20AlreadyRegistered,
+
+    #[error("The CW20 token is not registered with the Terranova EVM")]
+    UnknownCw20Token,
+
+    #[error("EVM contracts can't send messages to the Terranova contract or to registered CW20 tokens")]
+    WasmExecuteTargetNotAllowed,
+
+    #[error("The Terranova EVM can only be upgraded to a later hardfork")]
+    HardforkDowngrade,
+
+    #[error("The transaction accesses an account locked by another transaction in progress")]
+    AccountLocked,
+
+    #[error("The transaction is already in progress, continue it with ContinueTx")]
+    TxAlreadyPending,
+
+    #[error("No transaction in progress has this hash")]
+    UnknownPendingTx,
+
+    #[error("The transaction in progress was continued too recently to be cancelled")]
+    PendingTxNotExpired,
 }
 
 macro_rules! Err {
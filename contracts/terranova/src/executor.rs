@@ -1,14 +1,17 @@
-use std::convert::Infallible;
+use std::{collections::BTreeSet, convert::Infallible};
 
-use cosmwasm_std::Response;
-use evm::{Capture, ExitError, ExitFatal, ExitReason, Handler, Valids, H160, H256, U256};
-use evm_runtime::{save_created_address, save_return_value, Control, CONFIG};
+use cosmwasm_std::{Response, StdError};
+use evm::{Capture, ExitError, ExitFatal, ExitReason, ExitSucceed, Handler, Valids, H160, H256, U256};
+use evm_runtime::{save_created_address, save_return_value, Control};
 use serde::{Deserialize, Serialize};
 
 use crate::{emit_exit, event};
 use crate::{
+    erc20,
     executor_state::{ExecutorState, ExecutorSubstate},
     gasometer::Gasometer,
+    hardfork::{HardforkConfig, BASEFEE, PUSH0},
+    precompiles,
     storage::StorageInterface,
     utils::{keccak256_h256, keccak256_h256_v},
     ContractError,
@@ -25,6 +28,7 @@ struct CallInterrupt {
     code_address: H160,
     input: Vec<u8>,
     is_static: bool,
+    gas_limit: u64,
 }
 
 #[derive(Debug)]
@@ -33,6 +37,7 @@ struct CreateInterrupt {
     transfer: Option<evm::Transfer>,
     address: H160,
     init_code: Vec<u8>,
+    gas_limit: u64,
 }
 
 #[derive(Debug)]
@@ -44,12 +49,89 @@ enum RuntimeApply {
 }
 
 struct Executor<'a, B: StorageInterface> {
+    config: &'static HardforkConfig,
     origin: H160,
+    gas_price: U256,
     state: ExecutorState<'a, B>,
     gasometer: Gasometer,
 }
 
 impl<'a, B: StorageInterface> Executor<'a, B> {
+    /// Precompiles run natively and exit immediately, so their call frame is entered and exited here
+    /// instead of through a CallInterrupt
+    fn apply_precompile(
+        &mut self,
+        precompile: impl FnOnce(&mut ExecutorState<'a, B>) -> precompiles::PrecompileResult,
+        transfer: Option<&evm::Transfer>,
+        gas_limit: u64,
+        is_static: bool,
+    ) -> (ExitReason, Vec<u8>) {
+        self.state.enter(is_static);
+        self.gasometer.enter(gas_limit);
+
+        let result = transfer
+            .map_or(Ok(()), |transfer| self.state.transfer(transfer))
+            .and_then(|()| precompile(&mut self.state))
+            .and_then(|precompile| {
+                self.gasometer.record_cost(precompile.cost)?;
+                Ok(precompile.output)
+            });
+
+        let (reason, output) = match result {
+            Ok(output) => (ExitSucceed::Returned.into(), output),
+            Err(e) => (e.into(), Vec::new()),
+        };
+
+        self.gasometer.exit(&reason);
+
+        let exit = if reason.is_succeed() {
+            self.state.exit_commit()
+        } else {
+            self.state.exit_discard()
+        };
+
+        match exit {
+            Ok(()) => (reason, output),
+            Err(e) => (e.into(), Vec::new()),
+        }
+    }
+
+    /// Precompiles and ERC-20 facades run natively instead of EVM code
+    fn is_native(&self, address: H160) -> bool {
+        precompiles::is_precompile_address(&address) || self.state.cw20_token(address).is_some()
+    }
+
+    /// Runs the precompile or ERC-20 facade at `code_address`, returns `None` if the address has EVM code to run instead
+    fn call_native(
+        &mut self,
+        code_address: H160,
+        transfer: Option<&evm::Transfer>,
+        input: &[u8],
+        gas_limit: u64,
+        is_static: bool,
+        context: &evm::Context,
+    ) -> Option<(ExitReason, Vec<u8>)> {
+        if let Some(result) = precompiles::call_precompile(code_address, input, gas_limit, self.state.api(), self.config) {
+            return Some(self.apply_precompile(|_| result, transfer, gas_limit, is_static));
+        }
+
+        if precompiles::is_terranova_precompile_address(&code_address) {
+            let precompile = |state: &mut ExecutorState<'a, B>| {
+                precompiles::call_terranova_precompile(code_address, context, input, gas_limit, state)
+            };
+            return Some(self.apply_precompile(precompile, transfer, gas_limit, is_static));
+        }
+
+        if let Some(token) = self.state.cw20_token(code_address) {
+            let precompile = |state: &mut ExecutorState<'a, B>| {
+                erc20::call(code_address, &token, context, input, gas_limit, state)
+            };
+            return Some(self.apply_precompile(precompile, transfer, gas_limit, is_static));
+        }
+
+        None
+    }
+
     fn create_address(&self, scheme: evm::CreateScheme) -> H160 {
         match scheme {
             evm::CreateScheme::Create2 {
@@ -69,7 +151,8 @@ impl<'a, B: StorageInterface> Executor<'a, B> {
     }
 }
 
-/// TODO: Precompile addresses
+/// Calls and creates are interrupted to run in a new frame of the Machine,
+/// except calls to precompiles and ERC-20 facades which run natively, see `call_native`
 impl<'a, B: StorageInterface> Handler for Executor<'a, B> {
     type CreateInterrupt = crate::executor::CreateInterrupt;
     type CreateFeedback = Infallible;
@@ -115,11 +198,11 @@ impl<'a, B: StorageInterface> Handler for Executor<'a, B> {
     }
 
     fn gas_left(&self) -> U256 {
-        U256::one() // TODO
+        U256::from(self.gasometer.gas_left())
     }
 
     fn gas_price(&self) -> U256 {
-        U256::zero() // TODO
+        self.gas_price
     }
 
     fn origin(&self) -> H160 {
@@ -154,13 +237,12 @@ impl<'a, B: StorageInterface> Handler for Executor<'a, B> {
         self.state.chain_id()
     }
 
-    /// TODO: Go back to this when precompile contract addresses are implemented,
     fn exists(&self, address: H160) -> bool {
-        // if is_precompile_address(&address) {
-        //     return true;
-        // }
+        if precompiles::is_precompile_address(&address) {
+            return true;
+        }
 
-        if CONFIG.empty_considered_exists {
+        if self.config.empty_considered_exists {
             self.state.exists(address)
         } else {
             self.state.exists(address) && !self.state.is_empty(address)
@@ -177,7 +259,7 @@ impl<'a, B: StorageInterface> Handler for Executor<'a, B> {
         }
 
         self.gasometer
-            .record_storage_write(&self.state, address, index);
+            .record_storage_write(&self.state, address, index, value)?;
 
         self.state.set_storage(address, index, value);
         Ok(())
@@ -197,14 +279,17 @@ impl<'a, B: StorageInterface> Handler for Executor<'a, B> {
             return Err(ExitError::StaticModeViolation);
         }
 
-        let balance = self.balance(address);
-        let transfer = evm::Transfer {
-            source: address,
-            target,
-            value: balance,
-        };
+        // A contract destroying itself in favor of its own address burns its balance
+        if target != address {
+            let balance = self.balance(address);
+            let transfer = evm::Transfer {
+                source: address,
+                target,
+                value: balance,
+            };
 
-        self.state.transfer(&transfer)?;
+            self.state.transfer(&transfer)?;
+        }
         self.state.reset_balance(address);
         self.state.set_deleted(address);
 
@@ -226,7 +311,7 @@ impl<'a, B: StorageInterface> Handler for Executor<'a, B> {
         }
 
         if let Some(depth) = self.state.metadata().depth() {
-            if depth + 1 > CONFIG.call_stack_limit {
+            if depth + 1 > self.config.call_stack_limit {
                 return Capture::Exit((ExitError::CallTooDeep.into(), None, Vec::new()));
             }
         }
@@ -235,8 +320,20 @@ impl<'a, B: StorageInterface> Handler for Executor<'a, B> {
             return Capture::Exit((ExitError::OutOfFund.into(), None, Vec::new()));
         }
 
+        // Nested creations are given all but one 64th of the gas left in the creating frame,
+        // the outermost creation is given the transaction gas limit in create_begin
+        let gas_limit = if self.state.metadata().depth().is_some() {
+            match self.gasometer.record_create() {
+                Ok(gas_limit) => gas_limit,
+                Err(e) => return Capture::Exit((e.into(), None, Vec::new())),
+            }
+        } else {
+            0
+        };
+
         // Get the create address from given scheme.
         let address = self.create_address(scheme);
+        self.gasometer.access_address(address);
         debug_print!("Created contract address: {}", address);
         event!(Create {
             caller,
@@ -277,10 +374,10 @@ impl<'a, B: StorageInterface> Handler for Executor<'a, B> {
             transfer,
             address,
             init_code,
+            gas_limit,
         })
     }
 
-    /// TODO: Go back to this when precompile addresses are implemented
     fn call(
         &mut self,
         code_address: H160,
@@ -305,34 +402,53 @@ impl<'a, B: StorageInterface> Handler for Executor<'a, B> {
             return Capture::Exit((ExitError::StaticModeViolation.into(), Vec::new()));
         }
 
-        // TODO: Come back here once precompile addresses are implemented
-        // let precompile_result = call_precompile(code_address, &input, &context, &mut self.state, &mut self.gasometer);
-        // if let Some(Capture::Exit(exit_value)) = precompile_result {
-        //     return Capture::Exit(exit_value);
-        // }
-
         if let Some(depth) = self.state.metadata().depth() {
-            if depth + 1 > CONFIG.call_stack_limit {
+            if depth + 1 > self.config.call_stack_limit {
                 return Capture::Exit((ExitError::CallTooDeep.into(), Vec::new()));
             }
         }
 
+        let transfers_value = transfer.as_ref().map_or(false, |transfer| !transfer.value.is_zero());
+        if transfers_value && transfer.as_ref().map_or(false, |transfer| self.balance(transfer.source) < transfer.value) {
+            return Capture::Exit((ExitError::OutOfFund.into(), Vec::new()));
+        }
+
+        let gas_limit = match self.gasometer.record_call(target_gas, transfers_value) {
+            Ok(gas_limit) => gas_limit,
+            Err(e) => return Capture::Exit((e.into(), Vec::new())),
+        };
+
+        if let Some(exit) = self.call_native(code_address, transfer.as_ref(), &input, gas_limit, is_static, &context) {
+            return Capture::Exit(exit);
+        }
+
         Capture::Trap(CallInterrupt {
             context,
             transfer,
             code_address,
             input,
             is_static,
+            gas_limit,
         })
     }
 
     fn pre_validate(
         &mut self,
-        _context: &evm::Context,
-        _opcode: evm::Opcode,
-        _stack: &evm::Stack,
+        context: &evm::Context,
+        opcode: evm::Opcode,
+        stack: &evm::Stack,
     ) -> Result<(), ExitError> {
-        Ok(())
+        self.gasometer.record_opcode(&self.state, context, opcode, stack)
+    }
+
+    fn other(&mut self, opcode: evm::Opcode, machine: &mut evm::Machine) -> Result<(), ExitError> {
+        match opcode {
+            PUSH0 if self.config.has_push0 => machine.stack_mut().push(H256::default()),
+            // Terranova has no base fee, see UnsignedTransaction::effective_gas_price
+            BASEFEE if self.config.has_base_fee => machine.stack_mut().push(H256::default()),
+            // Undefined opcodes fail like in the default Handler implementation
+            _ => Err(ExitError::OutOfGas),
+        }
     }
 }
 
@@ -348,22 +464,39 @@ pub enum CreateReason {
 
 type RuntimeInfo = (evm::Runtime, CreateReason);
 
+/// State of a Machine between the messages of a transaction executed over several messages
+#[derive(Serialize, Deserialize)]
+struct SavedMachine {
+    origin: H160,
+    gas_price: U256,
+    substate: Box<ExecutorSubstate>,
+    gasometer: Gasometer,
+    runtime: Vec<RuntimeInfo>,
+    steps_executed: u64,
+    native_exit: Option<(Vec<u8>, ExitReason)>,
+}
+
 /// Represents a virtual machine.
 pub struct Machine<'a, B: StorageInterface> {
     executor: Executor<'a, B>,
     runtime: Vec<RuntimeInfo>,
     steps_executed: u64,
+    /// Result of a transaction calling a precompile or an ERC-20 facade, which runs entirely in call_begin
+    native_exit: Option<(Vec<u8>, ExitReason)>,
 }
 
 impl<'a, B: StorageInterface> Machine<'a, B> {
     /// Creates instance of the Machine.
-    pub fn new(origin: H160, backend: &'a B) -> Result<Self, ContractError> {
+    pub fn new(origin: H160, gas_price: U256, backend: &'a B) -> Result<Self, ContractError> {
+        let hardfork = backend.hardfork();
         let substate = Box::new(ExecutorSubstate::new(backend));
         let state = ExecutorState::new(substate, backend);
-        let gasometer = Gasometer::new();
+        let gasometer = Gasometer::new(hardfork);
 
         let executor = Executor {
+            config: hardfork.config(),
             origin,
+            gas_price,
             state,
             gasometer,
         };
@@ -371,29 +504,52 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
             executor,
             runtime: Vec::new(),
             steps_executed: 0,
+            native_exit: None,
         })
     }
 
-    // /// Serializes and saves state of runtime and executor into a storage account.
-    // ///
-    // /// # Panics
-    // ///
-    // /// Panics if account is invalid or any serialization error occurs.
-    // pub fn save_into(&self, storage: &mut crate::account::Storage) {
-    //     storage.serialize(&self.runtime, self.executor.state.substate()).unwrap();
-    // }
+    /// Serializes the runtime stack, the substate and the gas accounting, so that execution can continue
+    /// in a later message with `restore`. The hardfork of the transaction is kept with the gasometer
+    ///
+    /// # Errors
+    ///
+    /// Returns a serialization error if the state can't be encoded
+    pub fn save(self) -> Result<Vec<u8>, ContractError> {
+        let saved = SavedMachine {
+            origin: self.executor.origin,
+            gas_price: self.executor.gas_price,
+            substate: self.executor.state.into_substate(),
+            gasometer: self.executor.gasometer,
+            runtime: self.runtime,
+            steps_executed: self.steps_executed,
+            native_exit: self.native_exit,
+        };
 
-    // /// Deserializes and restores state of runtime and executor from a storage account.
-    // pub fn restore(storage: &crate::account::Storage, backend: &'a B) -> Result<Self, ProgramError> {
-    //     let (runtime, substate) = storage.deserialize()?;
-    //     let gasometer = Gasometer::new()?;
+        bincode::serialize(&saved).map_err(|e| StdError::serialize_err("SavedMachine", e).into())
+    }
 
-    //     let origin = storage.caller;
-    //     let state = ExecutorState::new(substate, backend);
+    /// Restores a Machine saved by `save` on top of the current persistent state
+    ///
+    /// # Errors
+    ///
+    /// Returns a parse error if the saved state can't be decoded
+    pub fn restore(data: &[u8], backend: &'a B) -> Result<Self, ContractError> {
+        let saved: SavedMachine = bincode::deserialize(data).map_err(|e| StdError::parse_err("SavedMachine", e))?;
 
-    //     let executor = Executor { origin, state, gasometer };
-    //     Ok(Self { executor, runtime, steps_executed: 0 })
-    // }
+        let executor = Executor {
+            config: saved.gasometer.hardfork().config(),
+            origin: saved.origin,
+            gas_price: saved.gas_price,
+            state: ExecutorState::new(saved.substate, backend),
+            gasometer: saved.gasometer,
+        };
+        Ok(Self {
+            executor,
+            runtime: saved.runtime,
+            steps_executed: saved.steps_executed,
+            native_exit: saved.native_exit,
+        })
+    }
 
     /// Begins a call of an Ethereum smart contract.
     ///
@@ -401,13 +557,14 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
     ///
     /// May return following errors:
     /// - `InsufficientFunds` if the caller lacks funds for the operation
+    /// - `GasLimitTooLow` if the gas limit does not cover the intrinsic gas of the transaction
     pub fn call_begin(
         &mut self,
         caller: H160,
         code_address: H160,
         input: Vec<u8>,
         transfer_value: U256,
-        #[allow(unused_variables)] gas_limit: U256,
+        gas_limit: U256,
     ) -> Result<Response, ContractError> {
         event!(TransactCall {
             caller,
@@ -418,14 +575,18 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
         });
         debug_print!("call_begin");
 
+        // The sender and the recipient are warm from the start of the transaction (EIP-2929), and the coinbase (EIP-3651)
+        self.executor.gasometer.access_address(caller);
+        self.executor.gasometer.access_address(code_address);
+        if self.executor.config.warm_coinbase {
+            self.executor.gasometer.access_address(self.executor.block_coinbase());
+        }
+        self.executor.gasometer.begin(gas_limit)?;
+
         self.executor.state.inc_nonce(caller);
         self.executor.state.enter(false);
         self.executor.state.touch(code_address);
 
-        self.executor
-            .gasometer
-            .record_transfer(&self.executor.state, code_address, transfer_value);
-
         let transfer = evm::Transfer {
             source: caller,
             target: code_address,
@@ -437,18 +598,39 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
             .map_err(emit_exit)
             .map_err(|e| E!(ContractError::InsufficientFunds; "ExitError={:?}", e))?;
 
-        let code = self.executor.code(code_address);
-        let valids = self.executor.valids(code_address);
         let context = evm::Context {
             address: code_address,
             caller,
             apparent_value: transfer_value,
         };
 
-        let runtime = evm::Runtime::new(code, valids, input, context);
+        if self.executor.is_native(code_address) {
+            // The value was already transferred, the native call is forwarded all the gas left in the outermost frame
+            let gas_limit = self.executor.gasometer.gas_left();
+            self.executor.gasometer.record_cost(gas_limit).map_err(|e| E!(ContractError::GasLimitTooLow; "ExitError={:?}", e))?;
+
+            let (reason, output) = self.executor.call_native(code_address, None, &input, gas_limit, false, &context)
+                .expect("address is a precompile or an ERC-20 facade");
 
-        debug_print!("Pushing call to executor runtime");
-        self.runtime.push((runtime, CreateReason::Call));
+            self.executor.gasometer.exit(&reason);
+            let exit = if reason.is_succeed() {
+                self.executor.state.exit_commit()
+            } else {
+                self.executor.state.exit_discard()
+            };
+
+            self.native_exit = Some(match exit {
+                Ok(()) => (output, reason),
+                Err(e) => (Vec::new(), e.into()),
+            });
+        } else {
+            let code = self.executor.code(code_address);
+            let valids = self.executor.valids(code_address);
+            let runtime = evm::Runtime::new(code, valids, input, context);
+
+            debug_print!("Pushing call to executor runtime");
+            self.runtime.push((runtime, CreateReason::Call));
+        }
 
         let response = Response::new()
             .add_attribute("tx_type", "call")
@@ -464,12 +646,14 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
     ///
     /// May return following errors:
     /// - `InsufficientFunds` if the caller lacks funds for the operation
+    /// - `GasLimitTooLow` if the gas limit does not cover the intrinsic gas of the transaction
+    /// - `InvalidTransactionData` if the init code exceeds the limit of the hardfork (EIP-3860)
     pub fn create_begin(
         &mut self,
         caller: H160,
         code: Vec<u8>,
         transfer_value: U256,
-        #[allow(unused_variables)] gas_limit: U256,
+        gas_limit: U256,
     ) -> Result<Response, ContractError> {
         event!(TransactCreate {
             caller,
@@ -483,7 +667,19 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
 
         debug_print!("create_begin");
 
+        if let Some(limit) = self.executor.config.create_initcode_limit {
+            if code.len() > limit {
+                return Err!(ContractError::InvalidTransactionData; "Init code size {} exceeds the limit {}", code.len(), limit);
+            }
+        }
+
         let scheme = evm::CreateScheme::Legacy { caller };
+        // The sender is warm from the start of the transaction, the created address is warmed by create (EIP-2929),
+        // and the coinbase (EIP-3651)
+        self.executor.gasometer.access_address(caller);
+        if self.executor.config.warm_coinbase {
+            self.executor.gasometer.access_address(self.executor.block_coinbase());
+        }
 
         let response: Response = match self
             .executor
@@ -494,18 +690,16 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
                 return Err!(ContractError::ContractCreationFailed; "create_begin() error={:?} ", (reason, addr, value));
             }
             Capture::Trap(info) => {
+                self.executor.gasometer.begin(gas_limit)?;
+
                 self.executor.state.enter(false);
 
                 self.executor.state.touch(info.address);
                 self.executor.state.reset_storage(info.address);
-                if CONFIG.create_increase_nonce {
+                if self.executor.config.create_increase_nonce {
                     self.executor.state.inc_nonce(info.address);
                 }
 
-                self.executor
-                    .gasometer
-                    .record_deploy(&self.executor.state, info.address);
-
                 if let Some(transfer) = info.transfer {
                     self.executor
                         .state
@@ -601,17 +795,14 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
         self.executor.state.touch(interrupt.code_address);
 
         if let Some(transfer) = interrupt.transfer {
-            self.executor.gasometer.record_transfer(
-                &self.executor.state,
-                interrupt.code_address,
-                transfer.value,
-            );
             self.executor
                 .state
                 .transfer(&transfer)
                 .map_err(|_| (Vec::new(), ExitError::OutOfFund.into()))?;
         }
 
+        self.executor.gasometer.enter(interrupt.gas_limit);
+
         let instance = evm::Runtime::new(code, valids, interrupt.input, interrupt.context);
         self.runtime.push((instance, CreateReason::Call));
 
@@ -623,14 +814,10 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
         self.executor.state.enter(false);
         self.executor.state.touch(interrupt.address);
         self.executor.state.reset_storage(interrupt.address);
-        if CONFIG.create_increase_nonce {
+        if self.executor.config.create_increase_nonce {
             self.executor.state.inc_nonce(interrupt.address);
         }
 
-        self.executor
-            .gasometer
-            .record_deploy(&self.executor.state, interrupt.address);
-
         if let Some(transfer) = interrupt.transfer {
             self.executor
                 .state
@@ -638,6 +825,8 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
                 .map_err(|_| (Vec::new(), ExitError::OutOfFund.into()))?;
         }
 
+        self.executor.gasometer.enter(interrupt.gas_limit);
+
         let valids = Valids::compute(&interrupt.init_code);
         let instance =
             evm::Runtime::new(interrupt.init_code, valids, Vec::new(), interrupt.context);
@@ -652,6 +841,8 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
         exited_runtime: &evm::Runtime,
         reason: ExitReason,
     ) -> Result<(), (Vec<u8>, ExitReason)> {
+        self.executor.gasometer.exit(&reason);
+
         if reason.is_succeed() {
             self.executor
                 .state
@@ -680,25 +871,37 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
         address: H160,
     ) -> Result<(), (Vec<u8>, ExitReason)> {
         if reason.is_succeed() {
-            match CONFIG.create_contract_limit {
-                Some(limit) if exited_runtime.machine().return_value_len() > limit => {
+            let return_value = exited_runtime.machine().return_value();
+            let code_size = return_value.len();
+            let deposit = match self.executor.config.create_contract_limit {
+                Some(limit) if code_size > limit => Err(ExitError::CreateContractLimit),
+                // EIP-3541 reserves code starting with 0xEF for the EVM Object Format
+                _ if self.executor.config.disallow_executable_format && return_value.first() == Some(&0xef) => {
+                    Err(ExitError::InvalidCode)
+                }
+                _ => self.executor.gasometer.record_deploy(code_size),
+            };
+
+            match deposit {
+                Err(e) => {
                     self.executor
                         .state
                         .exit_discard()
                         .map_err(|e| (Vec::new(), ExitReason::from(e)))?;
-                    reason = ExitError::CreateContractLimit.into();
+                    reason = e.into();
                 }
-                _ => {
+                Ok(()) => {
                     self.executor
                         .state
                         .exit_commit()
                         .map_err(|e| (Vec::new(), ExitReason::from(e)))?;
-                    let return_value = exited_runtime.machine().return_value();
                     self.executor.state.set_code(address, return_value);
                 }
             };
         }
 
+        self.executor.gasometer.exit(&reason);
+
         let runtime = match self.runtime.last_mut() {
             Some((runtime, _)) => runtime,
             None => {
@@ -765,6 +968,10 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
     /// - `Revert` if encountered an explicit revert
     /// - `Fatal` if encountered an error that is not supposed to be normal EVM errors
     pub fn execute_n_steps(&mut self, n: u64) -> Result<(), (Vec<u8>, ExitReason)> {
+        if let Some(exit) = self.native_exit.take() {
+            return Err(exit);
+        }
+
         let mut steps = 0_u64;
 
         while steps < n {
@@ -772,7 +979,6 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
             steps += steps_executed;
 
             self.steps_executed += steps_executed;
-            self.executor.gasometer.record_evm_steps(steps_executed);
 
             match apply {
                 RuntimeApply::Continue => (),
@@ -785,6 +991,12 @@ impl<'a, B: StorageInterface> Machine<'a, B> {
         Ok(())
     }
 
+    /// Returns the accounts changed so far by the execution
+    #[must_use]
+    pub fn written_addresses(&self) -> BTreeSet<H160> {
+        self.executor.state.written_addresses()
+    }
+
     /// Returns number of executed steps.
     #[must_use]
     pub fn get_steps_executed(&self) -> u64 {
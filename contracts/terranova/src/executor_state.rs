@@ -1,9 +1,62 @@
 use std::{cell::RefCell, collections::{BTreeMap, BTreeSet}, mem};
 
+use cosmwasm_std::{Addr, Api, Binary, QuerierWrapper, Uint128};
 use evm::{Transfer, H160, backend::{Log, Apply}, U256, H256, ExitError, Valids};
 use serde::{Serialize, Deserialize};
 
-use crate::{storage::StorageInterface};
+use crate::{storage::StorageInterface, config::native_to_evm, precompiles::wasm_execute_address, erc20::Cw20Token, wasm_call::WasmCallStatus};
+
+/// Receiver of a CW20 token transfer made through its ERC-20 facade
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Cw20Recipient {
+    /// An EVM account, the tokens stay in the custody of the contract
+    Evm(H160),
+    /// A Terra address, the tokens are sent out with a CW20 transfer message
+    Terra(Addr),
+}
+
+/// A transfer of CW20 tokens from an EVM account, made through the ERC-20 facade at `token`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Cw20Transfer {
+    pub token: H160,
+    pub source: H160,
+    pub target: Cw20Recipient,
+    pub amount: Uint128,
+}
+
+/// An ERC-20 approval of the facade at `token`, `amount` replaces the previous allowance
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Cw20Approve {
+    pub token: H160,
+    pub owner: H160,
+    pub spender: H160,
+    pub amount: Uint128,
+}
+
+/// Native coins withdrawn from an EVM balance through the withdrawal precompile,
+/// sent to the recipient with a BankMsg once the transaction state is applied
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Withdraw {
+    pub source: H160,
+    /// Terra address, validated by the precompile
+    pub recipient: Addr,
+    /// Amount of the native denom, the EVM balance is debited by this amount scaled to 18 decimals
+    pub amount: Uint128,
+}
+
+/// A CosmWasm execute message sent through the wasm execute precompile,
+/// sent as a submessage with the id `id` once the transaction state is applied
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WasmCall {
+    pub id: u64,
+    pub source: H160,
+    /// Terra address of the contract, validated by the precompile
+    pub contract: Addr,
+    /// JSON encoded execute message
+    pub msg: Binary,
+    /// Amount of the native denom sent with the message, the EVM balance is debited by this amount scaled to 18 decimals
+    pub funds: Uint128,
+}
 
 /// Each of these structs is tied to an EVM H160 address in the accounts field of ExecutorSubstate
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -109,6 +162,13 @@ pub struct ExecutorSubstate {
     parent: Option<Box<ExecutorSubstate>>,
     logs: Vec<Log>,
     transfers: Vec<Transfer>,
+    withdrawals: Vec<Withdraw>,
+    cw20_balances: RefCell<BTreeMap<(H160, H160), Uint128>>,
+    cw20_supplies: RefCell<BTreeMap<H160, Uint128>>,
+    cw20_allowances: BTreeMap<(H160, H160, H160), Uint128>,
+    cw20_transfers: Vec<Cw20Transfer>,
+    cw20_approves: Vec<Cw20Approve>,
+    wasm_calls: Vec<WasmCall>,
     block_hashes: RefCell<BTreeMap<U256, H256>>,
     accounts: BTreeMap<H160, ExecutorAccount>,
     balances: RefCell<BTreeMap<H160, U256>>,
@@ -117,7 +177,49 @@ pub struct ExecutorSubstate {
 }
 
 /// TODO: Document this
-pub type ApplyState = (Vec::<Apply<BTreeMap<U256, U256>>>, Vec<Log>, Vec<Transfer>); //, Vec<SplTransfer>, Vec<SplApprove>, Vec<Withdraw>, Vec<ERC20Approve>);
+pub type ApplyState = (Vec::<Apply<BTreeMap<U256, U256>>>, Vec<Log>, Vec<Transfer>, Vec<Withdraw>, Vec<Cw20Transfer>, Vec<Cw20Approve>, Vec<WasmCall>);
+
+/// Accounts whose state is changed by applying the state
+#[must_use]
+pub fn written_addresses(state: &ApplyState) -> BTreeSet<H160> {
+    let (applies, _, transfers, withdrawals, cw20_transfers, cw20_approves, wasm_calls) = state;
+
+    let mut addresses: BTreeSet<H160> = applies.iter()
+        .map(|apply| match apply {
+            Apply::Modify { address, .. } | Apply::Delete { address } => *address,
+        })
+        .collect();
+    insert_transfer_addresses(&mut addresses, transfers, withdrawals, cw20_transfers, cw20_approves, wasm_calls);
+
+    addresses
+}
+
+/// Both sides of native and CW20 transfers, the owners of CW20 allowances,
+/// and the wasm execute precompile whose call count is increased by the wasm calls
+fn insert_transfer_addresses(
+    addresses: &mut BTreeSet<H160>,
+    transfers: &[Transfer],
+    withdrawals: &[Withdraw],
+    cw20_transfers: &[Cw20Transfer],
+    cw20_approves: &[Cw20Approve],
+    wasm_calls: &[WasmCall],
+) {
+    for transfer in transfers {
+        addresses.insert(transfer.source);
+        addresses.insert(transfer.target);
+    }
+    addresses.extend(withdrawals.iter().map(|withdraw| withdraw.source));
+    for transfer in cw20_transfers {
+        addresses.insert(transfer.source);
+        if let Cw20Recipient::Evm(target) = transfer.target {
+            addresses.insert(target);
+        }
+    }
+    addresses.extend(cw20_approves.iter().map(|approve| approve.owner));
+    if !wasm_calls.is_empty() {
+        addresses.insert(wasm_execute_address());
+    }
+}
 
 impl ExecutorSubstate {
     #[allow(clippy::missing_const_for_fn)]
@@ -128,17 +230,17 @@ impl ExecutorSubstate {
             parent: None,
             logs: Vec::new(),
             transfers: Vec::new(),
+            withdrawals: Vec::new(),
+            cw20_balances: RefCell::new(BTreeMap::new()),
+            cw20_supplies: RefCell::new(BTreeMap::new()),
+            cw20_allowances: BTreeMap::new(),
+            cw20_transfers: Vec::new(),
+            cw20_approves: Vec::new(),
+            wasm_calls: Vec::new(),
             block_hashes: RefCell::new(BTreeMap::new()),
             accounts: BTreeMap::new(),
             balances: RefCell::new(BTreeMap::new()),
             storages: BTreeMap::new(),
-            // spl_balances: RefCell::new(BTreeMap::new()),
-            // spl_decimals: RefCell::new(BTreeMap::new()),
-            // spl_supply: RefCell::new(BTreeMap::new()),
-            // spl_transfers: Vec::new(),
-            // spl_approves: Vec::new(),
-            // withdrawals: Vec::new(),
-            // erc20_allowances: RefCell::new(BTreeMap::new()),
             deletes: BTreeSet::new(),
             // query_account_cache: query::AccountCache::new(),
         }
@@ -214,7 +316,20 @@ impl ExecutorSubstate {
             applies.push(Apply::Delete { address });
         }
 
-        (applies, self.logs, self.transfers) //, self.spl_transfers, self.spl_approves, self.withdrawals, erc20_approves)
+        (applies, self.logs, self.transfers, self.withdrawals, self.cw20_transfers, self.cw20_approves, self.wasm_calls)
+    }
+
+    /// Accounts changed so far, including by the calls still in progress
+    #[must_use]
+    pub fn written_addresses(&self) -> BTreeSet<H160> {
+        let mut addresses = self.parent.as_ref().map_or_else(BTreeSet::new, |parent| parent.written_addresses());
+
+        addresses.extend(self.accounts.keys());
+        addresses.extend(self.storages.keys().map(|(address, _)| *address));
+        addresses.extend(self.deletes.iter());
+        insert_transfer_addresses(&mut addresses, &self.transfers, &self.withdrawals, &self.cw20_transfers, &self.cw20_approves, &self.wasm_calls);
+
+        addresses
     }
 
     /// Creates new instance of `ExecutorSubstate` when entering next execution of a call or create.
@@ -224,6 +339,13 @@ impl ExecutorSubstate {
             parent: None,
             logs: Vec::new(),
             transfers: Vec::new(),
+            withdrawals: Vec::new(),
+            cw20_balances: RefCell::new(BTreeMap::new()),
+            cw20_supplies: RefCell::new(BTreeMap::new()),
+            cw20_allowances: BTreeMap::new(),
+            cw20_transfers: Vec::new(),
+            cw20_approves: Vec::new(),
+            wasm_calls: Vec::new(),
             block_hashes: RefCell::new(BTreeMap::new()),
             accounts: BTreeMap::new(),
             balances: RefCell::new(BTreeMap::new()),
@@ -248,6 +370,13 @@ impl ExecutorSubstate {
         self.logs.append(&mut exited.logs);
         self.balances.borrow_mut().append(&mut exited.balances.borrow_mut());
         self.transfers.append(&mut exited.transfers);
+        self.withdrawals.append(&mut exited.withdrawals);
+        self.cw20_balances.borrow_mut().append(&mut exited.cw20_balances.borrow_mut());
+        self.cw20_supplies.borrow_mut().append(&mut exited.cw20_supplies.borrow_mut());
+        self.cw20_allowances.append(&mut exited.cw20_allowances);
+        self.cw20_transfers.append(&mut exited.cw20_transfers);
+        self.cw20_approves.append(&mut exited.cw20_approves);
+        self.wasm_calls.append(&mut exited.wasm_calls);
 
         let mut resets = BTreeSet::new();
         for (address, account) in &exited.accounts {
@@ -458,13 +587,10 @@ impl ExecutorSubstate {
 
     /// Clears the storage of an account and marks the account as reset.
     pub fn reset_storage<B: StorageInterface>(&mut self, address: H160, backend: &B) {
-        let mut removing = Vec::new();
-
-        for (oa, ok) in self.storages.keys() {
-            if *oa == address {
-                removing.push(*ok);
-            }
-        }
+        let removing: Vec<U256> = self.storages
+            .range((address, U256::zero())..=(address, U256::MAX))
+            .map(|((_, key), _)| *key)
+            .collect();
 
         for ok in removing {
             self.storages.remove(&(address, ok));
@@ -545,6 +671,159 @@ impl ExecutorSubstate {
         Ok(())
     }
 
+    /// Adds a withdrawal to execute, debiting the source balance.
+    /// # Errors
+    /// May return `OutOfFund` if the source has no funds, or if the contract doesn't hold enough native coins for EVM balances.
+    pub fn withdraw<B: StorageInterface>(
+        &mut self,
+        withdraw: Withdraw,
+        backend: &B,
+    ) -> Result<(), ExitError> {
+        self.check_native_supply(withdraw.amount, backend)?;
+
+        let new_source_balance = {
+            let balance = self.balance(&withdraw.source, backend);
+            balance.checked_sub(native_to_evm(withdraw.amount)).ok_or(ExitError::OutOfFund)?
+        };
+
+        self.balances.borrow_mut().insert(withdraw.source, new_source_balance);
+        self.withdrawals.push(withdraw);
+
+        Ok(())
+    }
+
+    /// Native coins sent out of the contract by the withdrawals and wasm calls queued so far, in this substate and its parents
+    fn queued_native_outflow(&self) -> Uint128 {
+        let outflow = self.withdrawals.iter().map(|withdraw| withdraw.amount)
+            .chain(self.wasm_calls.iter().map(|call| call.funds))
+            .fold(Uint128::zero(), Uint128::saturating_add);
+
+        outflow.saturating_add(self.parent.as_ref().map_or_else(Uint128::zero, |parent| parent.queued_native_outflow()))
+    }
+
+    /// Balances created without a deposit aren't backed by NATIVE_SUPPLY, sending them out fails the call here
+    /// rather than failing to apply the whole transaction
+    fn check_native_supply<B: StorageInterface>(&self, amount: Uint128, backend: &B) -> Result<(), ExitError> {
+        let outflow = self.queued_native_outflow().checked_add(amount).map_err(|_| ExitError::OutOfFund)?;
+        if outflow > backend.native_supply() {
+            return Err(ExitError::OutOfFund);
+        }
+
+        Ok(())
+    }
+
+    /// Number of wasm calls queued by the transaction so far, in this substate and its parents
+    fn queued_wasm_calls(&self) -> u64 {
+        self.wasm_calls.len() as u64 + self.parent.as_ref().map_or(0, |parent| parent.queued_wasm_calls())
+    }
+
+    /// Adds a wasm call to send, debiting the source balance by the funds sent along. Returns the id of the call.\ 
+    /// Ids are assigned in the order the calls are sent, so the id of a call discarded with its frame is reused by the next call
+    /// # Errors
+    /// May return `OutOfFund` if the source has not enough funds, or if the contract doesn't hold enough native coins for EVM balances.
+    pub fn wasm_execute<B: StorageInterface>(
+        &mut self,
+        mut call: WasmCall,
+        backend: &B,
+    ) -> Result<u64, ExitError> {
+        if !call.funds.is_zero() {
+            self.check_native_supply(call.funds, backend)?;
+            let new_source_balance = self.balance(&call.source, backend)
+                .checked_sub(native_to_evm(call.funds))
+                .ok_or(ExitError::OutOfFund)?;
+            self.balances.borrow_mut().insert(call.source, new_source_balance);
+        }
+
+        call.id = backend.wasm_call_count() + self.queued_wasm_calls();
+        let id = call.id;
+        self.wasm_calls.push(call);
+
+        Ok(id)
+    }
+
+    fn known_cw20_balance(&self, token: &H160, owner: &H160) -> Option<Uint128> {
+        match self.cw20_balances.borrow().get(&(*token, *owner)) {
+            Some(balance) => Some(*balance),
+            None => self.parent.as_ref().and_then(|parent| parent.known_cw20_balance(token, owner))
+        }
+    }
+
+    #[must_use]
+    pub fn cw20_balance<B: StorageInterface>(&self, token: &H160, owner: &H160, backend: &B) -> Uint128 {
+        self.known_cw20_balance(token, owner).unwrap_or_else(|| {
+            let balance = backend.cw20_balance(token, owner);
+            self.cw20_balances.borrow_mut().insert((*token, *owner), balance);
+
+            balance
+        })
+    }
+
+    fn known_cw20_supply(&self, token: &H160) -> Option<Uint128> {
+        match self.cw20_supplies.borrow().get(token) {
+            Some(supply) => Some(*supply),
+            None => self.parent.as_ref().and_then(|parent| parent.known_cw20_supply(token))
+        }
+    }
+
+    #[must_use]
+    pub fn cw20_supply<B: StorageInterface>(&self, token: &H160, backend: &B) -> Uint128 {
+        self.known_cw20_supply(token).unwrap_or_else(|| {
+            let supply = backend.cw20_supply(token);
+            self.cw20_supplies.borrow_mut().insert(*token, supply);
+
+            supply
+        })
+    }
+
+    #[must_use]
+    pub fn cw20_allowance<B: StorageInterface>(&self, token: &H160, owner: &H160, spender: &H160, backend: &B) -> Uint128 {
+        match self.cw20_allowances.get(&(*token, *owner, *spender)) {
+            Some(allowance) => *allowance,
+            None => self.parent.as_ref().map_or_else(
+                || backend.cw20_allowance(token, owner, spender),
+                |parent| parent.cw20_allowance(token, owner, spender, backend)
+            )
+        }
+    }
+
+    /// Adds a CW20 transfer to execute, tokens sent to a Terra address leave the EVM supply.
+    /// # Errors
+    /// May return `OutOfFund` if the source has not enough tokens.
+    pub fn cw20_transfer<B: StorageInterface>(
+        &mut self,
+        transfer: Cw20Transfer,
+        backend: &B,
+    ) -> Result<(), ExitError> {
+        let new_source_balance = self.cw20_balance(&transfer.token, &transfer.source, backend)
+            .checked_sub(transfer.amount)
+            .map_err(|_| ExitError::OutOfFund)?;
+        self.cw20_balances.borrow_mut().insert((transfer.token, transfer.source), new_source_balance);
+
+        match &transfer.target {
+            Cw20Recipient::Evm(target) => {
+                let new_target_balance = self.cw20_balance(&transfer.token, target, backend)
+                    .checked_add(transfer.amount)
+                    .map_err(|_| ExitError::InvalidRange)?;
+                self.cw20_balances.borrow_mut().insert((transfer.token, *target), new_target_balance);
+            }
+            Cw20Recipient::Terra(_) => {
+                let new_supply = self.cw20_supply(&transfer.token, backend)
+                    .checked_sub(transfer.amount)
+                    .map_err(|_| ExitError::OutOfFund)?;
+                self.cw20_supplies.borrow_mut().insert(transfer.token, new_supply);
+            }
+        }
+
+        self.cw20_transfers.push(transfer);
+
+        Ok(())
+    }
+
+    pub fn cw20_approve(&mut self, approve: Cw20Approve) {
+        self.cw20_allowances.insert((approve.token, approve.owner, approve.spender), approve.amount);
+        self.cw20_approves.push(approve);
+    }
+
     /// Resets the balance of an account: sets it to 0.
     pub fn reset_balance(&self, address: H160) {
         let mut balances = self.balances.borrow_mut();
@@ -624,6 +903,16 @@ impl<'a, B: StorageInterface> ExecutorState<'a, B> {
         U256::from(self.backend.chain_id())
     }
 
+    #[must_use]
+    pub fn api(&self) -> &dyn Api {
+        self.backend.api()
+    }
+
+    #[must_use]
+    pub fn querier(&self) -> QuerierWrapper<'_> {
+        self.backend.querier()
+    }
+
     #[must_use]
     pub fn exists(&self, address: H160) -> bool {
         self.substate.known_account(address).is_some() || self.backend.exists(&address)
@@ -765,173 +1054,93 @@ impl<'a, B: StorageInterface> ExecutorState<'a, B> {
     pub fn touch(&mut self, address: H160) {
         self.substate.touch(address, self.backend);
     }
-    /*
+    /// CW20 token wrapped by the ERC-20 facade at `address`, if any
     #[must_use]
-    pub fn erc20_decimals(&self, mint: Pubkey) -> u8
-    {
-        self.substate.spl_decimals(&mint, self.backend)
+    pub fn cw20_token(&self, address: H160) -> Option<Cw20Token> {
+        self.backend.cw20_token(&address)
     }
 
+    /// Whether EVM contracts may send messages to the CosmWasm contract through the wasm execute precompile
     #[must_use]
-    pub fn erc20_total_supply(&self, mint: Pubkey) -> U256
-    {
-        let supply = self.substate.spl_supply(&mint, self.backend);
-        U256::from(supply)
+    pub fn wasm_execute_allowed(&self, contract: &Addr) -> bool {
+        self.backend.wasm_execute_allowed(contract)
     }
 
-    /// Returns the account balance of another account with `address`.
-    /// Returns zero if the account is not yet known.
     #[must_use]
-    pub fn erc20_balance_of(&self, mint: Pubkey, context: &evm::Context, address: H160) -> U256
-    {
-        let (token_account, _) = self.backend.get_erc20_token_address(&address, &context.address, &mint);
-
-        let balance = self.substate.spl_balance(&token_account, self.backend);
-        U256::from(balance)
-    }
-
-    fn erc20_emit_transfer_event(&mut self, contract: H160, source: H160, target: H160, value: u64) {
-        // event Transfer(address indexed from, address indexed to, uint256 value);
-
-        let topics = vec![
-            H256::from_str("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef").unwrap(),
-            H256::from(source),
-            H256::from(target)
-        ];
-
-        let mut data = vec![0_u8; 32];
-        U256::from(value).into_big_endian_fast(&mut data);
-
-        self.log(contract, topics, data);
+    pub fn cw20_balance(&self, token: H160, owner: H160) -> Uint128 {
+        self.substate.cw20_balance(&token, &owner, self.backend)
     }
 
+    /// Amount of the CW20 token held by EVM accounts
     #[must_use]
-    fn erc20_transfer_impl(&mut self, mint: Pubkey, contract: H160, source: H160, target: H160, value: U256) -> bool
-    {
-        if value > U256::from(u64::MAX) {
-            return false;
-        }
-        let value = value.as_u64();
-
-        let (source_token, _) = self.backend.get_erc20_token_address(&source, &contract, &mint);
-        let (target_token, _) = self.backend.get_erc20_token_address(&target, &contract, &mint);
-
-        let transfer = SplTransfer { source, target, contract, mint, source_token, target_token, value };
-        if self.substate.spl_transfer(transfer, self.backend).is_err() {
-            return false;
-        }
-
-        self.erc20_emit_transfer_event(contract, source, target, value);
-
-        true
+    pub fn cw20_supply(&self, token: H160) -> Uint128 {
+        self.substate.cw20_supply(&token, self.backend)
     }
 
     #[must_use]
-    pub fn erc20_transfer(&mut self, mint: Pubkey, context: &evm::Context, target: H160, value: U256) -> bool
-    {
-        self.erc20_transfer_impl(mint, context.address, context.caller, target, value)
+    pub fn cw20_allowance(&self, token: H160, owner: H160, spender: H160) -> Uint128 {
+        self.substate.cw20_allowance(&token, &owner, &spender, self.backend)
     }
 
-    #[must_use]
-    pub fn erc20_transfer_from(&mut self, mint: Pubkey, context: &evm::Context, source: H160, target: H160, value: U256) -> bool
-    {
-        let contract = context.address;
-
-        {
-            let allowance = self.substate.erc20_allowance(source, context.caller, contract, mint, self.backend);
-            if allowance < value {
-                return false;
-            }
-
-            let approve = ERC20Approve { owner: source, spender: context.caller, contract, mint, value: allowance - value };
-            self.substate.erc20_approve(&approve);
-        }
+    pub fn cw20_transfer(&mut self, token: H160, source: H160, target: Cw20Recipient, amount: Uint128) -> Result<(), ExitError> {
+        let transfer = Cw20Transfer {
+            token,
+            source,
+            target,
+            amount,
+        };
 
-        self.erc20_transfer_impl(mint, contract, source, target, value)
+        self.substate.cw20_transfer(transfer, self.backend)
     }
 
-    fn erc20_emit_approval_event(&mut self, contract: H160, owner: H160, spender: H160, value: U256) {
-        // event Approval(address indexed owner, address indexed spender, uint256 value);
-
-        let topics = vec![
-            H256::from_str("8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925").unwrap(),
-            H256::from(owner),
-            H256::from(spender)
-        ];
-
-        let mut data = vec![0_u8; 32];
-        value.into_big_endian_fast(&mut data);
+    pub fn cw20_approve(&mut self, token: H160, owner: H160, spender: H160, amount: Uint128) {
+        let approve = Cw20Approve {
+            token,
+            owner,
+            spender,
+            amount,
+        };
 
-        self.log(contract, topics, data);
+        self.substate.cw20_approve(approve);
     }
 
-    pub fn erc20_approve(&mut self, mint: Pubkey, context: &evm::Context, spender: H160, value: U256)
-    {
-        let contract = context.address;
-        let owner = context.caller;
-
-        let approve = ERC20Approve { owner, spender, contract, mint, value };
-        self.substate.erc20_approve(&approve);
+    /// Queues a CosmWasm execute message to send once the transaction succeeds, returns the id of the call.
+    /// Reverting the call frame discards the message
+    pub fn wasm_execute(&mut self, source: H160, contract: Addr, msg: Binary, funds: Uint128) -> Result<u64, ExitError> {
+        let call = WasmCall {
+            id: 0,
+            source,
+            contract,
+            msg,
+            funds,
+        };
 
-        self.erc20_emit_approval_event(context.address, owner, spender, value);
+        self.substate.wasm_execute(call, self.backend)
     }
 
     #[must_use]
-    pub fn erc20_allowance(&self, mint: Pubkey, context: &evm::Context, owner: H160, spender: H160) -> U256
-    {
-        let contract = context.address;
-
-        self.substate.erc20_allowance(owner, spender, contract, mint, self.backend)
-    }
-
-    fn erc20_emit_approval_solana_event(&mut self, contract: H160, owner: H160, spender: Pubkey, value: u64) {
-        // event ApprovalSolana(address indexed owner, bytes32 indexed spender, uint64 value);
-
-        let topics = vec![
-            H256::from_str("f2d0a01e4c49f3439199c8f8950e366e85c4d1bd845552f6da1009b3bb2c1a70").unwrap(),
-            H256::from(owner),
-            H256::from(spender.to_bytes())
-        ];
-
-        let mut data = vec![0_u8; 32];
-        U256::from(value).into_big_endian_fast(&mut data);
-
-        self.log(contract, topics, data);
-    }
-
-    pub fn erc20_approve_solana(&mut self, mint: Pubkey, context: &evm::Context, spender: Pubkey, value: u64)
-    {
-        let contract = context.address;
-        let owner = context.caller;
-
-        let approve = SplApprove { owner, spender, contract, mint, value };
-        self.substate.spl_approve(approve);
-
-        self.erc20_emit_approval_solana_event(context.address, owner, spender, value);
+    pub fn wasm_call_status(&self, id: u64) -> Option<WasmCallStatus> {
+        self.backend.wasm_call_status(id)
     }
 
+    /// Terra address of the Terranova contract
     #[must_use]
-    pub fn withdraw(&mut self, source: H160, destination: Pubkey, neon_amount: U256, spl_amount: u64) -> bool {
-        let dest_neon_acct = get_associated_token_address(
-            &destination,
-            self.backend.token_mint()
-        );
+    pub fn contract_address(&self) -> &Addr {
+        self.backend.contract_address()
+    }
 
-        let withdraw = Withdraw{
+    /// Debits `amount` of the native denom from the source balance, to be sent to the Terra recipient
+    /// once the transaction succeeds. Reverting the call frame discards the withdrawal
+    pub fn withdraw(&mut self, source: H160, recipient: Addr, amount: Uint128) -> Result<(), ExitError> {
+        let withdraw = Withdraw {
             source,
-            dest: destination,
-            dest_neon: dest_neon_acct,
-            neon_amount,
-            spl_amount
-        };
-
-        if self.substate.withdraw(withdraw, self.backend).is_err() {
-            return false;
+            recipient,
+            amount,
         };
 
-        true
+        self.substate.withdraw(withdraw, self.backend)
     }
-    */
+
     pub fn new(substate: Box<ExecutorSubstate>, backend: &'a B) -> Self {
         Self { backend, substate }
     }
@@ -942,6 +1151,18 @@ impl<'a, B: StorageInterface> ExecutorState<'a, B> {
         &self.substate
     }
 
+    /// Accounts changed so far, see `ExecutorSubstate::written_addresses`
+    #[must_use]
+    pub fn written_addresses(&self) -> BTreeSet<H160> {
+        self.substate.written_addresses()
+    }
+
+    /// Releases the backend, returns the substate to save between messages.
+    #[must_use]
+    pub fn into_substate(self) -> Box<ExecutorSubstate> {
+        self.substate
+    }
+
     /// Deconstructs the executor, returns state to be applied.
     /// # Panics
     /// Panics if the executor is not in the top-level substate.
@@ -1,59 +1,684 @@
-use std::convert::TryInto;
+use std::{collections::BTreeSet, convert::TryInto};
 
-use evm::{U256, H160};
+use evm::{Context, ExitError, ExitReason, Opcode, Stack, H160, U256};
+use serde::{Deserialize, Serialize};
 
-use crate::{storage::StorageInterface, executor_state::ExecutorState, transaction::UnsignedTransaction};
+use crate::{
+    storage::StorageInterface, executor_state::ExecutorState, hardfork::{Hardfork, HardforkConfig, BASEFEE, PUSH0}, precompiles,
+    transaction::UnsignedTransaction, ContractError
+};
 
-/// Not required for bytecode execution itself, but necessary if you want to measure opcode usage 
-/// TODO: Flesh out the skeleton here
+// Ethereum gas schedule (Istanbul), see the Ethereum yellow paper appendix G
+const G_ZERO: u64 = 0;
+const G_BASE: u64 = 2;
+const G_VERYLOW: u64 = 3;
+const G_LOW: u64 = 5;
+const G_MID: u64 = 8;
+const G_HIGH: u64 = 10;
+const G_JUMPDEST: u64 = 1;
+const G_BLOCKHASH: u64 = 20;
+const G_BALANCE: u64 = 700;
+const G_EXTCODE: u64 = 700;
+const G_EXTCODEHASH: u64 = 700;
+const G_SLOAD: u64 = 800;
+const G_SSTORE_SET: u64 = 20_000;
+const G_SSTORE_RESET: u64 = 5_000;
+const G_SSTORE_SENTRY: u64 = 2_300;
+const R_SSTORE_CLEARS: i64 = 15_000;
+const G_EXP: u64 = 10;
+const G_EXPBYTE: u64 = 50;
+const G_SHA3: u64 = 30;
+const G_SHA3WORD: u64 = 6;
+const G_COPY: u64 = 3;
+const G_MEMORY: u64 = 3;
+const G_QUADCOEFFDIV: u128 = 512;
+const G_LOG: u64 = 375;
+const G_LOGTOPIC: u64 = 375;
+const G_LOGDATA: u64 = 8;
+const G_CREATE: u64 = 32_000;
+const G_CODEDEPOSIT: u64 = 200;
+const G_CALL: u64 = 700;
+const G_CALLVALUE: u64 = 9_000;
+const G_CALLSTIPEND: u64 = 2_300;
+const G_NEWACCOUNT: u64 = 25_000;
+const G_SELFDESTRUCT: u64 = 5_000;
+const R_SELFDESTRUCT: i64 = 24_000;
+const G_TRANSACTION: u64 = 21_000;
+const G_TXCREATE: u64 = 32_000;
+const G_TXDATAZERO: u64 = 4;
+const G_TXDATANONZERO: u64 = 16;
+const G_ACCESS_LIST_ADDRESS: u64 = 2_400;
+const G_ACCESS_LIST_STORAGE_KEY: u64 = 1_900;
+
+// State access costs of EIP-2929 (Berlin) and refund of EIP-3529 (London)
+const G_WARM_STORAGE_READ: u64 = 100;
+const G_COLD_SLOAD: u64 = 2_100;
+const G_COLD_ACCOUNT_ACCESS: u64 = 2_600;
+const R_SSTORE_CLEARS_EIP3529: i64 = 4_800;
+
+// Init code metering of EIP-3860 (Shanghai)
+const G_INITCODE_WORD: u64 = 2;
+
+/// Gas accounting of a single call or create frame of execution
+#[derive(Serialize, Deserialize)]
+struct GasFrame {
+    /// Gas made available to this frame
+    gas_limit: u64,
+
+    /// Gas spent by this frame, including the gas forwarded to its nested frames
+    used_gas: u64,
+
+    /// Size of the memory of this frame in 32 byte words, memory expansion is only paid for once
+    memory_words: u64,
+
+    /// Accumulated refund counter, only kept if the frame exits successfully.\
+    /// Can be negative within a frame because EIP-2200 removes refunds granted earlier in the transaction
+    refunded_gas: i64,
+
+    /// Addresses and storage slots first accessed by this frame or by its nested frames that succeeded.\
+    /// They are cold again if this frame doesn't succeed (EIP-2929)
+    warmed_addresses: Vec<H160>,
+    warmed_storage: Vec<(H160, U256)>,
+}
+
+impl GasFrame {
+    fn new(gas_limit: u64) -> Self {
+        Self {
+            gas_limit,
+            used_gas: 0,
+            memory_words: 0,
+            refunded_gas: 0,
+            warmed_addresses: Vec::new(),
+            warmed_storage: Vec::new(),
+        }
+    }
+
+    fn gas_left(&self) -> u64 {
+        self.gas_limit - self.used_gas
+    }
+}
+
+/// Metering of EVM execution following the Ethereum gas schedule.
+///
+/// The gasometer keeps a stack of frames parallel to the runtime stack of the Machine. Every nested call or create
+/// is given a part of the gas left in its parent frame, and gives back whatever it didn't spend when it exits.
+/// Execution of a frame halts with `OutOfGas` as soon as it can't pay for the next opcode.
+#[derive(Serialize, Deserialize)]
 pub struct Gasometer {
-    gas: u64,
+    /// Hardfork whose gas schedule is followed, fixed for the whole transaction
+    hardfork: Hardfork,
+
+    /// Intrinsic gas of the transaction, paid before any EVM code is executed
+    intrinsic_gas: u64,
+
+    frames: Vec<GasFrame>,
+
+    /// Addresses and storage slots accessed so far in the transaction, they are cheaper to access again under EIP-2929.\
+    /// The access list of the transaction (EIP-2930), paid for in the intrinsic gas, is warm from the start of execution
+    warm_addresses: BTreeSet<H160>,
+    warm_storage: BTreeSet<(H160, U256)>,
 }
 
 impl Gasometer {
-    pub fn new() -> Self {
-        Self { gas: 0_u64 }
+    pub fn new(hardfork: Hardfork) -> Self {
+        Self {
+            hardfork,
+            intrinsic_gas: 0,
+            frames: Vec::new(),
+            warm_addresses: BTreeSet::new(),
+            warm_storage: BTreeSet::new(),
+        }
     }
 
     #[must_use]
-    pub fn used_gas(&self) -> U256 {
-        U256::from(self.gas)
+    pub fn hardfork(&self) -> Hardfork {
+        self.hardfork
+    }
+
+    fn config(&self) -> &'static HardforkConfig {
+        self.hardfork.config()
     }
 
-    pub fn record_iterative_overhead(&mut self) {
+    /// Gas used by the transaction so far, including the intrinsic gas.\
+    /// Once execution is completed, refunds are deducted, up to half of the used gas, or a fifth since EIP-3529.
+    #[must_use]
+    pub fn used_gas(&self) -> U256 {
+        let (used_gas, refunded_gas) = self.frames.first()
+            .map_or((0, 0), |root| (root.used_gas, root.refunded_gas));
+
+        let used_gas = self.intrinsic_gas.saturating_add(used_gas);
+        let refunded_gas: u64 = refunded_gas.max(0).try_into().expect("refund is not negative");
+
+        U256::from(used_gas - refunded_gas.min(used_gas / self.config().max_refund_quotient))
+    }
 
+    /// Gas left in the currently executing frame
+    #[must_use]
+    pub fn gas_left(&self) -> u64 {
+        self.frames.last().map_or(0, GasFrame::gas_left)
     }
 
+    /// Records the intrinsic gas of the transaction: the base transaction cost, the cost of a contract creation and of its init code,
+    /// the cost of every zero and non-zero byte of the call data and the cost of every address and storage key of the access list.
+    /// The entries of the access list are pre-warmed
     pub fn record_transaction_size(&mut self, trx: &UnsignedTransaction) {
+        let zero_bytes: u64 = trx.call_data.iter().filter(|byte| **byte == 0).count().try_into().expect("usize is 8 bytes");
+        let non_zero_bytes: u64 = trx.call_data.len().try_into().expect("usize is 8 bytes");
+        let non_zero_bytes = non_zero_bytes - zero_bytes;
+
+        let create_cost = match (trx.to, self.config().create_initcode_limit) {
+            (Some(_), _) => 0,
+            (None, None) => G_TXCREATE,
+            (None, Some(_)) => {
+                let words: u64 = ((trx.call_data.len() + 31) / 32).try_into().expect("usize is 8 bytes");
+                G_TXCREATE.saturating_add(words.saturating_mul(G_INITCODE_WORD))
+            }
+        };
+
+        let mut access_list_cost: u64 = 0;
+        for (address, keys) in &trx.access_list {
+            let keys_count: u64 = keys.len().try_into().expect("usize is 8 bytes");
+            access_list_cost = access_list_cost
+                .saturating_add(G_ACCESS_LIST_ADDRESS)
+                .saturating_add(keys_count.saturating_mul(G_ACCESS_LIST_STORAGE_KEY));
+
+            self.warm_addresses.insert(*address);
+            for key in keys {
+                self.warm_storage.insert((*address, U256::from_big_endian(key.as_bytes())));
+            }
+        }
 
+        self.intrinsic_gas = (G_TRANSACTION + create_cost + zero_bytes * G_TXDATAZERO + non_zero_bytes * G_TXDATANONZERO)
+            .saturating_add(access_list_cost);
     }
 
-    pub fn record_evm_steps(&mut self, steps: u64) {
+    /// Whether the address was accessed earlier in the transaction, precompiles are always warm
+    #[must_use]
+    pub fn is_warm_address(&self, address: &H160) -> bool {
+        precompiles::is_precompile_address(address) || self.warm_addresses.contains(address)
+    }
 
+    /// Whether the storage slot was accessed earlier in the transaction
+    #[must_use]
+    pub fn is_warm_storage(&self, address: &H160, index: &U256) -> bool {
+        self.warm_storage.contains(&(*address, *index))
     }
 
-    pub fn pad_evm_steps(&mut self, steps: u64) {
+    /// Marks the address as accessed, returns whether it was already warm.
+    /// Addresses accessed before the outermost frame is opened, like the sender and the recipient, stay warm
+    pub fn access_address(&mut self, address: H160) -> bool {
+        if self.is_warm_address(&address) {
+            return true;
+        }
+
+        self.warm_addresses.insert(address);
+        if let Some(frame) = self.frames.last_mut() {
+            frame.warmed_addresses.push(address);
+        }
 
+        false
     }
 
-    pub fn record_storage_write<B>(&mut self, state: &ExecutorState<B>, address: H160, key: U256)
-    where
-        B: StorageInterface
-    {
+    /// Marks the storage slot as accessed, returns whether it was already warm
+    fn access_storage(&mut self, address: H160, index: U256) -> bool {
+        if !self.warm_storage.insert((address, index)) {
+            return true;
+        }
+
+        if let Some(frame) = self.frames.last_mut() {
+            frame.warmed_storage.push((address, index));
+        }
+
+        false
+    }
+
+    /// Cost of an opcode reading an account: `cost` before EIP-2929, which makes it depend on whether
+    /// the account was accessed earlier in the transaction
+    fn account_access_cost(&mut self, address: H160, cost: u64) -> u64 {
+        if !self.config().increase_state_access_gas {
+            return cost;
+        }
+
+        if self.access_address(address) {
+            G_WARM_STORAGE_READ
+        } else {
+            G_COLD_ACCOUNT_ACCESS
+        }
+    }
+
+    /// Cost of the init code of a nested contract creation, nothing before EIP-3860.
+    /// Init code over the limit fails the creating frame like running out of gas
+    fn initcode_cost(&self, len: U256) -> Result<u64, ExitError> {
+        match self.config().create_initcode_limit {
+            None => Ok(0),
+            Some(limit) if len > U256::from(limit) => Err(ExitError::CreateContractLimit),
+            Some(_) => words_cost(G_INITCODE_WORD, len),
+        }
+    }
+
+    /// Opens the outermost frame of the transaction with the gas left after paying the intrinsic gas
+    ///
+    /// # Errors
+    ///
+    /// Returns `GasLimitTooLow` if the gas limit does not cover the intrinsic gas of the transaction
+    pub fn begin(&mut self, gas_limit: U256) -> Result<(), ContractError> {
+        assert!(self.frames.is_empty(), "Transaction gas accounting has already begun");
+
+        let gas_limit = if gas_limit > U256::from(u64::MAX) { u64::MAX } else { gas_limit.as_u64() };
+        let gas_limit = gas_limit.checked_sub(self.intrinsic_gas)
+            .ok_or_else(|| E!(ContractError::GasLimitTooLow; "Gas limit {} is lower than the intrinsic gas {}", gas_limit, self.intrinsic_gas))?;
+
+        self.frames.push(GasFrame::new(gas_limit));
+
+        Ok(())
+    }
 
+    /// Opens a nested frame with the gas forwarded to it by `record_call` or `record_create`
+    pub fn enter(&mut self, gas_limit: u64) {
+        self.frames.push(GasFrame::new(gas_limit));
     }
 
-    pub fn record_deploy<B>(&mut self, state: &ExecutorState<B>, address: H160)
+    /// Closes the current frame. Unless the frame exited with an error, its unused gas is returned to the parent frame.
+    /// Refunds are only kept if the frame succeeded.
+    pub fn exit(&mut self, reason: &ExitReason) {
+        let mut exited = match self.frames.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        match reason {
+            ExitReason::Succeed(_) => (),
+            ExitReason::Revert(_) => exited.refunded_gas = 0,
+            _ => {
+                exited.used_gas = exited.gas_limit;
+                exited.refunded_gas = 0;
+            }
+        }
+
+        if !reason.is_succeed() {
+            for address in exited.warmed_addresses.drain(..) {
+                self.warm_addresses.remove(&address);
+            }
+            for slot in exited.warmed_storage.drain(..) {
+                self.warm_storage.remove(&slot);
+            }
+        }
+
+        match self.frames.last_mut() {
+            Some(parent) => {
+                parent.used_gas = parent.used_gas.saturating_sub(exited.gas_left());
+                parent.refunded_gas += exited.refunded_gas;
+                parent.warmed_addresses.append(&mut exited.warmed_addresses);
+                parent.warmed_storage.append(&mut exited.warmed_storage);
+            }
+            // The outermost frame is kept to report the gas used by the transaction
+            None => self.frames.push(exited),
+        }
+    }
+
+    /// Charges gas to the current frame, the whole frame gas is consumed if there is not enough left
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfGas` if the current frame can't pay for `cost`
+    pub fn record_cost(&mut self, cost: u64) -> Result<(), ExitError> {
+        let frame = self.frames.last_mut().ok_or(ExitError::OutOfGas)?;
+
+        if cost > frame.gas_left() {
+            frame.used_gas = frame.gas_limit;
+            return Err(ExitError::OutOfGas);
+        }
+
+        frame.used_gas += cost;
+        Ok(())
+    }
+
+    pub fn record_refund(&mut self, refund: i64) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.refunded_gas += refund;
+        }
+    }
+
+    /// Charges memory expansion for an access of `len` bytes at `offset` in the memory of the current frame
+    fn record_memory(&mut self, offset: U256, len: U256) -> Result<(), ExitError> {
+        if len.is_zero() {
+            return Ok(());
+        }
+
+        let end = offset.checked_add(len).ok_or(ExitError::OutOfGas)?;
+        let words = to_u64(end)?.saturating_add(31) / 32;
+
+        let memory_words = self.frames.last().map_or(0, |frame| frame.memory_words);
+        if words <= memory_words {
+            return Ok(());
+        }
+
+        let cost = memory_cost(words)?.checked_sub(memory_cost(memory_words)?).ok_or(ExitError::OutOfGas)?;
+        self.record_cost(cost)?;
+
+        if let Some(frame) = self.frames.last_mut() {
+            frame.memory_words = words;
+        }
+
+        Ok(())
+    }
+
+    /// Charges the cost of an opcode before it is executed, called by the Handler for every step of the runtime.
+    /// The cost of SSTORE depends on the stored value and is charged by `record_storage_write` instead,
+    /// the gas forwarded by the CALL and CREATE families is charged by `record_call` and `record_create`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfGas` if the current frame can't pay for the opcode
+    pub fn record_opcode<B>(&mut self, state: &ExecutorState<B>, context: &Context, opcode: Opcode, stack: &Stack) -> Result<(), ExitError>
     where
         B: StorageInterface
     {
+        if let Some(cost) = static_cost(opcode) {
+            return self.record_cost(cost);
+        }
+
+        let cost = match opcode {
+            Opcode::EXP => {
+                let exponent = stack_u256(stack, 1)?;
+                let exponent_bytes = (exponent.bits() as u64 + 7) / 8;
+                G_EXP + G_EXPBYTE * exponent_bytes
+            }
+            Opcode::SHA3 => {
+                let len = stack_u256(stack, 1)?;
+                self.record_memory(stack_u256(stack, 0)?, len)?;
+                add_cost(G_SHA3, words_cost(G_SHA3WORD, len)?)?
+            }
+            Opcode::CALLDATACOPY | Opcode::CODECOPY | Opcode::RETURNDATACOPY => {
+                let len = stack_u256(stack, 2)?;
+                self.record_memory(stack_u256(stack, 0)?, len)?;
+                add_cost(G_VERYLOW, words_cost(G_COPY, len)?)?
+            }
+            Opcode::BALANCE => self.account_access_cost(H160::from(stack.peek(0)?), G_BALANCE),
+            Opcode::EXTCODESIZE => self.account_access_cost(H160::from(stack.peek(0)?), G_EXTCODE),
+            Opcode::EXTCODEHASH => self.account_access_cost(H160::from(stack.peek(0)?), G_EXTCODEHASH),
+            Opcode::EXTCODECOPY => {
+                let len = stack_u256(stack, 3)?;
+                self.record_memory(stack_u256(stack, 1)?, len)?;
+
+                let access_cost = self.account_access_cost(H160::from(stack.peek(0)?), G_EXTCODE);
+                add_cost(access_cost, words_cost(G_COPY, len)?)?
+            }
+            Opcode::SLOAD => {
+                let index = stack_u256(stack, 0)?;
+                if !self.config().increase_state_access_gas {
+                    G_SLOAD
+                } else if self.access_storage(context.address, index) {
+                    G_WARM_STORAGE_READ
+                } else {
+                    G_COLD_SLOAD
+                }
+            }
+            Opcode::MLOAD | Opcode::MSTORE => {
+                self.record_memory(stack_u256(stack, 0)?, U256::from(32))?;
+                G_VERYLOW
+            }
+            Opcode::MSTORE8 => {
+                self.record_memory(stack_u256(stack, 0)?, U256::one())?;
+                G_VERYLOW
+            }
+            Opcode::RETURN | Opcode::REVERT => {
+                self.record_memory(stack_u256(stack, 0)?, stack_u256(stack, 1)?)?;
+                G_ZERO
+            }
+            Opcode::LOG0 | Opcode::LOG1 | Opcode::LOG2 | Opcode::LOG3 | Opcode::LOG4 => {
+                let topics = u64::from(opcode.as_u8() - Opcode::LOG0.as_u8());
+                let len = stack_u256(stack, 1)?;
+                self.record_memory(stack_u256(stack, 0)?, len)?;
+
+                let data_cost = to_u64(len)?.checked_mul(G_LOGDATA).ok_or(ExitError::OutOfGas)?;
+                add_cost(G_LOG + G_LOGTOPIC * topics, data_cost)?
+            }
+            Opcode::CREATE => {
+                let len = stack_u256(stack, 2)?;
+                self.record_memory(stack_u256(stack, 1)?, len)?;
+                add_cost(G_CREATE, self.initcode_cost(len)?)?
+            }
+            Opcode::CREATE2 => {
+                // Init code is hashed to derive the address
+                let len = stack_u256(stack, 2)?;
+                self.record_memory(stack_u256(stack, 1)?, len)?;
+                add_cost(add_cost(G_CREATE, words_cost(G_SHA3WORD, len)?)?, self.initcode_cost(len)?)?
+            }
+            Opcode::CALL | Opcode::CALLCODE => {
+                self.record_memory(stack_u256(stack, 3)?, stack_u256(stack, 4)?)?;
+                self.record_memory(stack_u256(stack, 5)?, stack_u256(stack, 6)?)?;
+
+                let target = H160::from(stack.peek(1)?);
+                let value = stack_u256(stack, 2)?;
+
+                let mut cost = self.account_access_cost(target, G_CALL);
+                if !value.is_zero() {
+                    cost += G_CALLVALUE;
+
+                    if opcode == Opcode::CALL && (!state.exists(target) || state.is_empty(target)) {
+                        cost += G_NEWACCOUNT;
+                    }
+                }
 
+                cost
+            }
+            Opcode::DELEGATECALL | Opcode::STATICCALL => {
+                self.record_memory(stack_u256(stack, 2)?, stack_u256(stack, 3)?)?;
+                self.record_memory(stack_u256(stack, 4)?, stack_u256(stack, 5)?)?;
+                self.account_access_cost(H160::from(stack.peek(1)?), G_CALL)
+            }
+            Opcode::SUICIDE => {
+                let target = H160::from(stack.peek(0)?);
+
+                let mut cost = G_SELFDESTRUCT;
+                if !state.balance(context.address).is_zero() && (!state.exists(target) || state.is_empty(target)) {
+                    cost += G_NEWACCOUNT;
+                }
+
+                if self.config().increase_state_access_gas && !self.access_address(target) {
+                    cost += G_COLD_ACCOUNT_ACCESS;
+                }
+
+                if !self.config().decrease_clears_refund && !state.deleted(context.address) {
+                    self.record_refund(R_SELFDESTRUCT);
+                }
+
+                cost
+            }
+            PUSH0 if self.config().has_push0 => G_BASE,
+            BASEFEE if self.config().has_base_fee => G_BASE,
+            // Undefined opcodes, execution fails in the runtime
+            _ => G_ZERO,
+        };
+
+        self.record_cost(cost)
     }
 
-    pub fn record_transfer<B>(&mut self, state: &ExecutorState<B>, target: H160, value: U256)
+    /// Charges an SSTORE following the net gas metering rules of EIP-2200, using the value
+    /// the slot had at the beginning of the transaction.
+    /// Since EIP-2929 the first access to the slot is charged on top of it and reads are cheaper,
+    /// EIP-3529 lowers the refund for clearing the slot
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfGas` if the current frame can't pay for the write,
+    /// or if no more than the call stipend is left in the frame
+    pub fn record_storage_write<B>(&mut self, state: &ExecutorState<B>, address: H160, key: U256, value: U256) -> Result<(), ExitError>
     where
         B: StorageInterface
     {
+        if self.gas_left() <= G_SSTORE_SENTRY {
+            return Err(ExitError::OutOfGas);
+        }
+
+        let (access_cost, sload, sstore_reset) = if self.config().increase_state_access_gas {
+            let access_cost = if self.access_storage(address, key) { 0 } else { G_COLD_SLOAD };
+            (access_cost, G_WARM_STORAGE_READ, G_SSTORE_RESET - G_COLD_SLOAD)
+        } else {
+            (0, G_SLOAD, G_SSTORE_RESET)
+        };
+
+        let sstore_clears = if self.config().decrease_clears_refund { R_SSTORE_CLEARS_EIP3529 } else { R_SSTORE_CLEARS };
+
+        let current = state.storage(address, key);
+        let original = state.original_storage(address, key).unwrap_or_default();
+
+        if current == value {
+            return self.record_cost(access_cost + sload);
+        }
+
+        if original == current {
+            if original.is_zero() {
+                return self.record_cost(access_cost + G_SSTORE_SET);
+            }
+
+            if value.is_zero() {
+                self.record_refund(sstore_clears);
+            }
+
+            return self.record_cost(access_cost + sstore_reset);
+        }
+
+        if !original.is_zero() {
+            if current.is_zero() {
+                self.record_refund(-sstore_clears);
+            } else if value.is_zero() {
+                self.record_refund(sstore_clears);
+            }
+        }
+
+        if original == value {
+            if original.is_zero() {
+                self.record_refund(signed(G_SSTORE_SET - sload));
+            } else {
+                self.record_refund(signed(sstore_reset - sload));
+            }
+        }
+
+        self.record_cost(access_cost + sload)
+    }
+
+    /// Charges the gas forwarded to a nested call: the requested gas, but no more than all but one 64th
+    /// of the gas left (EIP-150). Calls transferring value get the call stipend on top of it for free.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfGas` if the current frame can't pay for the forwarded gas
+    pub fn record_call(&mut self, target_gas: Option<u64>, transfers_value: bool) -> Result<u64, ExitError> {
+        let available = all_but_one_64th(self.gas_left());
+        let gas_limit = target_gas.map_or(available, |target_gas| target_gas.min(available));
+
+        self.record_cost(gas_limit)?;
+
+        if transfers_value {
+            Ok(gas_limit + G_CALLSTIPEND)
+        } else {
+            Ok(gas_limit)
+        }
+    }
+
+    /// Charges the gas forwarded to a nested contract creation, all but one 64th of the gas left (EIP-150)
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfGas` if the current frame can't pay for the forwarded gas
+    pub fn record_create(&mut self) -> Result<u64, ExitError> {
+        let gas_limit = all_but_one_64th(self.gas_left());
+        self.record_cost(gas_limit)?;
+
+        Ok(gas_limit)
+    }
+
+    /// Charges the code deposit of a created contract to the frame that created it
+    ///
+    /// # Errors
+    ///
+    /// Returns `OutOfGas` if the creating frame can't pay for storing the code
+    pub fn record_deploy(&mut self, code_size: usize) -> Result<(), ExitError> {
+        let code_size: u64 = code_size.try_into().map_err(|_| ExitError::OutOfGas)?;
+        let cost = code_size.checked_mul(G_CODEDEPOSIT).ok_or(ExitError::OutOfGas)?;
 
+        self.record_cost(cost)
     }
 }
+
+impl Default for Gasometer {
+    fn default() -> Self {
+        Self::new(Hardfork::default())
+    }
+}
+
+/// Cost of opcodes that doesn't depend on the arguments, the state or the hardfork
+fn static_cost(opcode: Opcode) -> Option<u64> {
+    let cost = match opcode {
+        Opcode::STOP => G_ZERO,
+
+        Opcode::ADDRESS | Opcode::ORIGIN | Opcode::CALLER | Opcode::CALLVALUE | Opcode::CALLDATASIZE
+        | Opcode::CODESIZE | Opcode::GASPRICE | Opcode::COINBASE | Opcode::TIMESTAMP | Opcode::NUMBER
+        | Opcode::DIFFICULTY | Opcode::GASLIMIT | Opcode::RETURNDATASIZE | Opcode::POP | Opcode::PC
+        | Opcode::MSIZE | Opcode::GAS | Opcode::CHAINID => G_BASE,
+
+        Opcode::ADD | Opcode::SUB | Opcode::NOT | Opcode::LT | Opcode::GT | Opcode::SLT | Opcode::SGT
+        | Opcode::EQ | Opcode::ISZERO | Opcode::AND | Opcode::OR | Opcode::XOR | Opcode::BYTE
+        | Opcode::SHL | Opcode::SHR | Opcode::SAR | Opcode::CALLDATALOAD => G_VERYLOW,
+
+        Opcode::MUL | Opcode::DIV | Opcode::SDIV | Opcode::MOD | Opcode::SMOD | Opcode::SIGNEXTEND
+        | Opcode::SELFBALANCE => G_LOW,
+
+        Opcode::ADDMOD | Opcode::MULMOD | Opcode::JUMP => G_MID,
+
+        Opcode::JUMPI => G_HIGH,
+
+        Opcode::JUMPDEST => G_JUMPDEST,
+
+        Opcode::BLOCKHASH => G_BLOCKHASH,
+
+        // PUSH1-PUSH32, DUP1-DUP16, SWAP1-SWAP16
+        _ if (Opcode::PUSH1.as_u8()..=Opcode::SWAP16.as_u8()).contains(&opcode.as_u8()) => G_VERYLOW,
+
+        _ => return None,
+    };
+
+    Some(cost)
+}
+
+/// Total cost of a memory of `words` 32 byte words
+fn memory_cost(words: u64) -> Result<u64, ExitError> {
+    let words = u128::from(words);
+    let cost = u128::from(G_MEMORY) * words + words * words / G_QUADCOEFFDIV;
+
+    cost.try_into().map_err(|_| ExitError::OutOfGas)
+}
+
+/// Cost of `cost_per_word` for every 32 byte word of `len` bytes
+fn words_cost(cost_per_word: u64, len: U256) -> Result<u64, ExitError> {
+    let words = to_u64(len)?.saturating_add(31) / 32;
+
+    words.checked_mul(cost_per_word).ok_or(ExitError::OutOfGas)
+}
+
+fn add_cost(a: u64, b: u64) -> Result<u64, ExitError> {
+    a.checked_add(b).ok_or(ExitError::OutOfGas)
+}
+
+fn all_but_one_64th(gas: u64) -> u64 {
+    gas - gas / 64
+}
+
+fn signed(refund: u64) -> i64 {
+    refund.try_into().expect("refunds are small constants")
+}
+
+/// Anything that doesn't fit in u64 can never be paid for
+fn to_u64(value: U256) -> Result<u64, ExitError> {
+    if value > U256::from(u64::MAX) {
+        Err(ExitError::OutOfGas)
+    } else {
+        Ok(value.as_u64())
+    }
+}
+
+fn stack_u256(stack: &Stack, no_from_top: usize) -> Result<U256, ExitError> {
+    stack.peek(no_from_top).map(|value| U256::from_big_endian(&value[..]))
+}
@@ -0,0 +1,119 @@
+use evm::Opcode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// PUSH0 pushes a zero word without reading any immediate bytes (EIP-3855, Shanghai)
+pub const PUSH0: Opcode = Opcode(0x5f);
+
+/// BASEFEE pushes the base fee of the current block (EIP-3198, London)
+pub const BASEFEE: Opcode = Opcode(0x48);
+
+/// Ethereum hardfork whose rules the Terranova EVM follows.\
+/// Every hardfork includes the changes of the hardforks before it, the chain can only be upgraded to a later one
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Hardfork {
+    Istanbul,
+    /// EIP-2929 warm and cold state access costs, EIP-2565 ModExp pricing and EIP-2930 access list transactions
+    Berlin,
+    /// EIP-3529 reduced refunds, EIP-3541 rejection of new code starting with 0xEF, EIP-1559 dynamic fee transactions
+    /// and EIP-3198 BASEFEE
+    London,
+    /// EIP-3855 PUSH0, EIP-3860 init code size limit and metering and EIP-3651 warm COINBASE
+    Shanghai,
+}
+
+impl Default for Hardfork {
+    /// Deployments made before the hardfork was configurable follow the Istanbul rules
+    fn default() -> Self {
+        Hardfork::Istanbul
+    }
+}
+
+impl Hardfork {
+    #[must_use]
+    pub fn config(self) -> &'static HardforkConfig {
+        match self {
+            Hardfork::Istanbul => &ISTANBUL,
+            Hardfork::Berlin => &BERLIN,
+            Hardfork::London => &LONDON,
+            Hardfork::Shanghai => &SHANGHAI,
+        }
+    }
+}
+
+/// Execution rules of a hardfork, replaces the static `evm_runtime::CONFIG` in the Machine and the Gasometer
+#[derive(Debug, PartialEq, Eq)]
+pub struct HardforkConfig {
+    /// Maximum depth of nested calls and creates
+    pub call_stack_limit: usize,
+    /// Maximum size of the code of a created contract (EIP-170)
+    pub create_contract_limit: Option<usize>,
+    /// Whether empty accounts are considered to exist, false since EIP-161
+    pub empty_considered_exists: bool,
+    /// Whether created contracts start with a nonce of 1 (EIP-161)
+    pub create_increase_nonce: bool,
+    /// Accessing an account or a storage slot for the first time in a transaction costs more (EIP-2929)
+    pub increase_state_access_gas: bool,
+    /// Clearing a storage slot refunds less, and SELFDESTRUCT refunds nothing (EIP-3529)
+    pub decrease_clears_refund: bool,
+    /// Refunds are capped to the used gas divided by this quotient, 2 before EIP-3529 and 5 after
+    pub max_refund_quotient: u64,
+    /// Created contracts can't have code starting with the 0xEF byte (EIP-3541)
+    pub disallow_executable_format: bool,
+    /// Whether the PUSH0 opcode is defined (EIP-3855)
+    pub has_push0: bool,
+    /// Whether the BASEFEE opcode is defined (EIP-3198)
+    pub has_base_fee: bool,
+    /// Maximum size of the init code of a contract creation, whose every word is charged (EIP-3860)
+    pub create_initcode_limit: Option<usize>,
+    /// Whether the coinbase is warm from the start of the transaction (EIP-3651)
+    pub warm_coinbase: bool,
+    /// The ModExp precompile is priced by EIP-2565 instead of EIP-198
+    pub decrease_modexp_gas: bool,
+    /// Whether access list transactions are accepted (EIP-2930)
+    pub has_access_list_tx: bool,
+    /// Whether dynamic fee transactions are accepted (EIP-1559)
+    pub has_dynamic_fee_tx: bool,
+}
+
+const ISTANBUL: HardforkConfig = HardforkConfig {
+    call_stack_limit: 1024,
+    create_contract_limit: Some(0x6000),
+    empty_considered_exists: false,
+    create_increase_nonce: true,
+    increase_state_access_gas: false,
+    decrease_clears_refund: false,
+    max_refund_quotient: 2,
+    disallow_executable_format: false,
+    has_push0: false,
+    has_base_fee: false,
+    create_initcode_limit: None,
+    warm_coinbase: false,
+    decrease_modexp_gas: false,
+    has_access_list_tx: false,
+    has_dynamic_fee_tx: false,
+};
+
+const BERLIN: HardforkConfig = HardforkConfig {
+    increase_state_access_gas: true,
+    decrease_modexp_gas: true,
+    has_access_list_tx: true,
+    ..ISTANBUL
+};
+
+const LONDON: HardforkConfig = HardforkConfig {
+    decrease_clears_refund: true,
+    max_refund_quotient: 5,
+    disallow_executable_format: true,
+    has_dynamic_fee_tx: true,
+    has_base_fee: true,
+    ..BERLIN
+};
+
+const SHANGHAI: HardforkConfig = HardforkConfig {
+    has_push0: true,
+    create_initcode_limit: Some(2 * 0x6000),
+    warm_coinbase: true,
+    ..LONDON
+};
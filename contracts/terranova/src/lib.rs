@@ -14,10 +14,17 @@ pub mod executor_state;
 pub mod executor;
 pub mod utils;
 pub mod gasometer;
+pub mod precompiles;
 pub mod transaction;
 pub mod config;
+pub mod hardfork;
 pub mod airdrop;
 pub mod tx_chunk;
+pub mod receipt;
+pub mod pending_tx;
+pub mod operator;
+pub mod erc20;
+pub mod wasm_call;
 
 pub use crate::error::{ContractError};
 
@@ -0,0 +1,28 @@
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
+use evm::{H160, H256};
+
+use crate::ContractError;
+use crate::operator;
+use crate::tx_chunk;
+
+pub fn process(deps: DepsMut, env: Env, info: MessageInfo, caller_address_bytes: [u8; 20], full_tx_hash: [u8; 32]) -> Result<Response, ContractError> {
+    let caller_address = H160::from_slice(&caller_address_bytes);
+    let tx_hash = H256::from_slice(&full_tx_hash);
+
+    let chunked_tx = tx_chunk::chunked_tx(deps.storage, caller_address, tx_hash)?
+        .ok_or_else(|| E!(ContractError::UnknownChunkedTx; "No chunks stored for transaction {} of {}", tx_hash, caller_address))?;
+
+    // Anyone can purge expired chunks, only operators can cancel the chunks they are still storing
+    if env.block.height < chunked_tx.expiry_height {
+        operator::assert_operator(deps.as_ref(), &info.sender)
+            .map_err(|_| E!(ContractError::ChunksNotExpired; "Chunks of transaction {} expire at height {}", tx_hash, chunked_tx.expiry_height))?;
+    }
+
+    tx_chunk::remove_chunks(deps.storage, caller_address, tx_hash);
+
+    let response = Response::new()
+        .add_attribute("action", "cancel_chunked_tx")
+        .add_attribute("tx_hash", hex::encode(tx_hash));
+
+    Ok(response)
+}
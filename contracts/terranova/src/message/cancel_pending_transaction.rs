@@ -0,0 +1,44 @@
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
+use evm::H256;
+
+use crate::{
+    storage::{CwStorageInterface, StorageInterface},
+    config::token_mint_dummy,
+    operator,
+    pending_tx::PENDING_TX_TIMEOUT,
+    ContractError,
+};
+
+/// Nothing the transaction did is applied, its locks are released and the prepaid gas fee is refunded.\ 
+/// The caller nonce isn't used, the transaction can be submitted again
+pub fn process(deps: DepsMut, env: Env, info: MessageInfo, tx_hash: [u8; 32]) -> Result<Response, ContractError> {
+    operator::assert_admin(deps.as_ref(), &info.sender)?;
+    let tx_hash = H256::from_slice(&tx_hash);
+
+    let mut storage = CwStorageInterface::new_mut(
+        deps, 
+        env, 
+        token_mint_dummy()
+    )?;
+    let pending_tx = storage.pending_tx(&tx_hash)?
+        .ok_or_else(|| E!(ContractError::UnknownPendingTx; "Transaction {} is not in progress", tx_hash))?;
+
+    let height = storage.block_number().as_u64();
+    if height < pending_tx.updated_height.saturating_add(PENDING_TX_TIMEOUT) {
+        return Err!(ContractError::PendingTxNotExpired; "Transaction {} was continued at height {}, current height is {}", tx_hash, pending_tx.updated_height, height);
+    }
+
+    storage.hold_locks(pending_tx.locks);
+    storage.unlock_accounts()?;
+    storage.remove_pending_tx(&tx_hash);
+
+    let fee_collector = storage.config().fee_collector;
+    let prepaid = pending_tx.prepaid;
+    storage.settle_gas(&prepaid.caller, &fee_collector, prepaid.prepaid_fee, 0.into())?;
+
+    let response = Response::new()
+        .add_attribute("action", "cancel_pending_tx")
+        .add_attribute("tx_hash", hex::encode(tx_hash));
+
+    Ok(response)
+}
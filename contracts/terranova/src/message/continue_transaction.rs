@@ -0,0 +1,103 @@
+use std::collections::BTreeSet;
+
+use cosmwasm_std::{Binary, DepsMut, Env, Response};
+use evm::{H160, H256};
+
+use crate::{
+    storage::{CwStorageInterface, StorageInterface},
+    config::token_mint_dummy,
+    ContractError,
+    executor::Machine,
+    pending_tx::PendingTx,
+};
+
+use super::execute_simple_transaction::{self, Execution, PrepaidTransaction};
+
+/// Outcome of running a transaction in progress for a bounded number of steps
+pub enum Step {
+    /// The step limit was reached
+    Paused {
+        /// The Machine serialized by Machine::save
+        machine: Vec<u8>,
+        steps_executed: u64,
+        /// Accounts changed so far, to be write locked
+        written: BTreeSet<H160>,
+    },
+    Completed(Execution),
+}
+
+pub fn process(deps: DepsMut, env: Env, tx_hash: [u8; 32], max_steps: u64) -> Result<Response, ContractError> {
+    let tx_hash = H256::from_slice(&tx_hash);
+
+    let mut storage = CwStorageInterface::new_mut(
+        deps, 
+        env, 
+        token_mint_dummy()
+    )?;
+    let pending_tx = storage.pending_tx(&tx_hash)?
+        .ok_or_else(|| E!(ContractError::UnknownPendingTx; "Transaction {} is not in progress", tx_hash))?;
+    storage.hold_locks(pending_tx.locks);
+
+    let step = {
+        let machine = Machine::restore(&pending_tx.machine, &storage)?;
+        run(machine, max_steps)?
+    };
+
+    finish_step(storage, pending_tx.prepaid, step, Response::new())
+}
+
+/// Executes up to `max_steps` steps, the Machine is saved if execution hasn't completed
+pub fn run<B: StorageInterface>(mut machine: Machine<B>, max_steps: u64) -> Result<Step, ContractError> {
+    match machine.execute_n_steps(max_steps) {
+        Ok(()) => {
+            let steps_executed = machine.get_steps_executed();
+            let written = machine.written_addresses();
+            Ok(Step::Paused { machine: machine.save()?, steps_executed, written })
+        },
+        Err((result, exit_reason)) => Ok(Step::Completed(Execution::new(machine, result, exit_reason))),
+    }
+}
+
+/// A paused transaction is saved to PENDING_TXS and locks the accounts it accessed,
+/// a completed one is settled like a transaction executed in a single message and releases its locks.\ 
+/// Fails without saving anything if the transaction accessed an account locked by another transaction in progress
+pub fn finish_step(
+    mut storage: CwStorageInterface<DepsMut>,
+    prepaid: PrepaidTransaction,
+    step: Step,
+    response: Response,
+) -> Result<Response, ContractError> {
+    let tx_hash = prepaid.tx_hash;
+    storage.check_read_locks()?;
+
+    match step {
+        Step::Paused { machine, steps_executed, mut written } => {
+            // The prepaid gas fee is already deducted from the caller balance
+            written.insert(prepaid.caller);
+            let locks = storage.held_locks().extend(&storage.accessed_accounts(), &written);
+            storage.lock_accounts(locks.clone())?;
+
+            let updated_height = storage.block_number().as_u64();
+            storage.save_pending_tx(&PendingTx {
+                prepaid,
+                machine: Binary(machine),
+                locks,
+                updated_height,
+            })?;
+
+            Ok(response
+                .add_attribute("tx_status", "pending")
+                .add_attribute("steps_executed", steps_executed.to_string())
+                .add_attribute("tx_hash", hex::encode(tx_hash)))
+        },
+        Step::Completed(execution) => {
+            storage.remove_pending_tx(&tx_hash);
+
+            let response = response.add_attribute("tx_status", "completed");
+            let response = execute_simple_transaction::settle(&mut storage, &prepaid, execution, response)?;
+            storage.unlock_accounts()?;
+
+            Ok(response)
+        },
+    }
+}
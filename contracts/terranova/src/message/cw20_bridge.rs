@@ -0,0 +1,69 @@
+use cosmwasm_std::{from_binary, DepsMut, Env, MessageInfo, Response};
+use cw20::{Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use evm::H160;
+
+use crate::{
+    storage::{backend::CW20_FACADES, CwStorageInterface},
+    config::token_mint_dummy,
+    erc20::Cw20Token,
+    operator,
+    ContractError,
+};
+
+use super::Cw20HookMsg;
+
+/// Admin only, the name, symbol and decimals of the facade are read from the CW20 contract
+pub fn register(deps: DepsMut, env: Env, info: MessageInfo, cw20_address: String) -> Result<Response, ContractError> {
+    operator::assert_admin(deps.as_ref(), &info.sender)?;
+
+    let contract = deps.api.addr_validate(&cw20_address)?;
+    let token_info: TokenInfoResponse = deps.querier.query_wasm_smart(&contract, &Cw20QueryMsg::TokenInfo {})?;
+    let token = Cw20Token {
+        contract,
+        name: token_info.name,
+        symbol: token_info.symbol,
+        decimals: token_info.decimals,
+    };
+
+    let mut storage = CwStorageInterface::new_mut(
+        deps,
+        env,
+        token_mint_dummy()
+    )?;
+    let facade = storage.register_cw20(&token)?;
+
+    let response = Response::new()
+        .add_attribute("action", "register_cw20")
+        .add_attribute("cw20_address", token.contract)
+        .add_attribute("evm_address", hex::encode(facade));
+
+    Ok(response)
+}
+
+/// Called by a CW20 contract when tokens are sent to the Terranova contract, the sender of the message must be a registered CW20
+pub fn receive(deps: DepsMut, env: Env, info: MessageInfo, receive_msg: Cw20ReceiveMsg) -> Result<Response, ContractError> {
+    let facade = CW20_FACADES.may_load(deps.storage, &info.sender)?
+        .ok_or_else(|| E!(ContractError::UnknownCw20Token; "{} is not a registered CW20", info.sender))?;
+
+    match from_binary(&receive_msg.msg)? {
+        Cw20HookMsg::Deposit { evm_address } => {
+            let evm_address = H160::from_slice(&evm_address);
+
+            let mut storage = CwStorageInterface::new_mut(
+                deps,
+                env,
+                token_mint_dummy()
+            )?;
+            storage.deposit_cw20(&facade, &evm_address, receive_msg.amount)?;
+
+            let response = Response::new()
+                .add_attribute("action", "deposit_cw20")
+                .add_attribute("cw20_address", info.sender)
+                .add_attribute("sender", receive_msg.sender)
+                .add_attribute("evm_address", hex::encode(evm_address))
+                .add_attribute("amount", receive_msg.amount);
+
+            Ok(response)
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use cosmwasm_std::{Coin, DepsMut, Env, MessageInfo, Response, Uint128};
+use evm::H160;
+
+use crate::{
+    storage::CwStorageInterface,
+    config::token_mint_dummy,
+    ContractError,
+};
+
+/// Anyone can deposit, the deposited coins are paid by the message sender
+pub fn process(deps: DepsMut, env: Env, info: MessageInfo, evm_address_bytes: [u8; 20]) -> Result<Response, ContractError> {
+    let evm_address = H160::from_slice(&evm_address_bytes);
+
+    let storage = CwStorageInterface::new_mut(
+        deps,
+        env,
+        token_mint_dummy()
+    )?;
+    let amount = validate(&storage, &info.funds)?;
+
+    execute(storage, evm_address, amount)
+}
+
+/// Exactly one coin of the configured denom with a positive amount must be sent
+pub fn validate(storage: &CwStorageInterface<DepsMut>, funds: &[Coin]) -> Result<Uint128, ContractError> {
+    let denom = &storage.config().denom;
+
+    match funds {
+        [coin] if coin.denom == *denom && !coin.amount.is_zero() => Ok(coin.amount),
+        _ => Err!(ContractError::InvalidFunds; "Deposit funds {:?}, expected a positive amount of {}", funds, denom),
+    }
+}
+
+pub fn execute(mut storage: CwStorageInterface<DepsMut>, evm_address: H160, amount: Uint128) -> Result<Response, ContractError> {
+    storage.deposit_native_tokens(&evm_address, amount)?;
+
+    let response = Response::new()
+        .add_attribute("action", "deposit")
+        .add_attribute("evm_address", hex::encode(evm_address))
+        .add_attribute("amount", amount);
+
+    Ok(response)
+}
@@ -0,0 +1,151 @@
+use std::{collections::BTreeMap, convert::TryInto};
+
+use cosmwasm_std::{Deps, Env, Uint256};
+use evm::{ExitReason, H160, U256};
+
+use crate::{
+    transaction::{TransactionType, UnsignedTransaction},
+    storage::{CwStorageInterface, StorageInterface, overrides::{AccountOverride, BlockOverride, OverrideStorage}},
+    config::token_mint_dummy,
+    ContractError,
+    executor::Machine,
+};
+
+use super::{EthCallMsg, EthCallResponse, RevertReason};
+
+/// Gas limit of a call that doesn't specify one
+pub const ETH_CALL_GAS_LIMIT: u64 = 50_000_000;
+
+/// Selector of Error(string), the revert data of `require` and `revert` with a message
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of Panic(uint256), the revert data of failed `assert`, arithmetic overflows and the other checks added by Solidity
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+pub fn process(deps: Deps, env: Env, msg: EthCallMsg) -> Result<EthCallResponse, ContractError> {
+    let storage = CwStorageInterface::new_ref(
+        deps, 
+        env, 
+        token_mint_dummy()
+    )?;
+
+    let block = msg.block.map(|block| BlockOverride {
+        number: block.number.map(U256::from),
+        timestamp: block.timestamp.map(U256::from),
+    }).unwrap_or_default();
+
+    let accounts = msg.state_overrides.into_iter()
+        .map(|state| {
+            let code = state.code.map(|code| {
+                let valids = evm::Valids::compute(&code);
+                (code, valids)
+            });
+            let account = AccountOverride {
+                balance: state.balance.map(uint256_to_u256),
+                code,
+                storage: state.storage.iter()
+                    .map(|slot| (U256::from_big_endian(&slot.key), U256::from_big_endian(&slot.value)))
+                    .collect(),
+            };
+
+            (H160::from_slice(&state.address), account)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let overrides = OverrideStorage::new(&storage, block, accounts);
+    let caller_address = msg.from.map_or_else(H160::zero, |from| H160::from_slice(&from));
+
+    let trx = UnsignedTransaction {
+        tx_type: TransactionType::Legacy,
+        nonce: overrides.nonce(&caller_address).low_u64(),
+        gas_price: U256::zero(),
+        max_priority_fee_per_gas: U256::zero(),
+        gas_limit: msg.gas_limit.unwrap_or(ETH_CALL_GAS_LIMIT).into(),
+        to: msg.to.map(|to| H160::from_slice(&to)),
+        value: uint256_to_u256(msg.value),
+        call_data: msg.data,
+        access_list: Vec::new(),
+        chain_id: None,
+        rlp_len: 0,
+    };
+
+    execute(&overrides, caller_address, trx)
+}
+
+/// Runs the call like a transaction without a gas fee, its state changes are discarded like in eth_call
+pub fn execute<B: StorageInterface>(backend: &B, caller_address: H160, trx: UnsignedTransaction) -> Result<EthCallResponse, ContractError> {
+    let mut executor = Machine::new(caller_address, trx.gas_price, backend)?;
+    executor.gasometer_mut().record_transaction_size(&trx);
+
+    // Calls to native precompiles respond with messages that are not sent by queries
+    let _response = match trx.to {
+        Some(code_address) => {
+            executor.call_begin(
+                caller_address, 
+                code_address,
+                trx.call_data,
+                trx.value, 
+                trx.gas_limit
+            )?
+        },
+        None => {
+            executor.create_begin(
+                caller_address,
+                trx.call_data,
+                trx.value,
+                trx.gas_limit
+            )?
+        },
+    };
+
+    let (result, exit_reason) = executor.execute();
+    debug_print!("result, exit_reason of executor.execute(): {:?}, {:?}", result, exit_reason);
+
+    let revert_reason = match exit_reason {
+        ExitReason::Revert(_) => decode_revert_reason(&result),
+        _ => None,
+    };
+
+    Ok(EthCallResponse {
+        exit_reason: format!("{:?}", exit_reason),
+        success: exit_reason.is_succeed(),
+        gas_used: Uint256::from_be_bytes(executor.used_gas().to_bytes()),
+        result,
+        revert_reason,
+    })
+}
+
+/// Solidity revert data is the ABI encoding of an Error(string) or Panic(uint256) call,
+/// None for custom errors and malformed data
+fn decode_revert_reason(data: &[u8]) -> Option<RevertReason> {
+    let (selector, args) = (data.get(..4)?, data.get(4..)?);
+
+    if selector == ERROR_SELECTOR {
+        let offset = abi_usize(args, 0)?;
+        let length = abi_usize(args, offset)?;
+        let start = offset.checked_add(32)?;
+        let message = args.get(start..start.checked_add(length)?)?;
+
+        String::from_utf8(message.to_vec()).ok().map(RevertReason::Error)
+    } else if selector == PANIC_SELECTOR {
+        let code: [u8; 32] = args.get(..32)?.try_into().ok()?;
+
+        Some(RevertReason::Panic(Uint256::from_be_bytes(code)))
+    } else {
+        None
+    }
+}
+
+/// ABI word at `offset` as an offset or a length, None if it is out of bounds or doesn't fit in a usize
+fn abi_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = U256::from_big_endian(data.get(offset..offset.checked_add(32)?)?);
+    if word > U256::from(usize::MAX) {
+        return None;
+    }
+
+    Some(word.as_usize())
+}
+
+fn uint256_to_u256(value: Uint256) -> U256 {
+    U256::from_big_endian(&value.to_be_bytes())
+}
@@ -11,6 +11,10 @@ pub fn process(deps: DepsMut, env: Env, caller_address_bytes: [u8; 20], full_tx_
     let caller_address = H160::from_slice(&caller_address_bytes);
     let full_tx_hash = H256::from_slice(&full_tx_hash);
 
+    if let Some(chunked_tx) = tx_chunk::chunked_tx(deps.storage, caller_address, full_tx_hash)? {
+        tx_chunk::check_not_expired(&chunked_tx, env.block.height, full_tx_hash)?;
+    }
+
     let collected_tx_bytes = tx_chunk::collect_chunks(deps.as_ref(), caller_address, full_tx_hash, chunk_count)?;
     
     // Check that the hash of the merged transaction chunks match the provided transaction hash
@@ -18,6 +22,9 @@ pub fn process(deps: DepsMut, env: Env, caller_address_bytes: [u8; 20], full_tx_
         return Err(ContractError::InvalidTxChunks)
     }
 
+    // The chunks are only removed if the transaction is executed, a failed validation reverts the removal
+    tx_chunk::remove_chunks(deps.storage, caller_address, full_tx_hash);
+
     // Validation and execution will be handled in execute_simple_transaction
     execute_simple_transaction::process(deps, env, caller_address_bytes, collected_tx_bytes)
 }
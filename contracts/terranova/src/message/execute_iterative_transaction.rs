@@ -0,0 +1,38 @@
+use cosmwasm_std::{DepsMut, Env, Response};
+use evm::H160;
+
+use crate::{
+    transaction::{unsigned_tx_hash, UnsignedTransaction}, 
+    storage::CwStorageInterface, 
+    config::token_mint_dummy,
+    ContractError, 
+};
+
+use super::{continue_transaction, execute_simple_transaction};
+
+/// Prepays the gas fee and runs the transaction for up to `max_steps` steps,
+/// if it doesn't complete it is continued with ContinueTx until it does
+pub fn process(deps: DepsMut, env: Env, caller_address_bytes: [u8; 20], unsigned_tx: Vec<u8>, max_steps: u64) -> Result<Response, ContractError> {
+    let caller_address = H160::from_slice(&caller_address_bytes);
+    let trx = UnsignedTransaction::from_rlp(&unsigned_tx)?;
+    let tx_hash = unsigned_tx_hash(&caller_address, &unsigned_tx);
+
+    let mut storage = CwStorageInterface::new_mut(
+        deps, 
+        env, 
+        token_mint_dummy()
+    )?;
+    if storage.pending_tx(&tx_hash)?.is_some() {
+        return Err!(ContractError::TxAlreadyPending; "Transaction {} is already in progress", tx_hash);
+    }
+    execute_simple_transaction::validate(&storage, caller_address, &trx)?;
+
+    let prepaid = execute_simple_transaction::prepay(&mut storage, caller_address, &trx, tx_hash)?;
+
+    let (step, response) = {
+        let (machine, response) = execute_simple_transaction::begin(&storage, &prepaid, trx)?;
+        (continue_transaction::run(machine, max_steps)?, response)
+    };
+
+    continue_transaction::finish_step(storage, prepaid, step, response)
+}
@@ -0,0 +1,38 @@
+use cosmwasm_std::{DepsMut, Env, Response};
+use evm::{H160, U256};
+
+use crate::{
+    transaction::SignedTransaction,
+    storage::{CwStorageInterface, StorageInterface},
+    config::token_mint_dummy,
+    ContractError,
+    utils::keccak256_h256,
+};
+
+use super::execute_simple_transaction;
+
+pub fn process(deps: DepsMut, env: Env, signed_tx: Vec<u8>) -> Result<Response, ContractError> {
+    let trx = SignedTransaction::from_rlp(&signed_tx)?;
+    let tx_hash = keccak256_h256(&signed_tx);
+    let caller_address = trx.recover_caller(deps.api)?;
+
+    let storage = CwStorageInterface::new_mut(
+        deps,
+        env,
+        token_mint_dummy()
+    )?;
+    validate(&storage, caller_address, &trx)?;
+
+    // Once the sender is known, execution is identical to an unsigned transaction
+    execute_simple_transaction::execute(storage, caller_address, trx.unsigned, tx_hash)
+}
+
+/// Only replay protected (EIP-155) transactions signed for this chain are accepted
+pub fn validate(storage: &CwStorageInterface<DepsMut>, caller_address: H160, trx: &SignedTransaction) -> Result<(), ContractError> {
+    match trx.unsigned.chain_id {
+        Some(chain_id) if chain_id == U256::from(storage.chain_id()) => (),
+        chain_id => return Err!(ContractError::InvalidChainId; "Transaction chain_id={:?}, expected {}", chain_id, storage.chain_id()),
+    }
+
+    execute_simple_transaction::validate(storage, caller_address, &trx.unsigned)
+}
@@ -1,98 +1,264 @@
-use cosmwasm_std::{Addr, DepsMut, Env, Response};
-use evm::H160;
+use cosmwasm_std::{Addr, DepsMut, Env, Event, Response};
+use evm::{backend::Log, ExitReason, H160, H256, U256};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    transaction::UnsignedTransaction, 
-    storage::{CwStorageInterface}, 
-    config::{token_mint_dummy, chain_id_dummy},
+    transaction::{unsigned_tx_hash, UnsignedTransaction}, 
+    storage::{CwStorageInterface, StorageInterface}, 
+    config::token_mint_dummy,
     ContractError, 
-    executor::Machine
+    executor::Machine,
+    executor_state::ApplyState,
+    receipt::{logs_bloom, TransactionReceipt},
+    utils::keccak256_h256,
 };
 
 pub fn process(deps: DepsMut, env: Env, caller_address_bytes: [u8; 20], unsigned_tx: Vec<u8>) -> Result<Response, ContractError> {
     let caller_address = H160::from_slice(&caller_address_bytes);
     let trx = UnsignedTransaction::from_rlp(&unsigned_tx)?;
+    let tx_hash = unsigned_tx_hash(&caller_address, &unsigned_tx);
 
     let storage = CwStorageInterface::new_mut(
         deps, 
         env, 
-        token_mint_dummy(), 
-        chain_id_dummy()
+        token_mint_dummy()
     )?;
-    validate()?;
+    validate(&storage, caller_address, &trx)?;
 
-    execute(storage, caller_address, trx)
+    execute(storage, caller_address, trx, tx_hash)
 }
 
-/// Implement this later
-/// Contract validation, as well as validating EVM transaction signature
-pub fn validate() -> Result<(), ContractError> {
+/// The transaction nonce must be exactly the current nonce of the caller account, 
+/// so that a transaction can't be replayed or executed out of order.
+/// The caller must also be able to afford the full gas limit at the max fee per gas and the transferred value upfront
+pub fn validate(storage: &CwStorageInterface<DepsMut>, caller_address: H160, trx: &UnsignedTransaction) -> Result<(), ContractError> {
+    if !trx.tx_type.is_enabled(storage.hardfork().config()) {
+        return Err!(ContractError::InvalidTransactionData; "Transaction type {:?} is not accepted under {:?}", trx.tx_type, storage.hardfork());
+    }
+
+    if trx.max_priority_fee_per_gas > trx.gas_price {
+        return Err!(ContractError::InvalidTransactionData; "Max priority fee per gas {} is higher than max fee per gas {}", trx.max_priority_fee_per_gas, trx.gas_price);
+    }
+
+    let account_nonce = storage.nonce(&caller_address);
+    let trx_nonce = U256::from(trx.nonce);
+
+    if trx_nonce < account_nonce {
+        return Err!(ContractError::NonceTooLow; "Account {} - nonce {} is lower than account nonce {}", caller_address, trx_nonce, account_nonce);
+    }
+
+    if trx_nonce > account_nonce {
+        return Err!(ContractError::NonceTooHigh; "Account {} - nonce {} is higher than account nonce {}", caller_address, trx_nonce, account_nonce);
+    }
+
+    let balance = storage.balance(&caller_address);
+    let required_balance = max_gas_fee(trx)
+        .and_then(|max_fee| max_fee.checked_add(trx.value))
+        .ok_or_else(|| E!(ContractError::InsufficientFunds; "Account {} - gas fee and value overflow", caller_address))?;
+
+    if balance < required_balance {
+        return Err!(ContractError::InsufficientFunds; "Account {} - balance {} is lower than required {}", caller_address, balance, required_balance);
+    }
+
     Ok(())
 }
 
-pub fn execute(mut storage: CwStorageInterface<DepsMut>, caller_address: H160, trx: UnsignedTransaction) -> Result<Response, ContractError> {
-    let (exit_reason, return_value, apply_state, used_gas, response) = {
-        let mut executor = Machine::new(caller_address, &storage)?;
-        executor.gasometer_mut().record_transaction_size(&trx);
-
-        let response: Response = match trx.to {
-            Some(code_address) => {
-                executor.call_begin(
-                    caller_address, 
-                    code_address,
-                    trx.call_data,
-                    trx.value, 
-                    trx.gas_limit
-                )?
-            },
-            None => {
-                executor.create_begin(
-                    caller_address,
-                    trx.call_data,
-                    trx.value,
-                    trx.gas_limit
-                )?
-            },
+/// Highest fee the transaction allows, at the max fee per gas
+fn max_gas_fee(trx: &UnsignedTransaction) -> Option<U256> {
+    trx.gas_limit.checked_mul(trx.gas_price)
+}
+
+/// A validated transaction whose maximum gas fee was deducted from the caller balance,
+/// with everything needed to settle it once its execution completes
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PrepaidTransaction {
+    pub caller: H160,
+    /// None for contract creation transactions
+    pub to: Option<H160>,
+    pub nonce: u64,
+    /// hash of the transaction, see transaction::unsigned_tx_hash
+    pub tx_hash: H256,
+    /// Effective price of a unit of gas
+    pub gas_price: U256,
+    /// Fee paid upfront by the caller, before the unused gas is refunded
+    pub prepaid_fee: U256,
+}
+
+/// Result of a completed execution, the state changes are only kept if it succeeded
+pub struct Execution {
+    pub exit_reason: ExitReason,
+    pub return_value: Vec<u8>,
+    pub apply_state: Option<ApplyState>,
+    pub used_gas: U256,
+}
+
+impl Execution {
+    pub fn new<B: StorageInterface>(machine: Machine<B>, return_value: Vec<u8>, exit_reason: ExitReason) -> Self {
+        debug_print!("result, exit_reason of executor.execute(): {:?}, {:?}", return_value, exit_reason);
+
+        // Execution halts with OutOfGas before the gas limit is exceeded
+        let used_gas = machine.used_gas();
+        let apply_state = if exit_reason.is_succeed() {
+            let executor_state = machine.into_state();
+            Some(executor_state.deconstruct())
+        } else {
+            None
         };
 
+        Self { exit_reason, return_value, apply_state, used_gas }
+    }
+}
+
+/// Logs are only produced by successful transactions, they are added to the response as `evm_log` events
+/// and saved under `tx_hash` if the contract was instantiated with persist_logs.
+/// A receipt is saved under `tx_hash` for every executed transaction, successful or not
+pub fn execute(mut storage: CwStorageInterface<DepsMut>, caller_address: H160, trx: UnsignedTransaction, tx_hash: H256) -> Result<Response, ContractError> {
+    let prepaid = prepay(&mut storage, caller_address, &trx, tx_hash)?;
+
+    let (execution, response) = {
+        let (mut executor, response) = begin(&storage, &prepaid, trx)?;
+
         let (result, exit_reason) = executor.execute();
-        debug_print!("result, exit_reason of executor.execute(): {:?}, {:?}", result, exit_reason);
-        let steps_executed = executor.get_steps_executed();
-        executor.gasometer_mut().pad_evm_steps(steps_executed);
+        (Execution::new(executor, result, exit_reason), response)
+    };
 
-        let used_gas = executor.used_gas();
-        if used_gas > trx.gas_limit {
-            (evm::ExitError::OutOfGas.into(), vec![], None, trx.gas_limit, Response::new())
-        } else {
-            let apply = if exit_reason.is_succeed() {
-                let executor_state = executor.into_state();
-                Some(executor_state.deconstruct())
-            } else {
-                None 
-            };
-
-            (exit_reason, result, apply, used_gas, response)
-        }
+    // Accounts changed by a transaction executed over several messages can't be read until it completes
+    storage.check_read_locks()?;
+
+    settle(&mut storage, &prepaid, execution, response)
+}
+
+/// Deducts the maximum gas fee of a validated transaction from the caller balance
+pub fn prepay(storage: &mut CwStorageInterface<DepsMut>, caller_address: H160, trx: &UnsignedTransaction, tx_hash: H256) -> Result<PrepaidTransaction, ContractError> {
+    let gas_price = trx.effective_gas_price();
+    let prepaid_fee = trx.gas_limit.checked_mul(gas_price)
+        .ok_or_else(|| E!(ContractError::InsufficientFunds; "Account {} - gas fee overflow", caller_address))?;
+    storage.prepay_gas(&caller_address, prepaid_fee)?;
+
+    Ok(PrepaidTransaction {
+        caller: caller_address,
+        to: trx.to,
+        nonce: trx.nonce,
+        tx_hash,
+        gas_price,
+        prepaid_fee,
+    })
+}
+
+/// Creates the Machine and begins the call or contract creation of the transaction
+pub fn begin<'a, 'd>(
+    storage: &'a CwStorageInterface<DepsMut<'d>>,
+    prepaid: &PrepaidTransaction,
+    trx: UnsignedTransaction,
+) -> Result<(Machine<'a, CwStorageInterface<DepsMut<'d>>>, Response), ContractError> {
+    let mut executor = Machine::new(prepaid.caller, prepaid.gas_price, storage)?;
+    executor.gasometer_mut().record_transaction_size(&trx);
+
+    let response: Response = match trx.to {
+        Some(code_address) => {
+            executor.call_begin(
+                prepaid.caller, 
+                code_address,
+                trx.call_data,
+                trx.value, 
+                trx.gas_limit
+            )?
+        },
+        None => {
+            executor.create_begin(
+                prepaid.caller,
+                trx.call_data,
+                trx.value,
+                trx.gas_limit
+            )?
+        },
     };
 
+    Ok((executor, response))
+}
+
+/// Applies the state changes of a completed execution, pays the gas fee and saves the receipt
+pub fn settle(storage: &mut CwStorageInterface<DepsMut>, prepaid: &PrepaidTransaction, execution: Execution, response: Response) -> Result<Response, ContractError> {
+    let fee_collector = storage.config().fee_collector;
+    let persist_logs = storage.config().persist_logs;
+    let Execution { exit_reason, return_value, apply_state, used_gas } = execution;
+    let PrepaidTransaction { caller: caller_address, tx_hash, .. } = *prepaid;
+
     debug_print!("exit_reason: {:?}", exit_reason);
 
+    let revert_data = match exit_reason {
+        ExitReason::Revert(_) => return_value.clone(),
+        _ => Vec::new(),
+    };
+
     let response = response
         .add_attribute("result", hex::encode(&return_value))
-        .add_attribute("evm_exit_reason", format!("{:?}", exit_reason));
+        .add_attribute("evm_exit_reason", format!("{:?}", exit_reason))
+        .add_attribute("gas_used", used_gas.to_string())
+        .add_attribute("tx_hash", hex::encode(tx_hash));
 
-    let response = response
+    let mut response = response
         .set_data(return_value);
 
+    let logs = if let Some(apply_state) = apply_state {
+        let (logs, messages) = storage.apply_state_change(apply_state)?;
+        if persist_logs {
+            storage.save_logs(&tx_hash, &logs)?;
+        }
 
-    // TODO: Gas payment and calculation
-    if let Some(apply_state) = apply_state {
-        storage.apply_state_change(apply_state)?;
+        response = response.add_submessages(messages);
+        response = response.add_events(logs.iter().enumerate().map(|(index, log)| log_event(tx_hash, index, log)));
+        logs
     } else {
         // Transaction ended with error, no state to apply
         // Increment nonce here. Normally it is incremented inside apply_state_change
         storage.increment_nonce(&caller_address)?;
-    }
+        Vec::new()
+    };
+
+    // Failed and reverted transactions pay for the gas they used as well
+    storage.settle_gas(&caller_address, &fee_collector, prepaid.prepaid_fee, used_gas * prepaid.gas_price)?;
+
+    let cumulative_gas_used = storage.record_block_gas(used_gas)?;
+    let contract_address = match prepaid.to {
+        None if exit_reason.is_succeed() => Some(create_address(&caller_address, prepaid.nonce)),
+        _ => None,
+    };
+
+    storage.save_receipt(&TransactionReceipt {
+        tx_hash,
+        block_height: storage.block_number().as_u64(),
+        from: caller_address,
+        to: prepaid.to,
+        status: if exit_reason.is_succeed() { 1 } else { 0 },
+        gas_used: used_gas,
+        cumulative_gas_used,
+        logs_bloom: logs_bloom(&logs),
+        logs,
+        contract_address,
+        revert_data,
+    })?;
 
     Ok(response)
-}
\ No newline at end of file
+}
+
+/// Address of a contract deployed by a contract creation transaction, derived from the sender address and nonce
+fn create_address(caller_address: &H160, nonce: u64) -> H160 {
+    let mut stream = rlp::RlpStream::new_list(2);
+    stream.append(caller_address);
+    stream.append(&nonce);
+
+    keccak256_h256(&stream.out()).into()
+}
+
+/// Structured event for an EVM log, with the address, topics and data hex encoded
+fn log_event(tx_hash: H256, index: usize, log: &Log) -> Event {
+    let topics: Vec<String> = log.topics.iter().map(hex::encode).collect();
+
+    Event::new("evm_log")
+        .add_attribute("tx_hash", hex::encode(tx_hash))
+        .add_attribute("log_index", index.to_string())
+        .add_attribute("address", hex::encode(log.address))
+        .add_attribute("topics", topics.join(","))
+        .add_attribute("data", hex::encode(&log.data))
+}
@@ -0,0 +1,44 @@
+use cosmwasm_std::{DepsMut, MessageInfo, Response};
+
+use crate::ContractError;
+use crate::operator::{self, ADMIN};
+
+pub fn add_operator(deps: DepsMut, info: MessageInfo, address: String) -> Result<Response, ContractError> {
+    operator::assert_admin(deps.as_ref(), &info.sender)?;
+    let address = deps.api.addr_validate(&address)?;
+
+    operator::add_operator(deps.storage, &address)?;
+
+    let response = Response::new()
+        .add_attribute("action", "add_operator")
+        .add_attribute("operator", address);
+
+    Ok(response)
+}
+
+pub fn remove_operator(deps: DepsMut, info: MessageInfo, address: String) -> Result<Response, ContractError> {
+    operator::assert_admin(deps.as_ref(), &info.sender)?;
+    let address = deps.api.addr_validate(&address)?;
+
+    operator::remove_operator(deps.storage, &address);
+
+    let response = Response::new()
+        .add_attribute("action", "remove_operator")
+        .add_attribute("operator", address);
+
+    Ok(response)
+}
+
+/// The current admin loses all admin rights once the new admin is saved
+pub fn update_admin(deps: DepsMut, info: MessageInfo, admin: String) -> Result<Response, ContractError> {
+    operator::assert_admin(deps.as_ref(), &info.sender)?;
+    let admin = deps.api.addr_validate(&admin)?;
+
+    ADMIN.save(deps.storage, &admin)?;
+
+    let response = Response::new()
+        .add_attribute("action", "update_admin")
+        .add_attribute("admin", admin);
+
+    Ok(response)
+}
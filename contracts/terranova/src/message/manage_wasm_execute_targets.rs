@@ -0,0 +1,39 @@
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
+
+use crate::{
+    storage::backend::{CW20_FACADES, WASM_EXECUTE_TARGETS},
+    operator,
+    ContractError,
+};
+
+/// Allowing a contract that is already allowed does nothing
+pub fn allow(deps: DepsMut, env: Env, info: MessageInfo, contract: String) -> Result<Response, ContractError> {
+    operator::assert_admin(deps.as_ref(), &info.sender)?;
+    let contract = deps.api.addr_validate(&contract)?;
+
+    if contract == env.contract.address || CW20_FACADES.has(deps.storage, &contract) {
+        return Err!(ContractError::WasmExecuteTargetNotAllowed; "{} can't be allowed", contract);
+    }
+
+    WASM_EXECUTE_TARGETS.save(deps.storage, &contract, &true)?;
+
+    let response = Response::new()
+        .add_attribute("action", "allow_wasm_execute_target")
+        .add_attribute("contract", contract);
+
+    Ok(response)
+}
+
+/// Disallowing a contract that isn't allowed does nothing
+pub fn disallow(deps: DepsMut, info: MessageInfo, contract: String) -> Result<Response, ContractError> {
+    operator::assert_admin(deps.as_ref(), &info.sender)?;
+    let contract = deps.api.addr_validate(&contract)?;
+
+    WASM_EXECUTE_TARGETS.remove(deps.storage, &contract);
+
+    let response = Response::new()
+        .add_attribute("action", "disallow_wasm_execute_target")
+        .add_attribute("contract", contract);
+
+    Ok(response)
+}
@@ -0,0 +1,28 @@
+use cosmwasm_std::{DepsMut, Env, Response};
+
+use crate::{
+    config::CONTRACT_CONFIG,
+    ContractError,
+};
+
+use super::MigrateMsg;
+
+pub fn process(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let mut config = CONTRACT_CONFIG.load(deps.storage)?;
+
+    if let Some(hardfork) = msg.hardfork {
+        if hardfork < config.hardfork {
+            return Err!(ContractError::HardforkDowngrade; "Can't migrate from {:?} to {:?}", config.hardfork, hardfork);
+        }
+        config.hardfork = hardfork;
+    }
+
+    // Storage saved before the generations is moved in batches with MigrateLegacyStorage, see LEGACY_CONTRACT_STORAGE
+    CONTRACT_CONFIG.save(deps.storage, &config)?;
+
+    let response = Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("hardfork", format!("{:?}", config.hardfork).to_lowercase());
+
+    Ok(response)
+}
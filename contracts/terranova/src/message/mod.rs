@@ -1,9 +1,39 @@
-use cosmwasm_std::Uint256;
+use cosmwasm_std::{Uint128, Uint256};
+use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::hardfork::Hardfork;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
+    /// EVM chain ID, defaults to the chain ID of the network selected by the cargo features
+    pub chain_id: Option<u64>,
+    /// Denom of the native Terra coin backing EVM account balances, e.g. "uluna"
+    pub denom: String,
+    /// H160 address of the EVM account that receives the gas fees of every transaction
+    pub fee_collector: [u8; 20],
+    /// Save the logs of every transaction so they can be read with QueryTxLogs, defaults to false
+    #[serde(default)]
+    pub persist_logs: bool,
+    /// Terra address allowed to manage the operators, defaults to the instantiating account
+    pub admin: Option<String>,
+    /// Terra addresses allowed to submit EVM transactions from the start
+    #[serde(default)]
+    pub operators: Vec<String>,
+    /// Ethereum hardfork whose rules the EVM follows, defaults to Istanbul
+    #[serde(default)]
+    pub hardfork: Hardfork,
+    /// Maximum size in bytes of a chunk stored with StoreTxChunk, defaults to DEFAULT_MAX_CHUNK_SIZE
+    pub max_chunk_size: Option<u32>,
+    /// Maximum total size in bytes of the stored chunks of a transaction, defaults to DEFAULT_MAX_CHUNKED_TX_SIZE
+    pub max_chunked_tx_size: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    /// Upgrade the EVM to the rules of a later hardfork, the current rules are kept if not set
+    pub hardfork: Option<Hardfork>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -17,6 +47,13 @@ pub enum ExecuteMsg {
         unsigned_tx: Vec<u8>,
     },
 
+    ExecuteSignedEthereumTx {
+        /// The RLP encoded transaction, including the v, r, s signature values.\ 
+        /// Legacy transactions must be replay protected by EIP-155, `v = 27/28` is rejected with InvalidChainId.\ 
+        /// The caller EVM address is recovered from the signature
+        signed_tx: Vec<u8>,
+    },
+
     StoreTxChunk {
         /// H160 address in the form of a byte array
         caller_evm_address: [u8; 20],
@@ -36,6 +73,115 @@ pub enum ExecuteMsg {
 
         chunk_count: u8,
     },
+
+    /// Remove the stored chunks of a transaction, operators can cancel them at any time
+    /// and anyone can purge them once they are CHUNK_EXPIRY_BLOCKS blocks old
+    CancelChunkedTx {
+        /// H160 address in the form of a byte array
+        caller_evm_address: [u8; 20],
+
+        /// keccak_h256 hash of the full unsigned transaction as as byte array
+        full_tx_hash: [u8; 32],
+    },
+
+    /// Execute a transaction too long to run in a single message, for up to `max_steps` EVM steps.\ 
+    /// The accounts it reads are locked until it completes, continue it with ContinueTx
+    ExecuteIterativeEthereumTx {
+        /// H160 address in the form of a byte array
+        caller_evm_address: [u8; 20],
+
+        /// The RLP encoded unsigned transaction message
+        unsigned_tx: Vec<u8>,
+
+        max_steps: u64,
+    },
+
+    /// Run a transaction started with ExecuteIterativeEthereumTx for up to `max_steps` more EVM steps
+    ContinueTx {
+        /// hash of the transaction, see transaction::unsigned_tx_hash, as a byte array
+        tx_hash: [u8; 32],
+
+        max_steps: u64,
+    },
+
+    /// Admin only, drop a transaction in progress that wasn't continued for PENDING_TX_TIMEOUT blocks and release its locks
+    CancelPendingTx {
+        /// hash of the transaction, see transaction::unsigned_tx_hash, as a byte array
+        tx_hash: [u8; 32],
+    },
+
+    /// Credit the coins of the configured native denom sent with the message to the balance of the EVM address
+    Deposit {
+        /// H160 address in the form of a byte array
+        evm_address: [u8; 20],
+    },
+
+    /// Debit the balance of the EVM address and send the native coins to the Terra recipient address
+    Withdraw {
+        /// H160 address in the form of a byte array
+        caller_evm_address: [u8; 20],
+
+        recipient: String,
+
+        /// Amount of the native denom, EVM balances are scaled by NATIVE_DECIMALS_SCALE
+        amount: Uint128,
+    },
+
+    /// Admin only, allow the Terra address to submit EVM transactions
+    AddOperator {
+        address: String,
+    },
+
+    /// Admin only, revoke the Terra address' right to submit EVM transactions
+    RemoveOperator {
+        address: String,
+    },
+
+    /// Admin only, hand the admin role over to another Terra address
+    UpdateAdmin {
+        admin: String,
+    },
+
+    /// Admin only, deploy an ERC-20 facade of the CW20 token so that it can be used in the EVM
+    RegisterCw20 {
+        cw20_address: String,
+    },
+
+    /// Admin only, allow EVM contracts to send messages to the CosmWasm contract through the wasm execute precompile\ 
+    /// Registered CW20 tokens can't be allowed, their tokens held for EVM accounts only move through the ERC-20 facades
+    AllowWasmExecuteTarget {
+        contract: String,
+    },
+
+    /// Admin only, stop EVM contracts from sending messages to the CosmWasm contract, messages already sent still run
+    DisallowWasmExecuteTarget {
+        contract: String,
+    },
+
+    /// CW20 tokens sent to the contract, the embedded msg must be a Cw20HookMsg
+    Receive(Cw20ReceiveMsg),
+
+    /// Remove up to `limit` storage entries left behind by contracts whose storage was reset or deleted.\ 
+    /// They are no longer part of the EVM state, this only frees space
+    PruneStorage {
+        limit: u32,
+    },
+
+    /// Move up to `limit` storage entries saved before storage generations were introduced to their generation 0.\ 
+    /// They are read from where they are until then, this only completes the migration
+    MigrateLegacyStorage {
+        limit: u32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    /// Credit the tokens to the balance of the EVM address in the ERC-20 facade of the CW20 token
+    Deposit {
+        /// H160 address in the form of a byte array
+        evm_address: [u8; 20],
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -53,6 +199,94 @@ pub enum QueryMsg {
     QueryEvmAccount {
         evm_address: [u8; 20],
     },
+
+    /// Get the logs emitted by a transaction, only available if the contract was instantiated with persist_logs\ 
+    /// Optionally only the logs of the given contract address and/or matching the given topics are returned.\ 
+    /// Logs are only saved per transaction, there is no index to filter them across transactions,
+    /// which is done off chain from the evm_log events instead
+    QueryTxLogs {
+        /// hash of the transaction, see transaction::unsigned_tx_hash
+        tx_hash: [u8; 32],
+        address: Option<[u8; 20]>,
+        /// Topics by position like Ethereum log filters, None matches any topic at its position.
+        /// Logs with fewer topics than the filter don't match
+        #[serde(default)]
+        topics: Vec<Option<[u8; 32]>>,
+    },
+
+    /// Get the admin and the operators allowed to submit EVM transactions
+    QueryOperators {},
+
+    /// Get the CosmWasm contracts that EVM contracts may send messages to, see ExecuteMsg::AllowWasmExecuteTarget
+    QueryWasmExecuteTargets {},
+
+    /// Get the Ethereum style receipt of an executed transaction, errors if no transaction with this hash was executed
+    GetTransactionReceipt {
+        /// hash of the transaction, see transaction::unsigned_tx_hash
+        tx_hash: [u8; 32],
+    },
+
+    /// Get the EVM address of the ERC-20 facade of a registered CW20 token
+    QueryCw20Facade {
+        cw20_address: String,
+    },
+
+    /// Get the chunks stored so far with StoreTxChunk for a transaction, so that an interrupted upload can be resumed
+    TxChunkStatus {
+        caller_evm_address: [u8; 20],
+        /// keccak_h256 hash of the full unsigned transaction
+        full_tx_hash: [u8; 32],
+    },
+
+    /// Run a call on top of the current state like eth_call, optionally with some of the state replaced.\ 
+    /// State changes are discarded, and a reverted call returns its revert data and decoded reason instead of failing
+    EthCall(EthCallMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EthCallMsg {
+    /// H160 address of the caller, defaults to the zero address
+    pub from: Option<[u8; 20]>,
+    /// H160 address of the called contract, None to run `data` as the init code of a contract creation
+    pub to: Option<[u8; 20]>,
+    #[serde(default)]
+    pub data: Vec<u8>,
+    /// Value transferred from the caller, in EVM balance units
+    #[serde(default)]
+    pub value: Uint256,
+    /// Defaults to ETH_CALL_GAS_LIMIT
+    pub gas_limit: Option<u64>,
+    /// Context of the block the call runs in, defaults to the current block
+    pub block: Option<BlockContext>,
+    /// Accounts whose state is replaced for the call
+    #[serde(default)]
+    pub state_overrides: Vec<StateOverride>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BlockContext {
+    /// Returned by NUMBER, defaults to the current height
+    pub number: Option<u64>,
+    /// Returned by TIMESTAMP in seconds, defaults to the time of the current block
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StateOverride {
+    /// H160 address of the account
+    pub address: [u8; 20],
+    pub balance: Option<Uint256>,
+    /// Runtime code replacing the code of the account, an empty code removes it
+    pub code: Option<Vec<u8>>,
+    /// Storage slots replaced for the call, the other slots keep their value
+    #[serde(default)]
+    pub storage: Vec<StorageSlot>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StorageSlot {
+    pub key: [u8; 32],
+    pub value: [u8; 32],
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -66,7 +300,109 @@ pub struct EvmAccountResponse {
     pub nonce: u64
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OperatorsResponse {
+    pub admin: String,
+    pub operators: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WasmExecuteTargetsResponse {
+    pub contracts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EvmLogResponse {
+    pub address: [u8; 20],
+    pub topics: Vec<[u8; 32]>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TxLogsResponse {
+    pub logs: Vec<EvmLogResponse>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransactionReceiptResponse {
+    pub tx_hash: [u8; 32],
+    pub block_height: u64,
+    pub from: [u8; 20],
+    pub to: Option<[u8; 20]>,
+    /// 1 for success, 0 for failure
+    pub status: u8,
+    pub gas_used: Uint256,
+    pub cumulative_gas_used: Uint256,
+    pub logs: Vec<EvmLogResponse>,
+    /// 256 bytes bloom filter of the log addresses and topics
+    pub logs_bloom: Vec<u8>,
+    pub contract_address: Option<[u8; 20]>,
+    pub revert_data: Vec<u8>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw20FacadeResponse {
+    pub evm_address: [u8; 20],
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EthCallResponse {
+    /// The EVM exit reason in Debug format, e.g. "Succeed(Returned)" or "Revert(Reverted)"
+    pub exit_reason: String,
+    pub success: bool,
+    pub gas_used: Uint256,
+    /// Data returned by RETURN or REVERT
+    pub result: Vec<u8>,
+    /// Reason decoded from the revert data, None if the call didn't revert or reverted with a custom error
+    pub revert_reason: Option<RevertReason>,
+}
+
+/// Reasons Solidity gives for reverting
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevertReason {
+    /// The message of `require` or `revert`
+    Error(String),
+    /// The code of a failed `assert` or of another check added by the compiler, e.g. 0x11 for arithmetic overflow
+    Panic(Uint256),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TxChunkResponse {
+    pub chunk_index: u8,
+    /// Size of the chunk in bytes
+    pub length: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TxChunkStatusResponse {
+    /// The stored chunks, in ascending order of index, empty if none are stored
+    pub chunks: Vec<TxChunkResponse>,
+    pub total_size: u32,
+    /// Number of chunks stored from index 0 without a gap, the index to resume uploading from
+    pub contiguous_chunks: u32,
+    /// keccak_h256 hash of the contiguous chunks from index 0 concatenated in order,
+    /// equal to the full transaction hash once every chunk is stored
+    pub running_hash: [u8; 32],
+    /// Height from which anyone can purge the chunks, None if no chunks are stored
+    pub expiry_height: Option<u64>,
+}
+
 pub mod execute_simple_transaction;
+pub mod execute_signed_transaction;
 pub mod store_transaction_chunk;
 pub mod execute_chunked_transaction;
+pub mod cancel_chunked_transaction;
+pub mod execute_iterative_transaction;
+pub mod continue_transaction;
+pub mod cancel_pending_transaction;
 pub mod raw_ethereum_query;
+pub mod eth_call;
+pub mod manage_operators;
+pub mod manage_wasm_execute_targets;
+pub mod deposit;
+pub mod withdraw;
+pub mod cw20_bridge;
+pub mod wasm_call_reply;
+pub mod prune_storage;
+pub mod migrate_legacy_storage;
+pub mod migrate;
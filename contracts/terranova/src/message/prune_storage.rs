@@ -0,0 +1,22 @@
+use cosmwasm_std::{DepsMut, Env, Response};
+
+use crate::{
+    storage::CwStorageInterface,
+    config::token_mint_dummy,
+    ContractError,
+};
+
+pub fn process(deps: DepsMut, env: Env, limit: u32) -> Result<Response, ContractError> {
+    let mut storage = CwStorageInterface::new_mut(
+        deps,
+        env,
+        token_mint_dummy()
+    )?;
+    let pruned = storage.prune_storage(limit)?;
+
+    let response = Response::new()
+        .add_attribute("action", "prune_storage")
+        .add_attribute("pruned", pruned.to_string());
+
+    Ok(response)
+}
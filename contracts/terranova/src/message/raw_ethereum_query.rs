@@ -3,8 +3,8 @@ use evm::H160;
 
 use crate::{
     transaction::UnsignedTransaction, 
-    storage::{CwStorageInterface}, 
-    config::{token_mint_dummy, chain_id_dummy},
+    storage::{CwStorageInterface, StorageInterface}, 
+    config::token_mint_dummy,
     ContractError, 
     executor::Machine
 };
@@ -18,21 +18,24 @@ pub fn process(deps: Deps, env: Env, caller_address_bytes: [u8; 20], unsigned_tx
     let storage = CwStorageInterface::new_ref(
         deps, 
         env, 
-        token_mint_dummy(), 
-        chain_id_dummy()
+        token_mint_dummy()
     )?;
-    validate()?;
+    validate(&storage, &trx)?;
 
     execute(storage, caller_address, trx)
 }
 
-pub fn validate() -> Result<(), ContractError> {
+pub fn validate(storage: &CwStorageInterface<Deps>, trx: &UnsignedTransaction) -> Result<(), ContractError> {
+    if !trx.tx_type.is_enabled(storage.hardfork().config()) {
+        return Err!(ContractError::InvalidTransactionData; "Transaction type {:?} is not accepted under {:?}", trx.tx_type, storage.hardfork());
+    }
+
     Ok(())
 }
 
 pub fn execute(mut storage: CwStorageInterface<Deps>, caller_address: H160, trx: UnsignedTransaction) -> Result<RawEthereumQueryResponse, ContractError> {
     let (exit_reason, return_value, apply_state, used_gas, response) = {
-        let mut executor = Machine::new(caller_address, &storage)?;
+        let mut executor = Machine::new(caller_address, trx.effective_gas_price(), &storage)?;
         executor.gasometer_mut().record_transaction_size(&trx);
 
         let response: Response = match trx.to {
@@ -57,25 +60,20 @@ pub fn execute(mut storage: CwStorageInterface<Deps>, caller_address: H160, trx:
 
         let (result, exit_reason) = executor.execute();
         debug_print!("result, exit_reason of executor.execute(): {:?}, {:?}", result, exit_reason);
-        let steps_executed = executor.get_steps_executed();
-        executor.gasometer_mut().pad_evm_steps(steps_executed);
 
+        // Execution halts with OutOfGas before the gas limit is exceeded
         let used_gas = executor.used_gas();
-        if used_gas > trx.gas_limit {
-            (evm::ExitError::OutOfGas.into(), vec![], None, trx.gas_limit, response)
+        let apply = if exit_reason.is_succeed() {
+            let executor_state = executor.into_state();
+            Some(executor_state.deconstruct())
         } else {
-            let apply = if exit_reason.is_succeed() {
-                let executor_state = executor.into_state();
-                Some(executor_state.deconstruct())
-            } else {
-                None 
-            };
+            None 
+        };
 
-            (exit_reason, result, apply, used_gas, response)
-        }
+        (exit_reason, result, apply, used_gas, response)
     };
 
-    // TODO: Gas payment and calculation
+    // Queries don't change state, so no gas fee is charged, like eth_call
 
     if let Some(apply_state) = apply_state {
         // The nonce of the caller and contract address increasing should be the only two changes, we simply avoid writing them to state
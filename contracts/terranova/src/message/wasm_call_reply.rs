@@ -0,0 +1,37 @@
+use cosmwasm_std::{ContractResult, DepsMut, Env, Reply, Response};
+
+use crate::{
+    storage::CwStorageInterface,
+    config::token_mint_dummy,
+    ContractError,
+};
+
+/// Reply to a CosmWasm message sent by an EVM contract through the wasm execute precompile, the reply id is the call id.\ 
+/// The outcome is saved so that EVM contracts can read it with the wasm call status precompile
+pub fn process(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let mut storage = CwStorageInterface::new_mut(
+        deps,
+        env,
+        token_mint_dummy()
+    )?;
+
+    let response = Response::new()
+        .add_attribute("action", "wasm_call_reply")
+        .add_attribute("id", msg.id.to_string());
+
+    let response = match msg.result {
+        ContractResult::Ok(_) => {
+            storage.settle_wasm_call(msg.id, true)?;
+            response.add_attribute("status", "succeeded")
+        }
+        ContractResult::Err(error) => {
+            let record = storage.settle_wasm_call(msg.id, false)?;
+            response
+                .add_attribute("status", "failed")
+                .add_attribute("error", error)
+                .add_attribute("refunded", record.funds)
+        }
+    };
+
+    Ok(response)
+}
@@ -0,0 +1,48 @@
+use cosmwasm_std::{Addr, BankMsg, DepsMut, Env, Response, Uint128, coins};
+use evm::H160;
+
+use crate::{
+    storage::CwStorageInterface,
+    config::token_mint_dummy,
+    ContractError,
+};
+
+pub fn process(deps: DepsMut, env: Env, caller_address_bytes: [u8; 20], recipient: String, amount: Uint128) -> Result<Response, ContractError> {
+    let caller_address = H160::from_slice(&caller_address_bytes);
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    let storage = CwStorageInterface::new_mut(
+        deps,
+        env,
+        token_mint_dummy()
+    )?;
+    validate(amount)?;
+
+    execute(storage, caller_address, recipient, amount)
+}
+
+pub fn validate(amount: Uint128) -> Result<(), ContractError> {
+    if amount.is_zero() {
+        return Err!(ContractError::InvalidFunds; "Withdrawal amount must be positive");
+    }
+
+    Ok(())
+}
+
+/// The EVM balance is debited before the coins are sent, if the bank transfer fails the whole message is reverted
+pub fn execute(mut storage: CwStorageInterface<DepsMut>, caller_address: H160, recipient: Addr, amount: Uint128) -> Result<Response, ContractError> {
+    let denom = storage.config().denom.clone();
+    storage.withdraw_native_tokens(&caller_address, amount)?;
+
+    let response = Response::new()
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(amount.u128(), denom),
+        })
+        .add_attribute("action", "withdraw")
+        .add_attribute("evm_address", hex::encode(caller_address))
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount);
+
+    Ok(response)
+}
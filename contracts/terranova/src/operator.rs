@@ -0,0 +1,46 @@
+use cosmwasm_std::{Addr, Deps, Order, StdResult, Storage};
+use cw_storage_plus::{Item, Map};
+
+use crate::ContractError;
+
+/// The only account allowed to add and remove operators, or to hand the admin role over to another account
+pub const ADMIN: Item<Addr> = Item::new("admin");
+
+/// Key: the Terra address of a trusted operator, allowed to submit EVM transactions on behalf of any EVM address
+/// Value: always true, a removed operator is removed from the map
+const OPERATORS: Map<&Addr, bool> = Map::new("operators");
+
+pub fn assert_admin(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    let admin = ADMIN.load(deps.storage)?;
+    if *sender != admin {
+        return Err!(ContractError::Unauthorized; "{} is not the admin", sender);
+    }
+
+    Ok(())
+}
+
+pub fn assert_operator(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if !OPERATORS.has(deps.storage, sender) {
+        return Err!(ContractError::Unauthorized; "{} is not an operator", sender);
+    }
+
+    Ok(())
+}
+
+/// Adding an existing operator does nothing
+pub fn add_operator(storage: &mut dyn Storage, operator: &Addr) -> StdResult<()> {
+    OPERATORS.save(storage, operator, &true)
+}
+
+/// Removing an address that isn't an operator does nothing
+pub fn remove_operator(storage: &mut dyn Storage, operator: &Addr) {
+    OPERATORS.remove(storage, operator)
+}
+
+/// All operators, in ascending order of their address
+pub fn operators(storage: &dyn Storage) -> StdResult<Vec<Addr>> {
+    OPERATORS
+        .keys(storage, None, None, Order::Ascending)
+        .map(|key| String::from_utf8(key).map(Addr::unchecked).map_err(|e| e.into()))
+        .collect()
+}
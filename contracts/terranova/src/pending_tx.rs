@@ -0,0 +1,46 @@
+use std::collections::BTreeSet;
+
+use cosmwasm_std::Binary;
+use evm::H160;
+use serde::{Deserialize, Serialize};
+
+use crate::message::execute_simple_transaction::PrepaidTransaction;
+
+/// Number of blocks without progress after which the admin can cancel a transaction in progress and release its locks
+pub const PENDING_TX_TIMEOUT: u64 = 100;
+
+/// Transaction executed over several messages, saved to PENDING_TXS under the transaction hash between messages
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PendingTx {
+    /// The validated transaction whose gas fee was prepaid by the first message
+    pub prepaid: PrepaidTransaction,
+    /// The paused Machine, as serialized by Machine::save
+    pub machine: Binary,
+    /// Locks held on the accounts accessed so far, released when execution completes or is cancelled
+    pub locks: AccountLocks,
+    /// Height of the Terra block in which the transaction was last continued, for PENDING_TX_TIMEOUT
+    pub updated_height: u64,
+}
+
+/// Locks held by a transaction in progress, kept in the rw_blocked and ro_blocked_count fields of the EvmAccount
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AccountLocks {
+    /// Accounts changed by the transaction, no other transaction can read or write them
+    pub write: BTreeSet<H160>,
+    /// Accounts only read by the transaction, no other transaction can write them
+    pub read: BTreeSet<H160>,
+}
+
+impl AccountLocks {
+    /// Locks to hold after more accounts were read or changed, an account changed since it was read moves to the write locks
+    #[must_use]
+    pub fn extend(&self, read: &BTreeSet<H160>, written: &BTreeSet<H160>) -> Self {
+        let write: BTreeSet<H160> = self.write.union(written).copied().collect();
+        let read = self.read.union(read)
+            .filter(|address| !write.contains(address))
+            .copied()
+            .collect();
+
+        Self { write, read }
+    }
+}
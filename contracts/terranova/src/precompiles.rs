@@ -0,0 +1,673 @@
+//! Precompiled contracts at the addresses 0x01 - 0x09, with the Istanbul gas schedule and the ModExp pricing of the hardfork,
+//! and Terranova specific precompiles at the addresses 0xff00..0001 and up, which can access the EVM state.
+//!
+//! Precompiles are called from `Executor::call` like regular contracts, or directly by a transaction, but run native code instead of EVM bytecode.
+//! The fork of the EVM used by Terranova has no free form error variant, so invalid precompile input
+//! is reported as `ExitError::InvalidRange`. Like any other error, it consumes all the gas given to the call.
+
+use std::convert::TryInto;
+
+use cosmwasm_std::{from_slice, Api, Binary, ContractResult, Empty, QueryRequest, SystemResult, Uint128, WasmQuery};
+use evm::{ExitError, H160, U256};
+use num_bigint::BigUint;
+use ripemd160::Ripemd160;
+use sha2::{Digest, Sha256};
+
+use crate::executor_state::ExecutorState;
+use crate::hardfork::HardforkConfig;
+use crate::storage::StorageInterface;
+use crate::utils::keccak256_h256;
+use crate::wasm_call::WasmCallStatus;
+
+const ECRECOVER: u8 = 0x01;
+const SHA256: u8 = 0x02;
+const RIPEMD160: u8 = 0x03;
+const IDENTITY: u8 = 0x04;
+const MODEXP: u8 = 0x05;
+const BN128_ADD: u8 = 0x06;
+const BN128_MUL: u8 = 0x07;
+const BN128_PAIRING: u8 = 0x08;
+const BLAKE2F: u8 = 0x09;
+
+/// First byte of the Terranova precompile addresses, the last byte selects the precompile
+const TERRANOVA_PREFIX: u8 = 0xff;
+const WITHDRAW: u8 = 0x01;
+const WASM_EXECUTE: u8 = 0x02;
+const WASM_CALL_STATUS: u8 = 0x03;
+const WASM_QUERY: u8 = 0x04;
+
+const G_ECRECOVER: u64 = 3000;
+const G_SHA256_BASE: u64 = 60;
+const G_SHA256_WORD: u64 = 12;
+const G_RIPEMD160_BASE: u64 = 600;
+const G_RIPEMD160_WORD: u64 = 120;
+const G_IDENTITY_BASE: u64 = 15;
+const G_IDENTITY_WORD: u64 = 3;
+const G_MODEXP_QUAD_DIVISOR: u64 = 20;
+const G_MODEXP_EIP2565_DIVISOR: u64 = 3;
+const G_MODEXP_EIP2565_MIN: u64 = 200;
+const G_BN128_ADD: u64 = 150;
+const G_BN128_MUL: u64 = 6000;
+const G_BN128_PAIRING_BASE: u64 = 45000;
+const G_BN128_PAIRING_POINT: u64 = 34000;
+const G_BLAKE2F_ROUND: u64 = 1;
+const G_WITHDRAW: u64 = 10_000;
+const G_WASM_EXECUTE_BASE: u64 = 40_000;
+const G_WASM_EXECUTE_WORD: u64 = 100;
+const G_WASM_CALL_STATUS: u64 = 800;
+const G_WASM_QUERY_BASE: u64 = 10_000;
+const G_WASM_QUERY_WORD: u64 = 100;
+
+const BN128_PAIR_LEN: usize = 192;
+const BLAKE2F_INPUT_LEN: usize = 213;
+
+/// Return data of a successful precompile call and the gas it cost
+#[derive(Debug, PartialEq)]
+pub struct PrecompileOutput {
+    pub output: Vec<u8>,
+    pub cost: u64,
+}
+
+pub type PrecompileResult = Result<PrecompileOutput, ExitError>;
+
+#[must_use]
+pub fn is_precompile_address(address: &H160) -> bool {
+    is_ethereum_precompile_address(address) || is_terranova_precompile_address(address)
+}
+
+fn is_ethereum_precompile_address(address: &H160) -> bool {
+    let bytes = address.as_bytes();
+    bytes[..19].iter().all(|byte| *byte == 0) && (ECRECOVER..=BLAKE2F).contains(&bytes[19])
+}
+
+#[must_use]
+pub fn is_terranova_precompile_address(address: &H160) -> bool {
+    let bytes = address.as_bytes();
+    bytes[0] == TERRANOVA_PREFIX && bytes[1..19].iter().all(|byte| *byte == 0) && (WITHDRAW..=WASM_QUERY).contains(&bytes[19])
+}
+
+/// Address of the precompile withdrawing native coins from the caller's EVM balance to a Terra address
+#[must_use]
+pub fn withdraw_address() -> H160 {
+    terranova_precompile_address(WITHDRAW)
+}
+
+/// Address of the precompile sending a CosmWasm execute message on behalf of the caller
+#[must_use]
+pub fn wasm_execute_address() -> H160 {
+    terranova_precompile_address(WASM_EXECUTE)
+}
+
+/// Address of the precompile returning the status of a CosmWasm message sent through the wasm execute precompile
+#[must_use]
+pub fn wasm_call_status_address() -> H160 {
+    terranova_precompile_address(WASM_CALL_STATUS)
+}
+
+/// Address of the precompile running a read-only CosmWasm query
+#[must_use]
+pub fn wasm_query_address() -> H160 {
+    terranova_precompile_address(WASM_QUERY)
+}
+
+fn terranova_precompile_address(index: u8) -> H160 {
+    let mut address = H160::zero();
+    address.0[0] = TERRANOVA_PREFIX;
+    address.0[19] = index;
+
+    address
+}
+
+/// Runs the Ethereum precompile at `address`, returns `None` if `address` is not an Ethereum precompile
+///
+/// The precompile fails with `OutOfGas` before doing any work if its cost exceeds `gas_limit`
+pub fn call_precompile(address: H160, input: &[u8], gas_limit: u64, api: &dyn Api, config: &HardforkConfig) -> Option<PrecompileResult> {
+    if !is_ethereum_precompile_address(&address) {
+        return None;
+    }
+
+    let result = match address.as_bytes()[19] {
+        ECRECOVER => ecrecover(input, gas_limit, api),
+        SHA256 => sha256(input, gas_limit),
+        RIPEMD160 => ripemd160(input, gas_limit),
+        IDENTITY => identity(input, gas_limit),
+        MODEXP => modexp(input, gas_limit, config.decrease_modexp_gas),
+        BN128_ADD => bn128_add(input, gas_limit),
+        BN128_MUL => bn128_mul(input, gas_limit),
+        BN128_PAIRING => bn128_pairing(input, gas_limit),
+        BLAKE2F => blake2f(input, gas_limit),
+        _ => unreachable!(),
+    };
+
+    Some(result)
+}
+
+fn ecrecover(input: &[u8], gas_limit: u64, api: &dyn Api) -> PrecompileResult {
+    charge(G_ECRECOVER, gas_limit)?;
+
+    let input = padded(input, 0, 128);
+    let v = U256::from_big_endian(&input[32..64]);
+
+    // An invalid signature is not an error, the output is just empty
+    let output = if v == U256::from(27) || v == U256::from(28) {
+        let recovery_id = (v.low_u64() - 27) as u8;
+        api.secp256k1_recover_pubkey(&input[0..32], &input[64..128], recovery_id)
+            .map_or_else(|_| Vec::new(), |pubkey| {
+                let mut address = vec![0_u8; 32];
+                address[12..].copy_from_slice(&keccak256_h256(&pubkey[1..])[12..]);
+                address
+            })
+    } else {
+        Vec::new()
+    };
+
+    Ok(PrecompileOutput { output, cost: G_ECRECOVER })
+}
+
+fn sha256(input: &[u8], gas_limit: u64) -> PrecompileResult {
+    let cost = linear_cost(input.len(), G_SHA256_BASE, G_SHA256_WORD)?;
+    charge(cost, gas_limit)?;
+
+    let output = Sha256::digest(input).to_vec();
+
+    Ok(PrecompileOutput { output, cost })
+}
+
+fn ripemd160(input: &[u8], gas_limit: u64) -> PrecompileResult {
+    let cost = linear_cost(input.len(), G_RIPEMD160_BASE, G_RIPEMD160_WORD)?;
+    charge(cost, gas_limit)?;
+
+    // The 20 byte hash is left padded to a word
+    let mut output = vec![0_u8; 32];
+    output[12..].copy_from_slice(&Ripemd160::digest(input));
+
+    Ok(PrecompileOutput { output, cost })
+}
+
+fn identity(input: &[u8], gas_limit: u64) -> PrecompileResult {
+    let cost = linear_cost(input.len(), G_IDENTITY_BASE, G_IDENTITY_WORD)?;
+    charge(cost, gas_limit)?;
+
+    Ok(PrecompileOutput { output: input.to_vec(), cost })
+}
+
+/// Arbitrary precision modular exponentiation (EIP-198), priced by EIP-2565 if `eip2565` is set
+///
+/// The input is `base_len`, `exp_len` and `mod_len` as words, followed by `base`, `exp` and `mod`
+fn modexp(input: &[u8], gas_limit: u64, eip2565: bool) -> PrecompileResult {
+    let header = padded(input, 0, 96);
+    let base_len = U256::from_big_endian(&header[0..32]);
+    let exp_len = U256::from_big_endian(&header[32..64]);
+    let mod_len = U256::from_big_endian(&header[64..96]);
+
+    // The lengths are only trusted once the gas for them has been paid
+    let exp_head_len = exp_len.min(U256::from(32)).low_u64() as usize;
+    let exp_head = match base_len.checked_add(U256::from(96)) {
+        Some(exp_offset) if exp_offset <= U256::from(input.len()) => {
+            U256::from_big_endian(&padded(input, exp_offset.low_u64() as usize, exp_head_len))
+        }
+        _ => U256::zero(),
+    };
+
+    let adjusted_exp_len = if exp_len <= U256::from(32) {
+        U256::from(exp_head.bits().saturating_sub(1))
+    } else {
+        (exp_len - 32).saturating_mul(U256::from(8)).saturating_add(U256::from(exp_head.bits().saturating_sub(1)))
+    };
+
+    let iteration_count = adjusted_exp_len.max(U256::one());
+    let cost = if eip2565 {
+        // The complexity is the square of the number of 8 byte words of the longest operand
+        let words = base_len.max(mod_len).saturating_add(U256::from(7)) / 8;
+        (words.saturating_mul(words).saturating_mul(iteration_count) / U256::from(G_MODEXP_EIP2565_DIVISOR))
+            .max(U256::from(G_MODEXP_EIP2565_MIN))
+    } else {
+        modexp_mult_complexity(base_len.max(mod_len)).saturating_mul(iteration_count) / U256::from(G_MODEXP_QUAD_DIVISOR)
+    };
+    if cost > U256::from(gas_limit) {
+        return Err(ExitError::OutOfGas);
+    }
+    let cost = cost.low_u64();
+
+    if base_len.is_zero() && mod_len.is_zero() {
+        return Ok(PrecompileOutput { output: Vec::new(), cost });
+    }
+
+    let base_len = to_usize(base_len)?;
+    let exp_len = to_usize(exp_len)?;
+    let mod_len = to_usize(mod_len)?;
+
+    let base = BigUint::from_bytes_be(&padded(input, 96, base_len));
+    let exp = BigUint::from_bytes_be(&padded(input, 96 + base_len, exp_len));
+    let modulus = BigUint::from_bytes_be(&padded(input, 96 + base_len + exp_len, mod_len));
+
+    let mut output = vec![0_u8; mod_len];
+    if modulus != BigUint::from(0_u8) {
+        let result = base.modpow(&exp, &modulus).to_bytes_be();
+        output[mod_len - result.len()..].copy_from_slice(&result);
+    }
+
+    Ok(PrecompileOutput { output, cost })
+}
+
+fn modexp_mult_complexity(x: U256) -> U256 {
+    if x <= U256::from(64) {
+        x * x
+    } else if x <= U256::from(1024) {
+        x * x / 4 + x * 96 - 3072
+    } else {
+        (x.saturating_mul(x) / 16).saturating_add(x.saturating_mul(U256::from(480))) - 199_680
+    }
+}
+
+/// Addition of two points on the alt_bn128 curve (EIP-196)
+fn bn128_add(input: &[u8], gas_limit: u64) -> PrecompileResult {
+    use bn::AffineG1;
+
+    charge(G_BN128_ADD, gas_limit)?;
+
+    let input = padded(input, 0, 128);
+    let p1 = read_g1(&input[0..64])?;
+    let p2 = read_g1(&input[64..128])?;
+
+    let mut output = vec![0_u8; 64];
+    if let Some(sum) = AffineG1::from_jacobian(p1 + p2) {
+        write_fq(sum.x(), &mut output[0..32])?;
+        write_fq(sum.y(), &mut output[32..64])?;
+    }
+
+    Ok(PrecompileOutput { output, cost: G_BN128_ADD })
+}
+
+/// Scalar multiplication of a point on the alt_bn128 curve (EIP-196)
+fn bn128_mul(input: &[u8], gas_limit: u64) -> PrecompileResult {
+    use bn::{AffineG1, Fr};
+
+    charge(G_BN128_MUL, gas_limit)?;
+
+    let input = padded(input, 0, 96);
+    let p = read_g1(&input[0..64])?;
+    let scalar = Fr::from_slice(&input[64..96]).map_err(|_| invalid_input())?;
+
+    let mut output = vec![0_u8; 64];
+    if let Some(product) = AffineG1::from_jacobian(p * scalar) {
+        write_fq(product.x(), &mut output[0..32])?;
+        write_fq(product.y(), &mut output[32..64])?;
+    }
+
+    Ok(PrecompileOutput { output, cost: G_BN128_MUL })
+}
+
+/// Pairing check on the alt_bn128 curve (EIP-197)
+///
+/// The input is a list of (G1, G2) points, the output is the word 1 if the product of their pairings is one, 0 otherwise
+fn bn128_pairing(input: &[u8], gas_limit: u64) -> PrecompileResult {
+    use bn::{pairing_batch, AffineG2, Fq, Fq2, Group, Gt, G2};
+
+    if input.len() % BN128_PAIR_LEN != 0 {
+        return Err(invalid_input());
+    }
+
+    let pairs_count = (input.len() / BN128_PAIR_LEN) as u64;
+    let cost = G_BN128_PAIRING_POINT
+        .checked_mul(pairs_count)
+        .and_then(|cost| cost.checked_add(G_BN128_PAIRING_BASE))
+        .ok_or(ExitError::OutOfGas)?;
+    charge(cost, gas_limit)?;
+
+    let mut pairs = Vec::with_capacity(pairs_count as usize);
+    for pair in input.chunks(BN128_PAIR_LEN) {
+        let g1 = read_g1(&pair[0..64])?;
+
+        // Fq2 coordinates are encoded with the imaginary part first
+        let read_fq = |bytes: &[u8]| Fq::from_slice(bytes).map_err(|_| invalid_input());
+        let x = Fq2::new(read_fq(&pair[96..128])?, read_fq(&pair[64..96])?);
+        let y = Fq2::new(read_fq(&pair[160..192])?, read_fq(&pair[128..160])?);
+
+        let g2 = if x.is_zero() && y.is_zero() {
+            G2::zero()
+        } else {
+            AffineG2::new(x, y).map_err(|_| invalid_input())?.into()
+        };
+
+        pairs.push((g1, g2));
+    }
+
+    let mut output = vec![0_u8; 32];
+    if pairing_batch(&pairs) == Gt::one() {
+        output[31] = 1;
+    }
+
+    Ok(PrecompileOutput { output, cost })
+}
+
+fn read_g1(bytes: &[u8]) -> Result<bn::G1, ExitError> {
+    use bn::{AffineG1, Fq, Group, G1};
+
+    let x = Fq::from_slice(&bytes[0..32]).map_err(|_| invalid_input())?;
+    let y = Fq::from_slice(&bytes[32..64]).map_err(|_| invalid_input())?;
+
+    // The point at infinity is encoded as (0, 0)
+    if x.is_zero() && y.is_zero() {
+        Ok(G1::zero())
+    } else {
+        Ok(AffineG1::new(x, y).map_err(|_| invalid_input())?.into())
+    }
+}
+
+fn write_fq(value: bn::Fq, bytes: &mut [u8]) -> Result<(), ExitError> {
+    value.to_big_endian(bytes).map_err(|_| invalid_input())
+}
+
+/// BLAKE2b compression function F (EIP-152)
+fn blake2f(input: &[u8], gas_limit: u64) -> PrecompileResult {
+    if input.len() != BLAKE2F_INPUT_LEN {
+        return Err(invalid_input());
+    }
+
+    let rounds = u32::from_be_bytes(input[0..4].try_into().expect("slice has 4 bytes"));
+    let cost = u64::from(rounds) * G_BLAKE2F_ROUND;
+    charge(cost, gas_limit)?;
+
+    let final_block = match input[212] {
+        0 => false,
+        1 => true,
+        _ => return Err(invalid_input()),
+    };
+
+    let read_u64 = |offset: usize| u64::from_le_bytes(input[offset..offset + 8].try_into().expect("slice has 8 bytes"));
+
+    let mut h = [0_u64; 8];
+    for (i, word) in h.iter_mut().enumerate() {
+        *word = read_u64(4 + i * 8);
+    }
+
+    let mut m = [0_u64; 16];
+    for (i, word) in m.iter_mut().enumerate() {
+        *word = read_u64(68 + i * 8);
+    }
+
+    let t = [read_u64(196), read_u64(204)];
+
+    blake2_compress(&mut h, &m, t, final_block, rounds as usize);
+
+    let output = h.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+    Ok(PrecompileOutput { output, cost })
+}
+
+const BLAKE2B_IV: [u64; 8] = [
+    0x6a09_e667_f3bc_c908,
+    0xbb67_ae85_84ca_a73b,
+    0x3c6e_f372_fe94_f82b,
+    0xa54f_f53a_5f1d_36f1,
+    0x510e_527f_ade6_82d1,
+    0x9b05_688c_2b3e_6c1f,
+    0x1f83_d9ab_fb41_bd6b,
+    0x5be0_cd19_137e_2179,
+];
+
+const BLAKE2B_SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+fn blake2_compress(h: &mut [u64; 8], m: &[u64; 16], t: [u64; 2], final_block: bool, rounds: usize) {
+    let mut v = [0_u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&BLAKE2B_IV);
+
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    if final_block {
+        v[14] = !v[14];
+    }
+
+    for round in 0..rounds {
+        let s = &BLAKE2B_SIGMA[round % 10];
+
+        blake2_mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        blake2_mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        blake2_mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        blake2_mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        blake2_mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        blake2_mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        blake2_mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        blake2_mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+#[allow(clippy::many_single_char_names)]
+fn blake2_mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// Runs the Terranova precompile at `address`, which must be checked with `is_terranova_precompile_address` first
+///
+/// It runs in the call frame entered for it, so changes made to `state` are discarded if the precompile or any enclosing call fails
+pub fn call_terranova_precompile<B: StorageInterface>(
+    address: H160,
+    context: &evm::Context,
+    input: &[u8],
+    gas_limit: u64,
+    state: &mut ExecutorState<B>,
+) -> PrecompileResult {
+    match address.as_bytes()[19] {
+        WITHDRAW => withdraw(context, input, gas_limit, state),
+        WASM_EXECUTE => wasm_execute(context, input, gas_limit, state),
+        WASM_CALL_STATUS => wasm_call_status(input, gas_limit, state),
+        WASM_QUERY => wasm_query(context, input, gas_limit, state),
+        _ => unreachable!(),
+    }
+}
+
+/// Input: the amount of the native denom as a 32 bytes big-endian integer, followed by the bech32 Terra recipient address\ 
+/// Debits the caller's EVM balance by the amount scaled to 18 decimals, the coins are sent once the transaction succeeds.
+/// Value can't be sent along, it would be stuck in the precompile account.
+/// DELEGATECALL and CALLCODE are rejected, the caller of their context isn't the account calling the precompile
+fn withdraw<B: StorageInterface>(context: &evm::Context, input: &[u8], gas_limit: u64, state: &mut ExecutorState<B>) -> PrecompileResult {
+    charge(G_WITHDRAW, gas_limit)?;
+
+    if state.metadata().is_static() {
+        return Err(ExitError::StaticModeViolation);
+    }
+
+    if context.address != withdraw_address() {
+        return Err(invalid_input());
+    }
+
+    if !context.apparent_value.is_zero() || input.len() <= 32 {
+        return Err(invalid_input());
+    }
+
+    let amount = U256::from_big_endian(&input[..32]);
+    if amount.is_zero() || amount > U256::from(u128::MAX) {
+        return Err(invalid_input());
+    }
+
+    let recipient = std::str::from_utf8(&input[32..]).map_err(|_| invalid_input())?;
+    let recipient = state.api().addr_validate(recipient).map_err(|_| invalid_input())?;
+
+    state.withdraw(context.caller, recipient, Uint128::from(amount.as_u128()))?;
+
+    Ok(PrecompileOutput { output: Vec::new(), cost: G_WITHDRAW })
+}
+
+/// Input: the ABI encoding of (string contract, bytes msg, uint256 funds), without a function selector.\ 
+/// Queues a `WasmMsg::Execute` of the JSON message `msg` to the Terra `contract`, which the admin must have allowed with AllowWasmExecuteTarget.\ 
+/// The message is sent as a submessage once the transaction succeeds,
+/// with `funds` of the native denom debited from the caller's EVM balance scaled to 18 decimals.
+/// The output is the id of the call, which the wasm call status precompile takes.
+///
+/// The message runs after the transaction, as the Terranova contract. If it fails, the funds are credited back to the caller.
+/// DELEGATECALL and CALLCODE are rejected, the caller of their context isn't the account calling the precompile
+fn wasm_execute<B: StorageInterface>(context: &evm::Context, input: &[u8], gas_limit: u64, state: &mut ExecutorState<B>) -> PrecompileResult {
+    let cost = linear_cost(input.len(), G_WASM_EXECUTE_BASE, G_WASM_EXECUTE_WORD)?;
+    charge(cost, gas_limit)?;
+
+    if state.metadata().is_static() {
+        return Err(ExitError::StaticModeViolation);
+    }
+
+    if context.address != wasm_execute_address() || !context.apparent_value.is_zero() {
+        return Err(invalid_input());
+    }
+
+    let contract = std::str::from_utf8(read_bytes(input, 0)?).map_err(|_| invalid_input())?;
+    let contract = state.api().addr_validate(contract).map_err(|_| invalid_input())?;
+    let msg = Binary::from(read_bytes(input, 1)?);
+    let funds = U256::from_big_endian(read_word(input, 2)?);
+    if funds > U256::from(u128::MAX) {
+        return Err(invalid_input());
+    }
+
+    // The Terranova contract calling itself would bypass the operator checks, and only admin allowed contracts
+    // are called so that EVM contracts can't move the CW20 tokens held for EVM accounts, see WASM_EXECUTE_TARGETS
+    if contract == *state.contract_address() || !state.wasm_execute_allowed(&contract) {
+        return Err(invalid_input());
+    }
+
+    let id = state.wasm_execute(context.caller, contract, msg, Uint128::from(funds.as_u128()))?;
+
+    let mut output = vec![0_u8; 32];
+    U256::from(id).to_big_endian(&mut output);
+
+    Ok(PrecompileOutput { output, cost })
+}
+
+/// Input: the id of a call returned by the wasm execute precompile, as a 32 bytes big-endian integer\ 
+/// The output word is 0 for an unknown call, otherwise the code of its WasmCallStatus.
+/// Calls made by the current transaction are unknown until it succeeds
+fn wasm_call_status<B: StorageInterface>(input: &[u8], gas_limit: u64, state: &mut ExecutorState<B>) -> PrecompileResult {
+    charge(G_WASM_CALL_STATUS, gas_limit)?;
+
+    let id = U256::from_big_endian(&padded(input, 0, 32));
+    let status = if id > U256::from(u64::MAX) {
+        None
+    } else {
+        state.wasm_call_status(id.as_u64())
+    };
+
+    let mut output = vec![0_u8; 32];
+    output[31] = status.map_or(0, WasmCallStatus::code);
+
+    Ok(PrecompileOutput { output, cost: G_WASM_CALL_STATUS })
+}
+
+/// Input: the JSON of a CosmWasm `QueryRequest`, either a bank query or a smart or raw wasm query\ 
+/// The output is the JSON response of the query as returned by the querier, or the raw value for a raw query.
+/// It only reads state, so it can be called in a static context and with DELEGATECALL.
+/// Gas is charged per word of the request before the query, and per word of the response after it.
+/// A failed query fails the call, the Terranova contract itself can't be queried
+fn wasm_query<B: StorageInterface>(context: &evm::Context, input: &[u8], gas_limit: u64, state: &mut ExecutorState<B>) -> PrecompileResult {
+    let cost = linear_cost(input.len(), G_WASM_QUERY_BASE, G_WASM_QUERY_WORD)?;
+    charge(cost, gas_limit)?;
+
+    if !context.apparent_value.is_zero() {
+        return Err(invalid_input());
+    }
+
+    let request: QueryRequest<Empty> = from_slice(input).map_err(|_| invalid_input())?;
+    match &request {
+        QueryRequest::Bank(_) => {}
+        QueryRequest::Wasm(WasmQuery::Smart { contract_addr, .. } | WasmQuery::Raw { contract_addr, .. }) => {
+            // Querying the contract while it executes would re-enter it
+            if contract_addr == state.contract_address().as_str() {
+                return Err(invalid_input());
+            }
+        }
+        _ => return Err(invalid_input()),
+    }
+
+    let output = match state.querier().raw_query(input) {
+        SystemResult::Ok(ContractResult::Ok(response)) => response.to_vec(),
+        _ => return Err(invalid_input()),
+    };
+
+    let cost = cost.checked_add(linear_cost(output.len(), 0, G_WASM_QUERY_WORD)?).ok_or(ExitError::OutOfGas)?;
+    charge(cost, gas_limit)?;
+
+    Ok(PrecompileOutput { output, cost })
+}
+
+pub(crate) fn charge(cost: u64, gas_limit: u64) -> Result<(), ExitError> {
+    if cost > gas_limit {
+        return Err(ExitError::OutOfGas);
+    }
+
+    Ok(())
+}
+
+/// Base cost plus a cost per word of input
+fn linear_cost(len: usize, base: u64, word: u64) -> Result<u64, ExitError> {
+    let words = (len as u64).saturating_add(31) / 32;
+
+    words.checked_mul(word)
+        .and_then(|cost| cost.checked_add(base))
+        .ok_or(ExitError::OutOfGas)
+}
+
+/// The ABI encoded word at `index`, a missing word is an error
+pub(crate) fn read_word(input: &[u8], index: usize) -> Result<&[u8], ExitError> {
+    input.get(index * 32..(index + 1) * 32).ok_or_else(invalid_input)
+}
+
+/// The ABI encoded `bytes` or `string` whose offset is the word at `index`, the offset points to its length followed by its bytes
+pub(crate) fn read_bytes(input: &[u8], index: usize) -> Result<&[u8], ExitError> {
+    let offset = U256::from_big_endian(read_word(input, index)?);
+    if offset > U256::from(input.len()) {
+        return Err(invalid_input());
+    }
+    let offset = offset.as_usize();
+
+    let len = U256::from_big_endian(input.get(offset..offset + 32).ok_or_else(invalid_input)?);
+    if len > U256::from(input.len()) {
+        return Err(invalid_input());
+    }
+    let len = len.as_usize();
+
+    input.get(offset + 32..offset + 32 + len).ok_or_else(invalid_input)
+}
+
+/// Returns `len` bytes of `input` starting at `offset`, input past its end is read as zeros
+fn padded(input: &[u8], offset: usize, len: usize) -> Vec<u8> {
+    let mut data = vec![0_u8; len];
+
+    if offset < input.len() {
+        let available = (input.len() - offset).min(len);
+        data[..available].copy_from_slice(&input[offset..offset + available]);
+    }
+
+    data
+}
+
+fn to_usize(value: U256) -> Result<usize, ExitError> {
+    if value > U256::from(usize::MAX) {
+        return Err(ExitError::OutOfGas);
+    }
+
+    Ok(value.as_usize())
+}
+
+pub(crate) const fn invalid_input() -> ExitError {
+    ExitError::InvalidRange
+}
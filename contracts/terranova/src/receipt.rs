@@ -0,0 +1,55 @@
+use evm::{backend::Log, H160, H256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::keccak256_h256;
+
+/// Size in bytes of the 2048 bit logs bloom filter of a receipt
+pub const BLOOM_SIZE: usize = 256;
+
+/// Ethereum style receipt of an executed transaction, saved to RECEIPTS under the transaction hash
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TransactionReceipt {
+    /// hash of the transaction, see transaction::unsigned_tx_hash
+    pub tx_hash: H256,
+    /// Height of the Terra block in which the transaction was executed
+    pub block_height: u64,
+    pub from: H160,
+    /// None for contract creation transactions
+    pub to: Option<H160>,
+    /// 1 if the transaction succeeded, 0 if it failed or reverted, as in Ethereum receipts
+    pub status: u8,
+    pub gas_used: U256,
+    /// Gas used by all EVM transactions of the block so far, including this one
+    pub cumulative_gas_used: U256,
+    /// Logs emitted by the transaction, always empty for failed transactions
+    pub logs: Vec<Log>,
+    /// BLOOM_SIZE bytes bloom filter of the log addresses and topics
+    pub logs_bloom: Vec<u8>,
+    /// Address of the contract deployed by a successful contract creation transaction
+    pub contract_address: Option<H160>,
+    /// Data returned by REVERT, empty otherwise
+    pub revert_data: Vec<u8>,
+}
+
+/// Bloom filter of the addresses and topics of the logs, as defined in the Ethereum yellow paper:
+/// each entry sets the three bits selected by the low 11 bits of the first three byte pairs of its keccak hash
+pub fn logs_bloom(logs: &[Log]) -> Vec<u8> {
+    let mut bloom = vec![0_u8; BLOOM_SIZE];
+
+    let mut accrue = |input: &[u8]| {
+        let hash = keccak256_h256(input);
+        for i in 0..3 {
+            let bit = (usize::from(hash[2 * i]) << 8 | usize::from(hash[2 * i + 1])) & (BLOOM_SIZE * 8 - 1);
+            bloom[BLOOM_SIZE - 1 - bit / 8] |= 1 << (bit % 8);
+        }
+    };
+
+    for log in logs {
+        accrue(log.address.as_bytes());
+        for topic in &log.topics {
+            accrue(topic.as_bytes());
+        }
+    }
+
+    bloom
+}
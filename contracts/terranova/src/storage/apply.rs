@@ -1,23 +1,35 @@
-use std::{collections::BTreeMap, convert::TryInto};
+use std::{collections::BTreeMap, convert::TryInto, mem};
 
-use cosmwasm_std::{StdError, Order};
-use evm::{backend::Apply, U256, H160, Transfer};
+use cosmwasm_std::{coins, to_binary, BankMsg, SubMsg, StdError, StdResult, Order, Uint128, WasmMsg};
+use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::U64Key;
+use evm::{backend::{Apply, Log}, U256, H160, H256, Transfer};
 
-use crate::{storage::{CwStorageInterface}, executor_state::ApplyState, ContractError, account::{EvmAccount, EvmContract}};
+use crate::{storage::{CwStorageInterface}, executor_state::{written_addresses, ApplyState, Withdraw, Cw20Transfer, Cw20Approve, Cw20Recipient, WasmCall}, ContractError, account::{EvmAccount, EvmContract}, receipt::TransactionReceipt, pending_tx::{AccountLocks, PendingTx}, config::{evm_to_native, native_to_evm}, erc20::{self, Cw20Token}, wasm_call::{WasmCallRecord, WasmCallStatus}};
 
-use super::{backend::{ACCOUNTS, CONTRACTS, CONTRACT_STORAGE}, StorageInterface, Readable, Writable};
+use super::{backend::{ACCOUNTS, BLOCK_HASHES, BLOCK_HASH_HISTORY, CONTRACTS, CONTRACT_STORAGE, LEGACY_CONTRACT_STORAGE, STALE_STORAGE, TX_LOGS, RECEIPTS, BLOCK_GAS_USED, NATIVE_SUPPLY, CW20_TOKENS, CW20_FACADES, CW20_BALANCES, CW20_SUPPLIES, CW20_ALLOWANCES, WASM_CALL_COUNT, WASM_CALLS, WASM_EXECUTE_TARGETS, PENDING_TXS, derive_block_hash}, StorageInterface, Readable, Writable};
 
 /// Write operations on the backend EVM state
 /// Methods to apply the results of a completed transaction to persistent EVM state
 impl<S: Readable + Writable> CwStorageInterface<S> {
+    /// Returns the logs emitted by the transaction, to be emitted as events and optionally saved with save_logs,
+    /// and the messages sending the withdrawn native coins and CW20 tokens, then the CosmWasm messages sent by EVM contracts,
+    /// to be added to the response
     pub fn apply_state_change(
         &mut self,
         state: ApplyState,
-    ) -> Result<(), ContractError> {
+    ) -> Result<(Vec<Log>, Vec<SubMsg>), ContractError> {
+        // Accounts locked by transactions in progress can't be changed under them
+        self.check_write_locks(&written_addresses(&state))?;
+
         let (
             applies,
             logs,
             transfers,
+            withdrawals,
+            cw20_transfers,
+            cw20_approves,
+            wasm_calls,
         ) = state;
 
         debug_print!("Applies begin");
@@ -30,11 +42,22 @@ impl<S: Readable + Writable> CwStorageInterface<S> {
             self.apply_contract_results(applies)?;
         }
 
+        // Withdrawals come after transfers, so that value received during the transaction can be withdrawn
+        let mut messages = self.apply_withdrawals(withdrawals)?;
+
+        // Allowances are only ever spent by transfers, the transfers already checked them
+        self.apply_cw20_approves(cw20_approves)?;
+        messages.append(&mut self.apply_cw20_transfers(cw20_transfers)?);
+        messages.append(&mut self.apply_wasm_calls(wasm_calls)?);
+
         debug_print!("Applies done");
 
-        for log in logs {
-            // Do nothing for now
-        }
+        Ok((logs, messages))
+    }
+
+    /// Saves the logs emitted by a transaction to TX_LOGS, under the transaction hash
+    pub fn save_logs(&mut self, tx_hash: &H256, logs: &[Log]) -> Result<(), ContractError> {
+        TX_LOGS.save(self.cw_deps.get_mut(), tx_hash.as_bytes(), &logs.to_vec())?;
 
         Ok(())
     }
@@ -56,6 +79,240 @@ impl<S: Readable + Writable> CwStorageInterface<S> {
         Ok(())
     }
 
+    /// Deducts the maximum gas fee of a transaction from the caller balance before execution,
+    /// so that value transfers made during execution can't spend the funds needed to pay for gas
+    pub fn prepay_gas(&mut self, caller: &H160, max_fee: U256) -> Result<(), ContractError> {
+        self.check_write_locks([caller])?;
+
+        let balance = self.balance(caller);
+        let new_balance = balance.checked_sub(max_fee)
+            .ok_or_else(|| E!(ContractError::InsufficientFunds; "Account {} - balance {} can't pay gas fee {}", caller, balance, max_fee))?;
+
+        self.write_balance(caller, new_balance)
+    }
+
+    /// Refunds the unused part of the prepaid gas fee to the caller and pays the actual fee to the fee collector
+    pub fn settle_gas(&mut self, caller: &H160, fee_collector: &H160, prepaid_fee: U256, fee: U256) -> Result<(), ContractError> {
+        let refund = prepaid_fee.checked_sub(fee)
+            .ok_or_else(|| E!(ContractError::FeeExceedsPrepaid; "Fee {} exceeds the prepaid fee {}", fee, prepaid_fee))?;
+        if !refund.is_zero() {
+            let caller_balance = self.balance(caller).checked_add(refund)
+                .ok_or_else(|| E!(ContractError::BalanceOverflow; "Account {} - balance overflow", caller))?;
+            self.write_balance(caller, caller_balance)?;
+        }
+
+        if !ACCOUNTS.has(self.cw_deps.get_ref(), fee_collector) {
+            self.init_new_account(fee_collector)?;
+        }
+
+        let collector_balance = self.balance(fee_collector).checked_add(fee)
+            .ok_or_else(|| E!(ContractError::BalanceOverflow; "Account {} - balance overflow", fee_collector))?;
+
+        self.write_balance(fee_collector, collector_balance)
+    }
+
+    /// Credits an EVM balance with native coins deposited to the contract
+    pub fn deposit_native_tokens(&mut self, address: &H160, amount: Uint128) -> Result<(), ContractError> {
+        self.check_write_locks([address])?;
+
+        self.credit_native_tokens(address, amount)
+    }
+
+    /// Credits native coins held by the contract to an EVM balance, whether or not a transaction in progress locks the account.\ 
+    /// Balances are only ever changed by adding to or subtracting from the stored value, so the transaction still applies on top of it
+    fn credit_native_tokens(&mut self, address: &H160, amount: Uint128) -> Result<(), ContractError> {
+        let supply = NATIVE_SUPPLY.may_load(self.cw_deps.get_ref())?.unwrap_or_default()
+            .checked_add(amount)
+            .map_err(StdError::from)?;
+
+        if !ACCOUNTS.has(self.cw_deps.get_ref(), address) {
+            self.init_new_account(address)?;
+        }
+
+        let balance = self.balance(address).checked_add(native_to_evm(amount))
+            .ok_or_else(|| E!(ContractError::BalanceOverflow; "Account {} - balance overflow", address))?;
+
+        self.write_balance(address, balance)?;
+        NATIVE_SUPPLY.save(self.cw_deps.get_mut(), &supply)?;
+
+        Ok(())
+    }
+
+    /// Debits an EVM balance for native coins to be sent out of the contract
+    pub fn withdraw_native_tokens(&mut self, address: &H160, amount: Uint128) -> Result<(), ContractError> {
+        self.check_write_locks([address])?;
+
+        let value = native_to_evm(amount);
+        let balance = self.balance(address).checked_sub(value)
+            .ok_or_else(|| E!(ContractError::InsufficientFunds; "Account {} - balance {} is lower than withdrawal {}", address, self.balance(address), value))?;
+
+        let supply = NATIVE_SUPPLY.may_load(self.cw_deps.get_ref())?.unwrap_or_default();
+        let supply = supply.checked_sub(amount)
+            .map_err(|_| E!(ContractError::InsufficientSupply; "Withdrawal of {} exceeds native supply {}", amount, supply))?;
+
+        self.write_balance(address, balance)?;
+        NATIVE_SUPPLY.save(self.cw_deps.get_mut(), &supply)?;
+
+        Ok(())
+    }
+
+    /// Registers a CW20 token and deploys its ERC-20 facade, returns the address of the facade
+    pub fn register_cw20(&mut self, token: &Cw20Token) -> Result<H160, ContractError> {
+        let facade = erc20::facade_address(&token.contract);
+
+        if CW20_FACADES.has(self.cw_deps.get_ref(), &token.contract) || ACCOUNTS.has(self.cw_deps.get_ref(), &facade) {
+            return Err!(ContractError::Cw20AlreadyRegistered; "CW20 {} - facade {} already exists", token.contract, facade);
+        }
+
+        CW20_TOKENS.save(self.cw_deps.get_mut(), &facade, token)?;
+        CW20_FACADES.save(self.cw_deps.get_mut(), &token.contract, &facade)?;
+        // EVM contracts can no longer send messages to the token, see WASM_EXECUTE_TARGETS
+        WASM_EXECUTE_TARGETS.remove(self.cw_deps.get_mut(), &token.contract);
+
+        let code = erc20::FACADE_CODE.to_vec();
+        let valids = evm::Valids::compute(&code);
+        self.update_contract_account(facade, U256::one(), Some((code, valids)), BTreeMap::new(), false)?;
+
+        Ok(facade)
+    }
+
+    /// Credits the EVM account with CW20 tokens sent to the contract, `token` is the address of the facade
+    pub fn deposit_cw20(&mut self, token: &H160, owner: &H160, amount: Uint128) -> Result<(), ContractError> {
+        self.check_write_locks([owner])?;
+
+        let supply = self.cw20_supply(token).checked_add(amount).map_err(StdError::from)?;
+        let balance = self.cw20_balance(token, owner).checked_add(amount).map_err(StdError::from)?;
+
+        CW20_SUPPLIES.save(self.cw_deps.get_mut(), token, &supply)?;
+        CW20_BALANCES.save(self.cw_deps.get_mut(), (token, owner), &balance)?;
+
+        Ok(())
+    }
+
+    /// Adds the gas used by a transaction to the gas used by the current block, returns the new total
+    pub fn record_block_gas(&mut self, gas_used: U256) -> Result<U256, ContractError> {
+        let height = self.cw_env.block.height;
+
+        let block_gas_used = match BLOCK_GAS_USED.may_load(self.cw_deps.get_ref())? {
+            Some((recorded_height, recorded_gas)) if recorded_height == height => recorded_gas.saturating_add(gas_used),
+            _ => gas_used,
+        };
+        BLOCK_GAS_USED.save(self.cw_deps.get_mut(), &(height, block_gas_used))?;
+
+        Ok(block_gas_used)
+    }
+
+    pub fn save_receipt(&mut self, receipt: &TransactionReceipt) -> Result<(), ContractError> {
+        RECEIPTS.save(self.cw_deps.get_mut(), receipt.tx_hash.as_bytes(), receipt)?;
+
+        Ok(())
+    }
+
+    /// Acquires the locks not held yet by the transaction in progress, `locks` must include the held locks.\ 
+    /// A read lock held on an account the transaction has since changed is upgraded to a write lock,
+    /// which fails if other transactions in progress hold read locks on it too
+    pub fn lock_accounts(&mut self, locks: AccountLocks) -> Result<(), ContractError> {
+        self.check_write_locks(&locks.write)?;
+
+        for address in &locks.write {
+            if self.held_locks.write.contains(address) {
+                continue;
+            }
+
+            let upgrade = self.held_locks.read.contains(address);
+            self.update_account_lock(address, |account| {
+                account.rw_blocked = true;
+                if upgrade {
+                    account.ro_blocked_count = account.ro_blocked_count.checked_sub(1)
+                        .ok_or_else(|| E!(ContractError::AccountLocked; "Account {} - read lock to upgrade is missing", account.address))?;
+                }
+
+                Ok(())
+            })?;
+        }
+
+        for address in &locks.read {
+            if self.held_locks.read.contains(address) {
+                continue;
+            }
+
+            self.update_account_lock(address, |account| {
+                account.ro_blocked_count = account.ro_blocked_count.checked_add(1)
+                    .ok_or_else(|| E!(ContractError::AccountLocked; "Account {} - too many read locks", account.address))?;
+
+                Ok(())
+            })?;
+        }
+
+        self.held_locks = locks;
+
+        Ok(())
+    }
+
+    /// Releases the locks held by the transaction in progress once its execution completes or is cancelled.
+    /// Accounts deleted by the transaction have no lock left to release
+    pub fn unlock_accounts(&mut self) -> Result<(), ContractError> {
+        let locks = mem::take(&mut self.held_locks);
+
+        for address in &locks.write {
+            if ACCOUNTS.has(self.cw_deps.get_ref(), address) {
+                self.update_account_lock(address, |account| {
+                    account.rw_blocked = false;
+                    Ok(())
+                })?;
+            }
+        }
+
+        for address in &locks.read {
+            if ACCOUNTS.has(self.cw_deps.get_ref(), address) {
+                self.update_account_lock(address, |account| {
+                    account.ro_blocked_count = account.ro_blocked_count.saturating_sub(1);
+                    Ok(())
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Locks are kept in the EvmAccount, accounts that don't exist yet are created empty to hold them
+    fn update_account_lock(
+        &mut self,
+        address: &H160,
+        update: impl FnOnce(&mut EvmAccount) -> Result<(), ContractError>,
+    ) -> Result<(), ContractError> {
+        self.init_new_account(address)?;
+
+        let mut account = ACCOUNTS.load(self.cw_deps.get_ref(), address)?;
+        update(&mut account)?;
+        ACCOUNTS.save(self.cw_deps.get_mut(), address, &account)?;
+
+        Ok(())
+    }
+
+    pub fn save_pending_tx(&mut self, pending_tx: &PendingTx) -> Result<(), ContractError> {
+        PENDING_TXS.save(self.cw_deps.get_mut(), &pending_tx.prepaid.tx_hash, pending_tx)?;
+
+        Ok(())
+    }
+
+    pub fn remove_pending_tx(&mut self, tx_hash: &H256) {
+        PENDING_TXS.remove(self.cw_deps.get_mut(), tx_hash);
+    }
+
+    /// Saves the hash of the current block to the BLOCK_HASHES ring buffer, overwriting the block BLOCK_HASH_HISTORY heights before
+    pub fn record_block_hash(&mut self) -> Result<(), ContractError> {
+        let height = self.cw_env.block.height;
+        let slot = U64Key::new(height % BLOCK_HASH_HISTORY);
+
+        let recorded = BLOCK_HASHES.may_load(self.cw_deps.get_ref(), slot.clone())?;
+        if !matches!(recorded, Some((recorded_height, _)) if recorded_height == height) {
+            BLOCK_HASHES.save(self.cw_deps.get_mut(), slot, &(height, derive_block_hash(&self.cw_env)))?;
+        }
+
+        Ok(())
+    }
+
     pub fn airdrop_write_balance(&mut self, address: &H160) {
         println!("Setting balance of {} to 100,000,000", address);
         if !ACCOUNTS.has(self.cw_deps.get_ref(), address) {
@@ -79,23 +336,44 @@ impl<S: Readable + Writable> CwStorageInterface<S> {
     fn init_new_account(&mut self, address: &H160) -> Result<(), ContractError> {
         // If account already exists in the EVM state, do nothing
         if !ACCOUNTS.has(self.cw_deps.get_ref(), address) {
+            let mut account = EvmAccount::new_user_account(address);
+
+            // The storage of a contract previously deleted at the address may not be pruned yet
+            account.storage_generation = STALE_STORAGE
+                .prefix(address)
+                .range(self.cw_deps.get_ref(), None, None, Order::Descending)
+                .next()
+                .transpose()?
+                .map_or(0, |(_, (_, generation))| generation + 1);
+
             ACCOUNTS.save(
                 self.cw_deps.get_mut(),
                 address,
-                &EvmAccount::new_user_account(address)
+                &account
             )?;
         }
 
         Ok(())
     }
 
-    /// TODO: 
+    /// Removes an account destroyed by SELFDESTRUCT, along with its code and storage, so that a contract re-created at the address starts empty.\ 
+    /// The account may not be a deployed contract, when it was destroyed by its own init code.
+    /// Its balance was moved to the beneficiary by the transfers of the transaction, value it received after SELFDESTRUCT is burned like in Ethereum,
+    /// as is the balance of a contract destroyed in favor of itself. The native coins backing a burned balance are taken out of NATIVE_SUPPLY
     fn delete_account(&mut self, address: &H160) -> Result<(), ContractError> {
-        // Accounts can only be deleted by calling suicide() in contract code
-        assert!(CONTRACTS.has(self.cw_deps.get_ref(), address));
+        let burned = evm_to_native(self.balance(address));
+        if !burned.is_zero() {
+            // Balances airdropped without a deposit aren't part of the supply
+            let supply = NATIVE_SUPPLY.may_load(self.cw_deps.get_ref())?.unwrap_or_default();
+            NATIVE_SUPPLY.save(self.cw_deps.get_mut(), &supply.saturating_sub(burned))?;
+        }
+
+        if CONTRACTS.has(self.cw_deps.get_ref(), address) {
+            self.write_stale_storage(address)?;
+            CONTRACTS.remove(self.cw_deps.get_mut(), address);
+        }
 
         ACCOUNTS.remove(self.cw_deps.get_mut(), address);
-        CONTRACTS.remove(self.cw_deps.get_mut(), address);
 
         Ok(())
     }
@@ -179,35 +457,146 @@ impl<S: Readable + Writable> CwStorageInterface<S> {
             return Err(StdError::NotFound { kind: "EvmContract".to_string() }.into())
         }
 
+        let generation = ACCOUNTS.load(self.cw_deps.get_ref(), address)?.storage_generation;
+
         CONTRACT_STORAGE.save(
             self.cw_deps.get_mut(),
-            (address, &key.to_bytes()),
+            (address, U64Key::new(generation), &key.to_bytes()),
             &value
         )?;
 
+        // The new entry replaces the one saved before the generations, see LEGACY_CONTRACT_STORAGE
+        if generation == 0 {
+            LEGACY_CONTRACT_STORAGE.remove(self.cw_deps.get_mut(), (address, &key.to_bytes()));
+        }
+
         Ok(())  
     }
 
-    fn write_clear_storage(&mut self, address: &H160) -> Result<(), ContractError> {
-        if !CONTRACTS.has(self.cw_deps.get_ref(), address) {
-            return Err(StdError::NotFound { kind: "EvmContract".to_string() }.into())
+    /// Moves the account on to a new storage generation, the entries of the current one are left to PruneStorage.\ 
+    /// The generation only changes if the current one has entries
+    fn write_reset_storage(&mut self, address: &H160) -> Result<(), ContractError> {
+        if !self.write_stale_storage(address)? {
+            return Ok(())
         }
 
-        let storage_keys: Vec<Result<(Vec<u8>, U256), StdError>> = CONTRACT_STORAGE
+        ACCOUNTS.update(
+            self.cw_deps.get_mut(),
+            address,
+            |maybe_account| {
+                if let Some(mut account) = maybe_account {
+                    account.storage_generation += 1;
+                    Ok(account)
+                } else {
+                    Err(StdError::NotFound { kind: "EvmAccount".to_string() })
+                }
+            }
+        )?;
+
+        Ok(())
+    }
+
+    /// Marks the current storage generation of the account as stale if it has any entry, returns whether it did
+    fn write_stale_storage(&mut self, address: &H160) -> Result<bool, ContractError> {
+        let generation = ACCOUNTS.load(self.cw_deps.get_ref(), address)?.storage_generation;
+
+        let is_empty = CONTRACT_STORAGE
+            .prefix((address, U64Key::new(generation)))
+            .keys(self.cw_deps.get_ref(), None, None, Order::Ascending)
+            .next()
+            .is_none();
+        let has_legacy_storage = generation == 0 && LEGACY_CONTRACT_STORAGE
             .prefix(address)
+            .keys(self.cw_deps.get_ref(), None, None, Order::Ascending)
+            .next()
+            .is_some();
+        if is_empty && !has_legacy_storage {
+            return Ok(false)
+        }
+
+        STALE_STORAGE.save(
+            self.cw_deps.get_mut(),
+            (address, U64Key::new(generation)),
+            &(*address, generation)
+        )?;
+
+        Ok(true)
+    }
+
+    /// Removes up to `limit` CONTRACT_STORAGE entries of stale storage generations, oldest addresses first.
+    /// Returns the number of entries removed, less than `limit` once there is nothing left to prune
+    pub fn prune_storage(&mut self, limit: u32) -> Result<u32, ContractError> {
+        let mut pruned = 0;
+
+        while pruned < limit {
+            let stale = STALE_STORAGE
+                .range(self.cw_deps.get_ref(), None, None, Order::Ascending)
+                .next()
+                .transpose()?;
+            let (address, generation) = match stale {
+                Some((_, stale)) => stale,
+                None => break,
+            };
+
+            let mut keys: Vec<Vec<u8>> = CONTRACT_STORAGE
+                .prefix((&address, U64Key::new(generation)))
+                .keys(self.cw_deps.get_ref(), None, None, Order::Ascending)
+                .take((limit - pruned) as usize)
+                .collect();
+
+            for key in &keys {
+                CONTRACT_STORAGE.remove(self.cw_deps.get_mut(), (&address, U64Key::new(generation), key));
+            }
+
+            // Generation 0 also has the entries saved before the generations that weren't migrated yet
+            if generation == 0 && keys.len() < (limit - pruned) as usize {
+                let legacy_keys: Vec<Vec<u8>> = LEGACY_CONTRACT_STORAGE
+                    .prefix(&address)
+                    .keys(self.cw_deps.get_ref(), None, None, Order::Ascending)
+                    .take((limit - pruned) as usize - keys.len())
+                    .collect();
+
+                for key in &legacy_keys {
+                    LEGACY_CONTRACT_STORAGE.remove(self.cw_deps.get_mut(), (&address, key));
+                }
+
+                keys.extend(legacy_keys);
+            }
+
+            // The generation is done once a batch doesn't fill the remaining limit
+            if keys.len() < (limit - pruned) as usize {
+                STALE_STORAGE.remove(self.cw_deps.get_mut(), (&address, U64Key::new(generation)));
+            }
+
+            pruned += keys.len() as u32;
+        }
+
+        Ok(pruned)
+    }
+
+    /// Moves up to `limit` LEGACY_CONTRACT_STORAGE entries to generation 0 of CONTRACT_STORAGE, which every account
+    /// saved before the generations is at. Entries of accounts whose storage was reset since then are dropped instead.
+    /// Returns the number of entries moved or dropped, less than `limit` once there is nothing left to migrate
+    pub fn migrate_legacy_storage(&mut self, limit: u32) -> Result<u32, ContractError> {
+        // Keys read back from a range are the length prefixed address followed by the storage key
+        let entries = LEGACY_CONTRACT_STORAGE
             .range(self.cw_deps.get_ref(), None, None, Order::Ascending)
-            .collect();
+            .take(limit as usize)
+            .collect::<StdResult<Vec<(Vec<u8>, U256)>>>()?;
 
-        for entry in storage_keys {
-            let entry = entry?;
+        for (key, value) in &entries {
+            let address = H160::from_slice(&key[2..22]);
+            let index = &key[22..];
 
-            CONTRACT_STORAGE.remove(
-                self.cw_deps.get_mut(),
-                (address, &entry.0)
-            )
+            let generation = ACCOUNTS.may_load(self.cw_deps.get_ref(), &address)?
+                .map_or(0, |account| account.storage_generation);
+            if generation == 0 {
+                CONTRACT_STORAGE.save(self.cw_deps.get_mut(), (&address, U64Key::new(0), index), value)?;
+            }
+            LEGACY_CONTRACT_STORAGE.remove(self.cw_deps.get_mut(), (&address, index));
         }
 
-        Ok(()) 
+        Ok(entries.len() as u32)
     }
 
     fn apply_transfers(&mut self, transfers: Vec<Transfer>) -> Result<(), ContractError> {
@@ -220,6 +609,137 @@ impl<S: Readable + Writable> CwStorageInterface<S> {
         Ok(())
     }
     
+    fn apply_withdrawals(&mut self, withdrawals: Vec<Withdraw>) -> Result<Vec<SubMsg>, ContractError> {
+        debug_print!("apply_withdrawals: {:?}", withdrawals);
+
+        let denom = self.config.denom.clone();
+        let mut messages = Vec::with_capacity(withdrawals.len());
+
+        for withdraw in withdrawals {
+            self.withdraw_native_tokens(&withdraw.source, withdraw.amount)?;
+
+            messages.push(SubMsg::new(BankMsg::Send {
+                to_address: withdraw.recipient.into_string(),
+                amount: coins(withdraw.amount.u128(), &denom),
+            }));
+        }
+
+        Ok(messages)
+    }
+
+    fn apply_cw20_approves(&mut self, approves: Vec<Cw20Approve>) -> Result<(), ContractError> {
+        debug_print!("apply_cw20_approves: {:?}", approves);
+
+        for approve in approves {
+            let key = (&approve.token, &approve.owner, &approve.spender);
+            if approve.amount.is_zero() {
+                CW20_ALLOWANCES.remove(self.cw_deps.get_mut(), key);
+            } else {
+                CW20_ALLOWANCES.save(self.cw_deps.get_mut(), key, &approve.amount)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Transfers to a Terra address leave the EVM, they are sent with a CW20 transfer from the contract
+    fn apply_cw20_transfers(&mut self, transfers: Vec<Cw20Transfer>) -> Result<Vec<SubMsg>, ContractError> {
+        debug_print!("apply_cw20_transfers: {:?}", transfers);
+
+        let mut messages = Vec::new();
+
+        for Cw20Transfer { token, source, target, amount } in transfers {
+            let source_balance = self.cw20_balance(&token, &source);
+            let source_balance = source_balance.checked_sub(amount)
+                .map_err(|_| E!(ContractError::InsufficientFunds; "Account {} - CW20 {} balance {} is lower than {}", source, token, source_balance, amount))?;
+            CW20_BALANCES.save(self.cw_deps.get_mut(), (&token, &source), &source_balance)?;
+
+            match target {
+                Cw20Recipient::Evm(target) => {
+                    let target_balance = self.cw20_balance(&token, &target).checked_add(amount)
+                        .map_err(|_| E!(ContractError::BalanceOverflow; "Account {} - CW20 {} balance overflow", target, token))?;
+                    CW20_BALANCES.save(self.cw_deps.get_mut(), (&token, &target), &target_balance)?;
+                }
+                Cw20Recipient::Terra(recipient) => {
+                    let contract = CW20_TOKENS.may_load(self.cw_deps.get_ref(), &token)?
+                        .ok_or_else(|| E!(ContractError::UnknownCw20Token; "Facade {} is not registered", token))?
+                        .contract;
+                    let supply = self.cw20_supply(&token);
+                    let supply = supply.checked_sub(amount)
+                        .map_err(|_| E!(ContractError::InsufficientSupply; "CW20 {} - transfer of {} exceeds supply {}", contract, amount, supply))?;
+                    CW20_SUPPLIES.save(self.cw_deps.get_mut(), &token, &supply)?;
+
+                    messages.push(SubMsg::new(WasmMsg::Execute {
+                        contract_addr: contract.into_string(),
+                        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                            recipient: recipient.into_string(),
+                            amount,
+                        })?,
+                        funds: Vec::new(),
+                    }));
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// Each call is sent as a submessage replying with its id, so that its status can be updated by `settle_wasm_call`
+    fn apply_wasm_calls(&mut self, calls: Vec<WasmCall>) -> Result<Vec<SubMsg>, ContractError> {
+        debug_print!("apply_wasm_calls: {:?}", calls);
+
+        let denom = self.config.denom.clone();
+        let mut messages = Vec::with_capacity(calls.len());
+
+        for call in calls {
+            let funds = if call.funds.is_zero() {
+                Vec::new()
+            } else {
+                self.withdraw_native_tokens(&call.source, call.funds)?;
+                coins(call.funds.u128(), &denom)
+            };
+
+            WASM_CALLS.save(self.cw_deps.get_mut(), U64Key::new(call.id), &WasmCallRecord {
+                source: call.source,
+                funds: call.funds,
+                status: WasmCallStatus::Pending,
+            })?;
+            WASM_CALL_COUNT.save(self.cw_deps.get_mut(), &(call.id + 1))?;
+
+            messages.push(SubMsg::reply_always(WasmMsg::Execute {
+                contract_addr: call.contract.into_string(),
+                msg: call.msg,
+                funds,
+            }, call.id));
+        }
+
+        Ok(messages)
+    }
+
+    /// Records the outcome of a wasm call once its submessage replied, the funds of a failed call are credited back to its source
+    pub fn settle_wasm_call(&mut self, id: u64, succeeded: bool) -> Result<WasmCallRecord, ContractError> {
+        let mut record = WASM_CALLS.may_load(self.cw_deps.get_ref(), U64Key::new(id))?
+            .ok_or(StdError::NotFound { kind: "WasmCallRecord".to_string() })?;
+
+        if record.status != WasmCallStatus::Pending {
+            return Err(StdError::generic_err(format!("Wasm call {} was already settled", id)).into());
+        }
+
+        if succeeded {
+            record.status = WasmCallStatus::Succeeded;
+        } else {
+            record.status = WasmCallStatus::Failed;
+            // The reply can't be retried, so the refund doesn't wait for a transaction in progress to release the source
+            if !record.funds.is_zero() {
+                self.credit_native_tokens(&record.source, record.funds)?;
+            }
+        }
+
+        WASM_CALLS.save(self.cw_deps.get_mut(), U64Key::new(id), &record)?;
+
+        Ok(record)
+    }
+
     /// Make a transfer between the native balances of two EVM accounts
     fn transfer_native_tokens(&mut self, source: H160, target: H160, value: U256) -> Result<(), ContractError> {
         // If sender is sending to their own address, no change should occur
@@ -298,7 +818,7 @@ impl<S: Readable + Writable> CwStorageInterface<S> {
 
         if reset_storage | !storage.is_empty() {
             if reset_storage {
-                self.write_clear_storage(&address)?;
+                self.write_reset_storage(&address)?;
             }
 
             for (key, value) in storage {
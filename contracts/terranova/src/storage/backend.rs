@@ -1,11 +1,18 @@
 use std::convert::TryInto;
 
-use cosmwasm_std::{Uint128, Uint256};
-use cw_storage_plus::{Map, PrimaryKey};
-use evm::{H160, U256, H256};
+use cosmwasm_std::{Addr, QuerierWrapper, Uint128, Uint256};
+use cw_storage_plus::{Item, Map, PrimaryKey, U64Key};
+use evm::{H160, U256, H256, backend::Log};
 
 use crate::account::{EvmAccount, EvmContract};
+use crate::erc20::Cw20Token;
+use crate::hardfork::Hardfork;
+use crate::wasm_call::{WasmCallRecord, WasmCallStatus};
+use crate::receipt::TransactionReceipt;
+use crate::pending_tx::PendingTx;
 use crate::storage::{CwStorageInterface, StorageInterface};
+use crate::utils::keccak256_h256_v;
+use crate::precompiles::wasm_execute_address;
 
 use super::Readable;
 
@@ -26,11 +33,100 @@ pub const ACCOUNTS: Map<&H160, EvmAccount> = Map::new("accounts");
 /// Value: an EvmContract struct, see its documentation
 pub const CONTRACTS: Map<&H160, EvmContract> = Map::new("contracts");
 
-/// Key: a tuple (H160, U256). Convert the U256 using to_bytes (byte array in big-endian format) first.\ 
+/// Key: a tuple (H160, storage generation, U256). Convert the U256 using to_bytes (byte array in big-endian format) first.\ 
 /// Don't try implementing PrimaryKey for U256, it's a total fuckshow. If Terra upgrades to version 0.11.0 of cw-storage-plus then it'll be doable.\ 
+/// Only the entries of the current EvmAccount::storage_generation of the address are part of the EVM state\ 
 /// Value: a U256
 // pub const CONTRACT_STORAGE: Map<(H160, &[u8]), U256> = Map::new("contract_storage");
-pub const CONTRACT_STORAGE: Map<(&H160, &[u8]), U256> = Map::new("contract_storage");
+pub const CONTRACT_STORAGE: Map<(&H160, U64Key, &[u8]), U256> = Map::new("contract_storage_v2");
+
+/// CONTRACT_STORAGE entries saved before storage generations were introduced, without the generation in the key.\ 
+/// They are read as part of generation 0, which every account saved before then is at, until MigrateLegacyStorage moves them\ 
+/// Key: a tuple (H160, U256 bytes)\ 
+/// Value: a U256
+pub const LEGACY_CONTRACT_STORAGE: Map<(&H160, &[u8]), U256> = Map::new("contract_storage");
+
+/// Storage generations replaced by a reset or a SELFDESTRUCT, whose CONTRACT_STORAGE entries are left to prune\ 
+/// Key: a tuple (H160, storage generation), removed once all the entries of the generation are pruned\ 
+/// Value: the same tuple, since keys read back from a range can't be decoded\ 
+/// A contract re-created at the address starts at the generation after the last stale one
+pub const STALE_STORAGE: Map<(&H160, U64Key), (H160, u64)> = Map::new("stale_storage");
+
+/// Logs emitted by each successful transaction, only saved if Config::persist_logs is set\ 
+/// Key: the hash of the transaction, see transaction::unsigned_tx_hash, as bytes\ 
+/// Value: the logs in the order they were emitted
+pub const TX_LOGS: Map<&[u8], Vec<Log>> = Map::new("tx_logs");
+
+/// Total amount of the native denom deposited into EVM balances and not yet withdrawn, all held by the contract\ 
+/// Withdrawals can never exceed it, so EVM balances created without a deposit can't drain the contract
+pub const NATIVE_SUPPLY: Item<Uint128> = Item::new("native_supply");
+
+/// CW20 tokens registered by the admin\ 
+/// Key: the H160 address of the ERC-20 facade of the token, see erc20::facade_address\ 
+/// Value: a Cw20Token struct, see its documentation
+pub const CW20_TOKENS: Map<&H160, Cw20Token> = Map::new("cw20_tokens");
+
+/// Reverse lookup of CW20_TOKENS\ 
+/// Key: the Terra address of the CW20 contract\ 
+/// Value: the H160 address of its ERC-20 facade
+pub const CW20_FACADES: Map<&Addr, H160> = Map::new("cw20_facades");
+
+/// Key: a tuple (facade address, owner address)\ 
+/// Value: the amount of the CW20 token held by the EVM account, in custody of the contract
+pub const CW20_BALANCES: Map<(&H160, &H160), Uint128> = Map::new("cw20_balances");
+
+/// Total amount of each CW20 token held by EVM accounts, the contract holds at least as many of the CW20 tokens\ 
+/// Key: the facade address
+pub const CW20_SUPPLIES: Map<&H160, Uint128> = Map::new("cw20_supplies");
+
+/// Key: a tuple (facade address, owner address, spender address)\ 
+/// Value: the ERC-20 allowance of the spender, a zero allowance is removed
+pub const CW20_ALLOWANCES: Map<(&H160, &H160, &H160), Uint128> = Map::new("cw20_allowances");
+
+/// Number of CosmWasm messages sent by EVM contracts, the id of the next message
+pub const WASM_CALL_COUNT: Item<u64> = Item::new("wasm_call_count");
+
+/// CosmWasm messages sent by EVM contracts through the wasm execute precompile\ 
+/// Key: the call id, which is also the id of the submessage\ 
+/// Value: a WasmCallRecord struct, see its documentation
+pub const WASM_CALLS: Map<U64Key, WasmCallRecord> = Map::new("wasm_calls");
+
+/// CosmWasm contracts that EVM contracts may send messages to through the wasm execute precompile, managed by the admin\ 
+/// Key: the Terra address of the contract\ 
+/// Value: always true, a disallowed contract is removed from the map
+pub const WASM_EXECUTE_TARGETS: Map<&Addr, bool> = Map::new("wasm_execute_targets");
+
+/// Receipts of all executed transactions, successful or not\ 
+/// Key: the hash of the transaction, see transaction::unsigned_tx_hash, as bytes\ 
+/// Value: a TransactionReceipt struct, see its documentation
+pub const RECEIPTS: Map<&[u8], TransactionReceipt> = Map::new("receipts");
+
+/// Total gas used by the EVM transactions of a block, for the cumulative gas used of receipts\ 
+/// Value: a tuple (block height, gas used), the gas used is reset when the height changes
+pub const BLOCK_GAS_USED: Item<(u64, U256)> = Item::new("block_gas_used");
+
+/// Transactions executed over several messages whose execution hasn't completed yet\ 
+/// Key: the hash of the transaction, see transaction::unsigned_tx_hash\ 
+/// Value: a PendingTx struct, see its documentation
+pub const PENDING_TXS: Map<&H256, PendingTx> = Map::new("pending_txs");
+
+/// Number of past blocks whose hash is available to BLOCKHASH, as in Ethereum
+pub const BLOCK_HASH_HISTORY: u64 = 256;
+
+/// Ring buffer of the hashes of the last BLOCK_HASH_HISTORY Terra blocks in which the EVM state was accessed\ 
+/// Key: the block height modulo BLOCK_HASH_HISTORY\ 
+/// Value: a tuple (block height, block hash), the height tells whether the slot was overwritten by a later block
+pub const BLOCK_HASHES: Map<U64Key, (u64, H256)> = Map::new("block_hashes");
+
+/// Cosmwasm does not expose the block hash through Env, so a deterministic hash is derived
+/// from the Cosmwasm chain id, block height and block time instead
+pub fn derive_block_hash(env: &cosmwasm_std::Env) -> H256 {
+    keccak256_h256_v(&[
+        env.block.chain_id.as_bytes(),
+        &env.block.height.to_be_bytes(),
+        &env.block.time.nanos().to_be_bytes(),
+    ])
+}
 
 /// Read from persistent EVM state state (after the most recent finalized transaction)
 impl<S: Readable> StorageInterface for CwStorageInterface<S> {
@@ -38,6 +134,18 @@ impl<S: Readable> StorageInterface for CwStorageInterface<S> {
         &self.token_mint
     }
 
+    fn api(&self) -> &dyn cosmwasm_std::Api {
+        self.cw_deps.get_api()
+    }
+
+    fn querier(&self) -> QuerierWrapper<'_> {
+        self.cw_deps.get_querier()
+    }
+
+    fn contract_address(&self) -> &Addr {
+        &self.cw_env.contract.address
+    }
+
     fn block_number(&self) -> evm::U256 {
         self.cw_env.block.height.into()
     }
@@ -49,20 +157,30 @@ impl<S: Readable> StorageInterface for CwStorageInterface<S> {
         self.cw_env.block.time.seconds().into()
     }
 
-    /// TODO: Come back to this
-    /// Cosmwasm currently does not directly expose the block hash through Env
-    /// What to do here? Create own hash using block number + block time or something?
-    /// Need to find out the specific purpose/uses of this in EVM smart contracts, and what the requirements of this hash are
-    /// Does it only need to be unique? Or do we need other properties...?
+    /// Hash of one of the last BLOCK_HASH_HISTORY blocks, see BLOCK_HASHES.\ 
+    /// Like in Ethereum, the hash is zero for the current block and blocks outside of that window.
+    /// It is also zero for blocks in which the EVM state was never accessed, since no hash was recorded for them
     fn block_hash(&self, number: evm::U256) -> evm::H256 {
-        todo!()
+        let current = self.cw_env.block.height;
+        if number >= U256::from(current) || number + BLOCK_HASH_HISTORY < U256::from(current) {
+            return H256::zero();
+        }
+
+        let height = number.as_u64();
+        BLOCK_HASHES
+            .may_load(self.cw_deps.get_ref(), U64Key::new(height % BLOCK_HASH_HISTORY))
+            .unwrap_or(None)
+            .filter(|(recorded_height, _)| *recorded_height == height)
+            .map_or_else(H256::zero, |(_, hash)| hash)
     }
 
-    /// TODO: Come back to this
-    /// Would it be better to use some hash of the cw_env chain_id, which is a String,
-    /// or would it be better to just use arbitrary chain_id numbers as in Neon?
+    /// The Terranova EVM chain ID, this is separate from the Cosmwasm chain ID in Env, which is a String
     fn chain_id(&self) -> u64 {
-        todo!()
+        self.config.chain_id
+    }
+
+    fn hardfork(&self) -> Hardfork {
+        self.config.hardfork
     }
 
     /// What should "existing" mean? Probably just exists as a key entry in ACCOUNTS?
@@ -70,10 +188,14 @@ impl<S: Readable> StorageInterface for CwStorageInterface<S> {
     /// tbh we could even just have this always return true and just insert a new entry into
     /// evm_accounts if it's not there already
     fn exists(&self, address: &H160) -> bool {
+        self.access(address);
+
         ACCOUNTS.has(self.cw_deps.get_ref(), address)
     }
 
     fn nonce(&self, address: &H160) -> evm::U256 {
+        self.access(address);
+
         ACCOUNTS
             .may_load(self.cw_deps.get_ref(), address)
             .unwrap_or(None)
@@ -82,6 +204,8 @@ impl<S: Readable> StorageInterface for CwStorageInterface<S> {
     }
 
     fn balance(&self, address: &H160) -> U256 {
+        self.access(address);
+
         ACCOUNTS
             .may_load(self.cw_deps.get_ref(), address)
             .unwrap_or(None)
@@ -93,6 +217,8 @@ impl<S: Readable> StorageInterface for CwStorageInterface<S> {
     /// This method can panic, but only because of architecture related reasons, should (hopefully) be fine since
     /// this is compiled to cosmwasm anyways.
     fn code_size(&self, address: &H160) -> usize {
+        self.access(address);
+
         CONTRACTS
             .may_load(self.cw_deps.get_ref(), address)
             .unwrap_or(None)
@@ -102,6 +228,8 @@ impl<S: Readable> StorageInterface for CwStorageInterface<S> {
     }
 
     fn code_hash(&self, address: &H160) -> H256 {
+        self.access(address);
+
         CONTRACTS
             .may_load(self.cw_deps.get_ref(), address)
             .unwrap_or(None)
@@ -112,6 +240,8 @@ impl<S: Readable> StorageInterface for CwStorageInterface<S> {
     }
 
     fn code(&self, address: &H160) -> Vec<u8> {
+        self.access(address);
+
         CONTRACTS
             .may_load(self.cw_deps.get_ref(), address)
             .unwrap_or(None)
@@ -120,6 +250,8 @@ impl<S: Readable> StorageInterface for CwStorageInterface<S> {
     }
 
     fn valids(&self, address: &H160) -> Vec<u8> {
+        self.access(address);
+
         CONTRACTS
             .may_load(self.cw_deps.get_ref(), address)
             .unwrap_or(None)
@@ -127,12 +259,90 @@ impl<S: Readable> StorageInterface for CwStorageInterface<S> {
     }
 
     fn storage(&self, address: &H160, index: &U256) -> U256 {
-        CONTRACT_STORAGE
+        self.access(address);
+
+        let generation = ACCOUNTS
+            .may_load(self.cw_deps.get_ref(), address)
+            .unwrap_or(None)
+            .map_or(0, |account| account.storage_generation);
+
+        let index = index.to_bytes();
+        let value = CONTRACT_STORAGE
             .may_load(
                 self.cw_deps.get_ref(), 
-                (address, &index.to_bytes())
+                (address, U64Key::new(generation), &index)
             )
+            .unwrap_or(None);
+
+        // Entries saved before the generations are read until MigrateLegacyStorage moves them to generation 0
+        match value {
+            None if generation == 0 => LEGACY_CONTRACT_STORAGE
+                .may_load(self.cw_deps.get_ref(), (address, &index))
+                .unwrap_or(None),
+            value => value,
+        }
+        .unwrap_or_else(U256::zero)
+    }
+
+    fn native_supply(&self) -> Uint128 {
+        NATIVE_SUPPLY
+            .may_load(self.cw_deps.get_ref())
+            .unwrap_or(None)
+            .unwrap_or_default()
+    }
+
+    fn cw20_token(&self, address: &H160) -> Option<Cw20Token> {
+        CW20_TOKENS
+            .may_load(self.cw_deps.get_ref(), address)
+            .unwrap_or(None)
+    }
+
+    fn cw20_balance(&self, token: &H160, owner: &H160) -> Uint128 {
+        self.access(owner);
+
+        CW20_BALANCES
+            .may_load(self.cw_deps.get_ref(), (token, owner))
             .unwrap_or(None)
-            .unwrap_or_else(U256::zero)
+            .unwrap_or_default()
+    }
+
+    fn cw20_supply(&self, token: &H160) -> Uint128 {
+        CW20_SUPPLIES
+            .may_load(self.cw_deps.get_ref(), token)
+            .unwrap_or(None)
+            .unwrap_or_default()
+    }
+
+    fn cw20_allowance(&self, token: &H160, owner: &H160, spender: &H160) -> Uint128 {
+        self.access(owner);
+
+        CW20_ALLOWANCES
+            .may_load(self.cw_deps.get_ref(), (token, owner, spender))
+            .unwrap_or(None)
+            .unwrap_or_default()
+    }
+
+    /// The wasm execute precompile counts as accessed, the ids of the calls queued by a transaction in progress
+    /// would be taken by the calls of other transactions
+    fn wasm_call_count(&self) -> u64 {
+        self.access(&wasm_execute_address());
+
+        WASM_CALL_COUNT
+            .may_load(self.cw_deps.get_ref())
+            .unwrap_or(None)
+            .unwrap_or_default()
+    }
+
+    fn wasm_call_status(&self, id: u64) -> Option<WasmCallStatus> {
+        WASM_CALLS
+            .may_load(self.cw_deps.get_ref(), U64Key::new(id))
+            .unwrap_or(None)
+            .map(|record| record.status)
+    }
+
+    fn wasm_execute_allowed(&self, contract: &Addr) -> bool {
+        // The contract holds the CW20 tokens of EVM accounts, transfers of them only go through the ERC-20 facades
+        WASM_EXECUTE_TARGETS.has(self.cw_deps.get_ref(), contract)
+            && !CW20_FACADES.has(self.cw_deps.get_ref(), contract)
     }
 }
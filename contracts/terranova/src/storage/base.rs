@@ -2,32 +2,108 @@ use std::{cell::RefCell, collections::{BTreeSet, BTreeMap}};
 
 use cosmwasm_std::{Addr, Env, DepsMut};
 
+use evm::{H160, H256};
+
 use crate::ContractError;
+use crate::config::{Config, CONTRACT_CONFIG};
+use crate::pending_tx::{AccountLocks, PendingTx};
 
-use super::{CwStorageInterface, Readable, Writable};
+use super::{backend::{ACCOUNTS, PENDING_TXS}, CwStorageInterface, Readable, Writable};
 
 impl<S: Readable> CwStorageInterface<S> {
-    pub fn new_ref(cw_deps: S, cw_env: Env, token_mint: Addr,  chain_id: u64) -> Result<Self, ContractError> {
+    /// Contract wide settings of the Terranova EVM, saved at instantiation
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Records a read of the state of an EVM account
+    pub(super) fn access(&self, address: &H160) {
+        self.accessed_accounts.borrow_mut().insert(*address);
+    }
+
+    /// EVM accounts whose state was read so far
+    pub fn accessed_accounts(&self) -> BTreeSet<H160> {
+        self.accessed_accounts.borrow().clone()
+    }
+
+    pub fn held_locks(&self) -> &AccountLocks {
+        &self.held_locks
+    }
+
+    /// Continues a transaction in progress, its own locks don't block it
+    pub fn hold_locks(&mut self, locks: AccountLocks) {
+        self.held_locks = locks;
+    }
+
+    /// Fails if an account read so far is write locked by another transaction in progress
+    pub fn check_read_locks(&self) -> Result<(), ContractError> {
+        for address in self.accessed_accounts.borrow().iter() {
+            if let Some(account) = ACCOUNTS.may_load(self.cw_deps.get_ref(), address)? {
+                if account.rw_blocked && !self.held_locks.write.contains(address) {
+                    return Err!(ContractError::AccountLocked; "Account {} is write locked by a transaction in progress", address);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fails if one of the accounts is read or write locked by another transaction in progress
+    pub fn check_write_locks<'b>(&self, addresses: impl IntoIterator<Item = &'b H160>) -> Result<(), ContractError> {
+        for address in addresses {
+            if let Some(account) = ACCOUNTS.may_load(self.cw_deps.get_ref(), address)? {
+                if account.rw_blocked && !self.held_locks.write.contains(address) {
+                    return Err!(ContractError::AccountLocked; "Account {} is write locked by a transaction in progress", address);
+                }
+
+                let own_read_lock = u8::from(self.held_locks.read.contains(address));
+                if account.ro_blocked_count > own_read_lock {
+                    return Err!(ContractError::AccountLocked; "Account {} is read locked by {} transactions in progress", address, account.ro_blocked_count - own_read_lock);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The transaction in progress with this hash, if any
+    pub fn pending_tx(&self, tx_hash: &H256) -> Result<Option<PendingTx>, ContractError> {
+        Ok(PENDING_TXS.may_load(self.cw_deps.get_ref(), tx_hash)?)
+    }
+
+    pub fn new_ref(cw_deps: S, cw_env: Env, token_mint: Addr) -> Result<Self, ContractError> {
+        let config = CONTRACT_CONFIG.load(cw_deps.get_ref())?;
+
         Ok(Self {
             cw_deps,
             cw_env,
             token_mint,
             evm_accounts: BTreeMap::new(),
             empty_evm_accounts: RefCell::new(BTreeSet::new()),
-            chain_id,
+            config,
+            accessed_accounts: RefCell::new(BTreeSet::new()),
+            held_locks: AccountLocks::default(),
         })
     }
 }
 
 impl<S: Readable + Writable> CwStorageInterface<S> {
-    pub fn new_mut(cw_deps: S, cw_env: Env, token_mint: Addr,  chain_id: u64) -> Result<Self, ContractError> {
-        Ok(Self {
+    /// Every mutable access to the EVM state records the hash of the current block, for BLOCKHASH in later blocks
+    pub fn new_mut(cw_deps: S, cw_env: Env, token_mint: Addr) -> Result<Self, ContractError> {
+        let config = CONTRACT_CONFIG.load(cw_deps.get_ref())?;
+
+        let mut storage = Self {
             cw_deps,
             cw_env,
             token_mint,
             evm_accounts: BTreeMap::new(),
             empty_evm_accounts: RefCell::new(BTreeSet::new()),
-            chain_id,
-        })
+            config,
+            accessed_accounts: RefCell::new(BTreeSet::new()),
+            held_locks: AccountLocks::default(),
+        };
+        storage.record_block_hash()?;
+
+        Ok(storage)
     }
 }
@@ -2,13 +2,19 @@
 pub mod backend;
 mod base;
 mod apply;
+pub mod overrides;
 
 use std::{collections::{BTreeMap, BTreeSet}, cell::RefCell};
 
-use cosmwasm_std::{Addr, Env, DepsMut, Storage, Deps};
+use cosmwasm_std::{Addr, Api, Env, DepsMut, QuerierWrapper, Storage, Deps, Uint128};
 use evm::{H160, U256, H256};
 
 use crate::account::{EvmAccount, EvmContract};
+use crate::config::Config;
+use crate::erc20::Cw20Token;
+use crate::hardfork::Hardfork;
+use crate::pending_tx::AccountLocks;
+use crate::wasm_call::WasmCallStatus;
 
 /// Currently unused\ 
 /// A thin enum wrapper for Ethereum
@@ -47,7 +53,14 @@ pub struct CwStorageInterface<S: Readable> {
     /// Is there a point to this...?
     empty_evm_accounts: RefCell<BTreeSet<H160>>,
 
-    chain_id: u64, 
+    /// Contract wide settings, loaded from CONTRACT_CONFIG
+    config: Config,
+
+    /// EVM accounts whose state was read so far, checked against the accounts locked by transactions in progress
+    accessed_accounts: RefCell<BTreeSet<H160>>,
+
+    /// Locks held by the transaction in progress being continued, they don't block its own reads and writes
+    held_locks: AccountLocks,
 }
 
 /// TODO: Document this better
@@ -56,6 +69,12 @@ pub struct CwStorageInterface<S: Readable> {
 pub trait StorageInterface {
     /// Get NOVA's cw20 token mint
     fn token_mint(&self) -> &Addr;
+    /// Get the Cosmwasm API, which gives access to the crypto functions implemented by the host
+    fn api(&self) -> &dyn Api;
+    /// Get the Cosmwasm querier, which gives read access to the bank module and to other contracts
+    fn querier(&self) -> QuerierWrapper<'_>;
+    /// Get the Terra address of the Terranova contract
+    fn contract_address(&self) -> &Addr;
 
     /// Get block number of EVM state
     fn block_number(&self) -> U256;
@@ -65,6 +84,8 @@ pub trait StorageInterface {
     fn block_hash(&self, number: U256) -> H256;
     /// Get chain id of EVM
     fn chain_id(&self) -> u64;
+    /// Get the hardfork whose rules the EVM follows
+    fn hardfork(&self) -> Hardfork;
     
     /// Check if EVM account exists
     fn exists(&self, address: &H160) -> bool;
@@ -83,10 +104,31 @@ pub trait StorageInterface {
     fn valids(&self, address: &H160) -> Vec<u8>;
     /// Get data from EVM storage
     fn storage(&self, address: &H160, index: &U256) -> U256;
+
+    /// Get the amount of the native denom held by the contract for EVM balances, see NATIVE_SUPPLY
+    fn native_supply(&self) -> Uint128;
+
+    /// Get the CW20 token wrapped by the ERC-20 facade at the address, if any
+    fn cw20_token(&self, address: &H160) -> Option<Cw20Token>;
+    /// Get the balance of an EVM account in the CW20 token of the facade
+    fn cw20_balance(&self, token: &H160, owner: &H160) -> Uint128;
+    /// Get the amount of the CW20 token of the facade held by EVM accounts
+    fn cw20_supply(&self, token: &H160) -> Uint128;
+    /// Get the amount of the CW20 token of the facade that the spender may transfer from the owner
+    fn cw20_allowance(&self, token: &H160, owner: &H160, spender: &H160) -> Uint128;
+
+    /// Get the number of CosmWasm messages sent by EVM contracts so far, which is the id of the next one
+    fn wasm_call_count(&self) -> u64;
+    /// Get the status of the CosmWasm message sent by an EVM contract with the id, if any
+    fn wasm_call_status(&self, id: u64) -> Option<WasmCallStatus>;
+    /// Get whether EVM contracts may send messages to the CosmWasm contract, see WASM_EXECUTE_TARGETS
+    fn wasm_execute_allowed(&self, contract: &Addr) -> bool;
 }
 
 pub trait Readable {
     fn get_ref(&self) -> &dyn Storage;
+    fn get_api(&self) -> &dyn Api;
+    fn get_querier(&self) -> QuerierWrapper<'_>;
 }
 
 pub trait Writable {
@@ -97,6 +139,14 @@ impl<'a> Readable for DepsMut<'a> {
     fn get_ref(&self) -> &dyn Storage {
         self.storage
     }
+
+    fn get_api(&self) -> &dyn Api {
+        self.api
+    }
+
+    fn get_querier(&self) -> QuerierWrapper<'_> {
+        self.querier
+    }
 }
 
 impl<'a> Writable for DepsMut<'a> {
@@ -109,4 +159,12 @@ impl<'a> Readable for Deps<'a> {
     fn get_ref(&self) -> &dyn Storage {
         self.storage
     }
+
+    fn get_api(&self) -> &dyn Api {
+        self.api
+    }
+
+    fn get_querier(&self) -> QuerierWrapper<'_> {
+        self.querier
+    }
 }
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Addr, Api, QuerierWrapper, Uint128};
+use evm::{H160, H256, U256};
+
+use crate::erc20::Cw20Token;
+use crate::hardfork::Hardfork;
+use crate::utils::keccak256_h256;
+use crate::wasm_call::WasmCallStatus;
+
+use super::StorageInterface;
+
+/// State of an EVM account replacing the persistent state during a call, the fields that are not set are left unchanged
+#[derive(Debug, Default)]
+pub struct AccountOverride {
+    pub balance: Option<U256>,
+    /// The code and its valid jumps, see evm::Valids::compute
+    pub code: Option<(Vec<u8>, Vec<u8>)>,
+    /// Only the listed storage slots are replaced, the other ones keep their persistent value
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// Block context of a call, the fields that are not set are those of the current block
+#[derive(Debug, Default)]
+pub struct BlockOverride {
+    pub number: Option<U256>,
+    pub timestamp: Option<U256>,
+}
+
+/// Read-only backend running a call on top of another backend with some of its state replaced, like eth_call state overrides
+pub struct OverrideStorage<'a, B: StorageInterface> {
+    backend: &'a B,
+    block: BlockOverride,
+    accounts: BTreeMap<H160, AccountOverride>,
+}
+
+impl<'a, B: StorageInterface> OverrideStorage<'a, B> {
+    pub fn new(backend: &'a B, block: BlockOverride, accounts: BTreeMap<H160, AccountOverride>) -> Self {
+        Self { backend, block, accounts }
+    }
+
+    fn account(&self, address: &H160) -> Option<&AccountOverride> {
+        self.accounts.get(address)
+    }
+
+    fn overridden_code(&self, address: &H160) -> Option<&(Vec<u8>, Vec<u8>)> {
+        self.account(address).and_then(|account| account.code.as_ref())
+    }
+}
+
+impl<'a, B: StorageInterface> StorageInterface for OverrideStorage<'a, B> {
+    fn token_mint(&self) -> &Addr {
+        self.backend.token_mint()
+    }
+
+    fn api(&self) -> &dyn Api {
+        self.backend.api()
+    }
+
+    fn querier(&self) -> QuerierWrapper<'_> {
+        self.backend.querier()
+    }
+
+    fn contract_address(&self) -> &Addr {
+        self.backend.contract_address()
+    }
+
+    fn block_number(&self) -> U256 {
+        self.block.number.unwrap_or_else(|| self.backend.block_number())
+    }
+
+    fn block_timestamp(&self) -> U256 {
+        self.block.timestamp.unwrap_or_else(|| self.backend.block_timestamp())
+    }
+
+    fn block_hash(&self, number: U256) -> H256 {
+        self.backend.block_hash(number)
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.backend.chain_id()
+    }
+
+    fn hardfork(&self) -> Hardfork {
+        self.backend.hardfork()
+    }
+
+    fn exists(&self, address: &H160) -> bool {
+        self.accounts.contains_key(address) || self.backend.exists(address)
+    }
+
+    fn nonce(&self, address: &H160) -> U256 {
+        self.backend.nonce(address)
+    }
+
+    fn balance(&self, address: &H160) -> U256 {
+        self.account(address)
+            .and_then(|account| account.balance)
+            .unwrap_or_else(|| self.backend.balance(address))
+    }
+
+    fn code_size(&self, address: &H160) -> usize {
+        self.overridden_code(address)
+            .map_or_else(|| self.backend.code_size(address), |(code, _)| code.len())
+    }
+
+    /// Overridden code hashes like deployed code, an empty override has the zero hash of an account without code
+    fn code_hash(&self, address: &H160) -> H256 {
+        match self.overridden_code(address) {
+            Some((code, _)) if code.is_empty() => H256::zero(),
+            Some((code, _)) => keccak256_h256(code),
+            None => self.backend.code_hash(address),
+        }
+    }
+
+    fn code(&self, address: &H160) -> Vec<u8> {
+        self.overridden_code(address)
+            .map_or_else(|| self.backend.code(address), |(code, _)| code.clone())
+    }
+
+    fn valids(&self, address: &H160) -> Vec<u8> {
+        self.overridden_code(address)
+            .map_or_else(|| self.backend.valids(address), |(_, valids)| valids.clone())
+    }
+
+    fn storage(&self, address: &H160, index: &U256) -> U256 {
+        self.account(address)
+            .and_then(|account| account.storage.get(index).copied())
+            .unwrap_or_else(|| self.backend.storage(address, index))
+    }
+
+    fn native_supply(&self) -> Uint128 {
+        self.backend.native_supply()
+    }
+
+    fn cw20_token(&self, address: &H160) -> Option<Cw20Token> {
+        self.backend.cw20_token(address)
+    }
+
+    fn cw20_balance(&self, token: &H160, owner: &H160) -> Uint128 {
+        self.backend.cw20_balance(token, owner)
+    }
+
+    fn cw20_supply(&self, token: &H160) -> Uint128 {
+        self.backend.cw20_supply(token)
+    }
+
+    fn cw20_allowance(&self, token: &H160, owner: &H160, spender: &H160) -> Uint128 {
+        self.backend.cw20_allowance(token, owner, spender)
+    }
+
+    fn wasm_call_count(&self) -> u64 {
+        self.backend.wasm_call_count()
+    }
+
+    fn wasm_call_status(&self, id: u64) -> Option<WasmCallStatus> {
+        self.backend.wasm_call_status(id)
+    }
+
+    fn wasm_execute_allowed(&self, contract: &Addr) -> bool {
+        self.backend.wasm_execute_allowed(contract)
+    }
+}
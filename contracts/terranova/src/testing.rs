@@ -1,21 +1,85 @@
 use super::*;
-use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-use cosmwasm_std::{coins, from_binary, Order, Uint256};
-use evm::{H160, U256};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{attr, coin, coins, from_binary, from_slice, to_binary, to_vec, Addr, BalanceResponse, BankMsg, BankQuery, Binary, Coin, ContractResult, CosmosMsg, Deps, DepsMut, Empty, Order, OwnedDeps, Querier, QuerierResult, QueryRequest, Reply, Response, StdResult, SubMsg, SubMsgExecutionResponse, SystemResult, Uint128, Uint256, WasmMsg, WasmQuery};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use evm::{H160, H256, U256};
+use cw_storage_plus::U64Key;
 use crate::airdrop::{airdrop_write_balance, airdrop_deploy_contract, get_backend};
-use crate::contract::{instantiate, execute, query};
-use crate::storage::backend::{ACCOUNTS, CONTRACTS, CONTRACT_STORAGE};
-use crate::message::{ExecuteMsg, InstantiateMsg, QueryMsg, RawEthereumQueryResponse, EvmAccountResponse};
-use crate::transaction::UnsignedTransaction;
-use crate::utils::{parse_h160, parse_hex};
+use crate::contract::{instantiate, execute, migrate, query, reply};
+use crate::precompiles::{withdraw_address, wasm_execute_address, wasm_call_status_address, wasm_query_address};
+use crate::erc20::facade_address;
+use crate::config::CONTRACT_CONFIG;
+use crate::hardfork::Hardfork;
+use crate::pending_tx::PENDING_TX_TIMEOUT;
+use crate::tx_chunk::CHUNK_EXPIRY_BLOCKS;
+use crate::storage::backend::{ACCOUNTS, CONTRACTS, CONTRACT_STORAGE, LEGACY_CONTRACT_STORAGE, NATIVE_SUPPLY, STALE_STORAGE, CW20_ALLOWANCES, CW20_BALANCES, CW20_SUPPLIES};
+use crate::message::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, RawEthereumQueryResponse, EvmAccountResponse, EvmLogResponse, TxLogsResponse, TransactionReceiptResponse, OperatorsResponse, WasmExecuteTargetsResponse, Cw20FacadeResponse, Cw20HookMsg, TxChunkResponse, TxChunkStatusResponse, EthCallMsg, EthCallResponse, BlockContext, StateOverride, StorageSlot, RevertReason};
+use crate::transaction::{unsigned_tx_hash, UnsignedTransaction};
+use crate::utils::{keccak256_h256, keccak256_h256_v, parse_h160, parse_hex};
 use env_logger;
 
+/// EVM account receiving the gas fees in tests
+fn fee_collector() -> H160 {
+    parse_h160("0x00000000000000000000000000000000000fee01")
+}
+
+fn instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        chain_id: None,
+        denom: String::from("uluna"),
+        fee_collector: fee_collector().to_fixed_bytes(),
+        persist_logs: true,
+        admin: None,
+        operators: vec![String::from("creator")],
+        hardfork: Hardfork::Istanbul,
+        max_chunk_size: None,
+        max_chunked_tx_size: None,
+    }
+}
+
+/// RLP encoded unsigned legacy transaction from `caller` at its current nonce, with a gas limit of 1_000_000.\
+/// `to` is None for a contract creation
+fn raw_tx(deps: Deps, caller: H160, to: Option<H160>, gas_price: u64, value: u64, call_data: &[u8]) -> Vec<u8> {
+    let nonce = ACCOUNTS.may_load(deps.storage, &caller).unwrap().map_or(0, |account| account.trx_count);
+
+    let mut trx = rlp::RlpStream::new_list(6);
+    trx.append(&nonce)
+        .append(&gas_price)
+        .append(&1_000_000_u64);
+    match to {
+        Some(to) => trx.append(&to.as_bytes()),
+        None => trx.append_empty_data(),
+    };
+    trx.append(&value)
+        .append(&call_data);
+
+    trx.out().to_vec()
+}
+
+/// Executes a transaction from `caller` without gas fee, submitted by the operator
+fn raw_call(deps: DepsMut, caller: H160, to: Option<H160>, value: u64, call_data: &[u8]) -> Response {
+    let msg = ExecuteMsg::ExecuteRawEthereumTx {
+        caller_evm_address: caller.to_fixed_bytes(),
+        unsigned_tx: raw_tx(deps.as_ref(), caller, to, 0, value, call_data),
+    };
+    execute(deps, mock_env(), mock_info("creator", &[]), msg).unwrap()
+}
+
+/// Runs a call from `caller` with RawEthereumQuery and returns its result
+fn raw_query(deps: Deps, caller: H160, to: H160, call_data: &[u8]) -> Vec<u8> {
+    let msg = QueryMsg::RawEthereumQuery {
+        caller_evm_address: caller.to_fixed_bytes(),
+        unsigned_tx: raw_tx(deps, caller, Some(to), 0, 0, call_data),
+    };
+    let res: RawEthereumQueryResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    res.result
+}
 
 #[test]
 fn proper_initialization() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg { };
+    let msg = instantiate_msg();
     let info = mock_info("creator", &coins(1000, "earth"));
 
     // we can just call .unwrap() to assert this was a success
@@ -27,7 +91,7 @@ fn proper_initialization() {
 fn simple_user_user_transfer() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg { };
+    let msg = instantiate_msg();
     let info = mock_info("creator", &coins(1000, "earth"));
 
     // we can just call .unwrap() to assert this was a success
@@ -69,7 +133,7 @@ fn simple_user_user_transfer() {
     // rlp.encode(tx).hex()
         
     // let trx_hex = "0xe06401830186a094d3cda913deb6f67967b99d67acdfa1712c2936018301e24080";
-    let trx_hex = "0xe00101830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080";
+    let trx_hex = "0xe08001830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080";
     let trx = parse_hex(&trx_hex);
 
     let msg = ExecuteMsg::ExecuteRawEthereumTx { 
@@ -79,7 +143,9 @@ fn simple_user_user_transfer() {
 
     let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
-    assert_eq!(99876544, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().balance.as_u128());
+    // 123456 transferred and 21000 gas paid at a gas price of 1
+    assert_eq!(99855544, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().balance.as_u128());
+    assert_eq!(21000, ACCOUNTS.load(deps.as_ref().storage, &fee_collector()).unwrap().balance.as_u128());
     assert_eq!(123456, ACCOUNTS.load(deps.as_ref().storage, &receiver_addr).unwrap().balance.as_u128());
 }
 
@@ -87,7 +153,7 @@ fn simple_user_user_transfer() {
 fn simple_contract_deploy() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg { };
+    let msg = instantiate_msg();
     let info = mock_info("creator", &coins(1000, "earth"));
 
     // we can just call .unwrap() to assert this was a success
@@ -113,7 +179,7 @@ fn simple_contract_interact() {
     env_logger::init();
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg { };
+    let msg = instantiate_msg();
     let info = mock_info("creator", &coins(1000, "earth"));
 
     // we can just call .unwrap() to assert this was a success
@@ -142,7 +208,7 @@ fn simple_contract_interact() {
     let contract_addr = parse_h160(&res.attributes[1].value);
 
     // Call store(0xbb)
-    let trx_hex = "0xf84180018398968094ff3b783539a1a7a53ecacfb1c0778274c670f35b80a46057361d00000000000000000000000000000000000000000000000000000000000000bb";
+    let trx_hex = "0xf84101018398968094ff3b783539a1a7a53ecacfb1c0778274c670f35b80a46057361d00000000000000000000000000000000000000000000000000000000000000bb";
     let trx = parse_hex(&trx_hex);
     let msg = ExecuteMsg::ExecuteRawEthereumTx { 
         caller_evm_address: sender_addr.to_fixed_bytes(), 
@@ -167,7 +233,7 @@ fn simple_contract_interact() {
 fn erc20_transfer() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg { };
+    let msg = instantiate_msg();
     let info = mock_info("creator", &coins(1000, "earth"));
 
     // we can just call .unwrap() to assert this was a success
@@ -206,7 +272,7 @@ fn erc20_transfer() {
     // Transfer 77777 tokens from sender to receiver
     let receiver_addr: H160 = parse_h160("0x2e36b2970ab7A4C955eADD836585c21A087Ab904");
 
-    let trx_hex = "0xf86280018398968094ff3b783539a1a7a53ecacfb1c0778274c670f35b80b844a9059cbb0000000000000000000000002e36b2970ab7a4c955eadd836585c21a087ab9040000000000000000000000000000000000000000000000000000000000012fd1";
+    let trx_hex = "0xf86201018398968094ff3b783539a1a7a53ecacfb1c0778274c670f35b80b844a9059cbb0000000000000000000000002e36b2970ab7a4c955eadd836585c21a087ab9040000000000000000000000000000000000000000000000000000000000012fd1";
     let trx = parse_hex(&trx_hex);
     let msg = ExecuteMsg::ExecuteRawEthereumTx { 
         caller_evm_address: sender_addr.to_fixed_bytes(),
@@ -232,7 +298,7 @@ fn erc20_transfer() {
 fn erc20_approve() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg { };
+    let msg = instantiate_msg();
     let info = mock_info("creator", &coins(1000, "earth"));
 
     // we can just call .unwrap() to assert this was a success
@@ -270,7 +336,9 @@ fn erc20_approve() {
 
     // Approve address 0x47e0a3ddd614e28670da25b414afad2751741725 to spend sender_addr's tokens
     let approved: H160 = parse_h160("0x47e0a3ddd614e28670da25b414afad2751741725");
-    let trx_hex = "0xf8628001830f424094ff3b783539a1a7a53ecacfb1c0778274c670f35b80b844095ea7b300000000000000000000000047e0a3ddd614e28670da25b414afad275174172500ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
+    // The approved address sends the transferFrom transaction, so it needs funds to pay for gas
+    airdrop_write_balance(deps.as_mut(), mock_env(), approved);
+    let trx_hex = "0xf8620101830f424094ff3b783539a1a7a53ecacfb1c0778274c670f35b80b844095ea7b300000000000000000000000047e0a3ddd614e28670da25b414afad275174172500ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
     let trx = parse_hex(&trx_hex);
     let msg = ExecuteMsg::ExecuteRawEthereumTx { 
         caller_evm_address: sender_addr.to_fixed_bytes(),
@@ -311,7 +379,7 @@ fn erc20_approve() {
 fn chunked_transaction() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg { };
+    let msg = instantiate_msg();
     let info = mock_info("creator", &coins(1000, "earth"));
 
     // we can just call .unwrap() to assert this was a success
@@ -348,14 +416,169 @@ fn chunked_transaction() {
         chunk_count: chunks.len() as u8
     };
 
-    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap(); 
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap(); 
+
+    // The stored chunks are removed once the transaction is executed
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::TxChunksMissing));
+}
+
+#[test]
+fn chunk_lifecycle() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg { max_chunk_size: Some(4), max_chunked_tx_size: Some(10), ..instantiate_msg() };
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let tx_hash = H256::repeat_byte(0x11);
+
+    let env_at = |height: u64| {
+        let mut env = mock_env();
+        env.block.height = height;
+        env
+    };
+    let store_at = |deps: DepsMut, height: u64, chunk_index: u8, chunk_data: Vec<u8>| {
+        let msg = ExecuteMsg::StoreTxChunk {
+            caller_evm_address: sender_addr.to_fixed_bytes(),
+            full_tx_hash: tx_hash.to_fixed_bytes(),
+            chunk_index,
+            chunk_data,
+        };
+        execute(deps, env_at(height), mock_info("creator", &[]), msg)
+    };
+    let height = mock_env().block.height;
+    let store = |deps: DepsMut, chunk_index: u8, chunk_data: Vec<u8>| store_at(deps, height, chunk_index, chunk_data);
+    let execute_chunks = |deps: DepsMut, height: u64| {
+        let msg = ExecuteMsg::ExecuteChunkedEthereumTx {
+            caller_evm_address: sender_addr.to_fixed_bytes(),
+            full_tx_hash: tx_hash.to_fixed_bytes(),
+            chunk_count: 3,
+        };
+        execute(deps, env_at(height), mock_info("creator", &[]), msg)
+    };
+    let cancel = |deps: DepsMut, sender: &str, height: u64| {
+        let msg = ExecuteMsg::CancelChunkedTx {
+            caller_evm_address: sender_addr.to_fixed_bytes(),
+            full_tx_hash: tx_hash.to_fixed_bytes(),
+        };
+        execute(deps, env_at(height), mock_info(sender, &[]), msg)
+    };
+
+    let err = store(deps.as_mut(), 0, vec![0; 5]).unwrap_err();
+    assert!(matches!(err, ContractError::ChunkTooLarge));
+
+    let _res = store(deps.as_mut(), 0, vec![0; 4]).unwrap();
+    let _res = store(deps.as_mut(), 1, vec![1; 4]).unwrap();
+
+    // A stored chunk isn't overwritten
+    let err = store(deps.as_mut(), 1, vec![2; 4]).unwrap_err();
+    assert!(matches!(err, ContractError::ChunkAlreadyStored));
+
+    let err = store(deps.as_mut(), 2, vec![2; 4]).unwrap_err();
+    assert!(matches!(err, ContractError::ChunkedTxTooLarge));
+    let _res = store(deps.as_mut(), 2, vec![2; 2]).unwrap();
+
+    // Expired chunks can't be added to or executed
+    let err = store_at(deps.as_mut(), height + CHUNK_EXPIRY_BLOCKS, 3, vec![3; 1]).unwrap_err();
+    assert!(matches!(err, ContractError::ChunksExpired));
+    let err = execute_chunks(deps.as_mut(), height + CHUNK_EXPIRY_BLOCKS).unwrap_err();
+    assert!(matches!(err, ContractError::ChunksExpired));
+
+    // Anyone can purge the chunks once they expire, operators can cancel them before
+    let err = cancel(deps.as_mut(), "anyone", height + CHUNK_EXPIRY_BLOCKS - 1).unwrap_err();
+    assert!(matches!(err, ContractError::ChunksNotExpired));
+
+    let res = cancel(deps.as_mut(), "anyone", height + CHUNK_EXPIRY_BLOCKS).unwrap();
+    assert_eq!(vec![attr("action", "cancel_chunked_tx"), attr("tx_hash", hex::encode(tx_hash))], res.attributes);
+
+    let err = cancel(deps.as_mut(), "creator", height).unwrap_err();
+    assert!(matches!(err, ContractError::UnknownChunkedTx));
+
+    // The removed chunks can be stored again, and the size limit starts over
+    let _res = store(deps.as_mut(), 1, vec![2; 4]).unwrap();
+    let _res = store(deps.as_mut(), 2, vec![2; 4]).unwrap();
+
+    let _res = cancel(deps.as_mut(), "creator", height).unwrap();
+
+    let err = execute_chunks(deps.as_mut(), height).unwrap_err();
+    assert!(matches!(err, ContractError::TxChunksMissing));
+}
+
+#[test]
+fn tx_chunk_status() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let trx_full = b"terranova chunked upload".to_vec();
+    let tx_hash = keccak256_h256(&trx_full);
+    let chunks = vec![&trx_full[..10], &trx_full[10..20], &trx_full[20..]];
+
+    let status = |deps: Deps| -> TxChunkStatusResponse {
+        let msg = QueryMsg::TxChunkStatus {
+            caller_evm_address: sender_addr.to_fixed_bytes(),
+            full_tx_hash: tx_hash.to_fixed_bytes(),
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+
+    assert_eq!(TxChunkStatusResponse {
+        chunks: vec![],
+        total_size: 0,
+        contiguous_chunks: 0,
+        running_hash: keccak256_h256(&[]).to_fixed_bytes(),
+        expiry_height: None,
+    }, status(deps.as_ref()));
+
+    // The upload drops after the first and last chunks, only the first one is hashed
+    for i in [0, 2] {
+        let msg = ExecuteMsg::StoreTxChunk {
+            caller_evm_address: sender_addr.to_fixed_bytes(),
+            full_tx_hash: tx_hash.to_fixed_bytes(),
+            chunk_index: i as u8,
+            chunk_data: chunks[i].to_vec(),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    assert_eq!(TxChunkStatusResponse {
+        chunks: vec![TxChunkResponse { chunk_index: 0, length: 10 }, TxChunkResponse { chunk_index: 2, length: 4 }],
+        total_size: 14,
+        contiguous_chunks: 1,
+        running_hash: keccak256_h256(chunks[0]).to_fixed_bytes(),
+        expiry_height: Some(mock_env().block.height + CHUNK_EXPIRY_BLOCKS),
+    }, status(deps.as_ref()));
+
+    // Only the missing chunk is sent to resume the upload
+    let msg = ExecuteMsg::StoreTxChunk {
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        full_tx_hash: tx_hash.to_fixed_bytes(),
+        chunk_index: 1,
+        chunk_data: chunks[1].to_vec(),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let res = status(deps.as_ref());
+    assert_eq!(3, res.chunks.len());
+    assert_eq!(24, res.total_size);
+    assert_eq!(3, res.contiguous_chunks);
+    assert_eq!(tx_hash.to_fixed_bytes(), res.running_hash);
 }
 
 #[test]
 fn account_query() {
     let mut deps = mock_dependencies(&[]);
 
-    let msg = InstantiateMsg { };
+    let msg = instantiate_msg();
     let info = mock_info("creator", &coins(1000, "earth"));
 
     // we can just call .unwrap() to assert this was a success
@@ -371,6 +594,1779 @@ fn account_query() {
     assert_eq!(0, res.nonce); 
 }
 
+#[test]
+fn transaction_nonce_validation() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let receiver_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31755fcA6F1B4FA5bB6bE1");
+
+    // Transfer of 123456 with nonce 5, the sender account nonce is still 0
+    let trx_hex = "0xe00501830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::NonceTooHigh));
+
+    // The same transfer with nonce 0
+    let trx_hex = "0xe08001830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+
+    // Replaying it fails and doesn't transfer anything
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::NonceTooLow));
+
+    assert_eq!(123456, ACCOUNTS.load(deps.as_ref().storage, &receiver_addr).unwrap().balance.as_u128());
+    assert_eq!(1, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().trx_count);
+}
+
+#[test]
+fn gas_accounting() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let gas_used = |res: &Response| res.attributes.iter().find(|attr| attr.key == "gas_used").unwrap().value.clone();
+    let exit_reason = |res: &Response| res.attributes.iter().find(|attr| attr.key == "evm_exit_reason").unwrap().value.clone();
+
+    // Transfer of 123456 with a gas limit of 20000, less than the intrinsic gas of a transaction
+    let trx_hex = "0xdf8001824e2094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::GasLimitTooLow));
+
+    // SimpleStorage.sol, compiled on truffle remix browser
+    let trx_hex = "0xf901808001839896808080b90175608060405260aa60005534801561001557600080fd5b50610150806100256000396000f3fe608060405234801561001057600080fd5b50600436106100365760003560e01c80632e64cec11461003b5780636057361d14610059575b600080fd5b610043610075565b60405161005091906100d9565b60405180910390f35b610073600480360381019061006e919061009d565b61007e565b005b60008054905090565b8060008190555050565b60008135905061009781610103565b92915050565b6000602082840312156100b3576100b26100fe565b5b60006100c184828501610088565b91505092915050565b6100d3816100f4565b82525050565b60006020820190506100ee60008301846100ca565b92915050565b6000819050919050565b600080fd5b61010c816100f4565b811461011757600080fd5b5056fea2646970667358221220b65bdaef17cddab79670f4265ba7f40ee7d3c93b549cac6537012e5ac8ee7f5064736f6c63430008070033";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert!(exit_reason(&res).starts_with("Succeed"));
+
+    // Call store(0xbb) with a gas limit of 25000, not enough left for the SSTORE after the intrinsic gas
+    let trx_hex = "0xf84001018261a894ff3b783539a1a7a53ecacfb1c0778274c670f35b80a46057361d00000000000000000000000000000000000000000000000000000000000000bb";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!("Error(OutOfGas)", exit_reason(&res));
+    assert_eq!("25000", gas_used(&res));
+
+    // The stored value is unchanged: retrieve() still returns 0xaa
+    let trx_hex = "0xe180018398968094ff3b783539a1a7a53ecacfb1c0778274c670f35b80842e64cec1";
+    let msg = QueryMsg::RawEthereumQuery {
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let res: RawEthereumQueryResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(0xaa, U256::from_big_endian_fast(&res.result).as_u128());
+
+    // A plain transfer only pays for the intrinsic gas
+    let trx_hex = "0xe00201830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!("21000", gas_used(&res));
+}
+
+#[test]
+fn gas_fee_payment() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    airdrop_write_balance(deps.as_mut(), mock_env(), sender_addr);
+
+    // Transfer of 123456 with nonce 0, gas price 1 and gas limit 100000
+    let trx_hex = "0xe08001830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Only the used gas is paid, the rest of the gas limit is refunded
+    assert_eq!(99_855_544, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().balance.as_u128());
+    assert_eq!(21_000, ACCOUNTS.load(deps.as_ref().storage, &fee_collector()).unwrap().balance.as_u128());
+
+    // Same transfer with nonce 1 and gas price 10000, the sender can't afford gas_limit * gas_price
+    let trx_hex = "0xe201822710830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InsufficientFunds));
+
+    // Contract creation with nonce 1, gas limit 100000 and the init code 0xfe (INVALID), which consumes all gas
+    let trx_hex = "0xca0101830186a0808081fe";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert!(!res.attributes.iter().any(|attr| attr.key == "evm_exit_reason" && attr.value.starts_with("Succeed")));
+
+    // The failed transaction still pays for the whole gas limit and increments the nonce
+    let sender = ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap();
+    assert_eq!(99_755_544, sender.balance.as_u128());
+    assert_eq!(2, sender.trx_count);
+    assert_eq!(121_000, ACCOUNTS.load(deps.as_ref().storage, &fee_collector()).unwrap().balance.as_u128());
+}
+
+/// Calls the precompile at address `precompile` with `gas` through the proxy contract deployed in `precompiled_contracts`.
+/// The result is empty if the precompile call failed
+fn query_precompile(deps: Deps, proxy: H160, precompile: u64, gas: u64, input: &[u8]) -> Vec<u8> {
+    let mut call_data = H256::from_low_u64_be(precompile).as_bytes().to_vec();
+    call_data.extend_from_slice(H256::from_low_u64_be(gas).as_bytes());
+    call_data.extend_from_slice(input);
+
+    raw_query(deps, parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1"), proxy, &call_data)
+}
+
+#[test]
+fn precompiled_contracts() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Proxy contract, the call data is the precompile address and the gas to give it as words, followed by the precompile input.
+    // It STATICCALLs the precompile, then returns its output or reverts with empty data if the call failed
+    // 
+    // CALLDATASIZE PUSH1 0x40 SWAP1 SUB DUP1 PUSH1 0x40 PUSH1 0 CALLDATACOPY 
+    // PUSH1 0 PUSH1 0 DUP3 PUSH1 0 PUSH1 0 CALLDATALOAD PUSH1 0x20 CALLDATALOAD STATICCALL 
+    // RETURNDATASIZE PUSH1 0 PUSH1 0 RETURNDATACOPY PUSH1 0x26 JUMPI RETURNDATASIZE PUSH1 0 REVERT 
+    // JUMPDEST RETURNDATASIZE PUSH1 0 RETURN
+    let proxy: H160 = parse_h160("0x00000000000000000000000000000000000c0de1");
+    let code = parse_hex("0x366040900380604060003760006000826000600035602035fa3d600060003e6026573d6000fd5b3d6000f3");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), proxy, code);
+
+    let precompiles: Vec<(u64, &str, u64, &str)> = vec![
+        // ecrecover of keccak256("terranova") signed by the private key 0x5b96acee476c4bafe03924b5423be759b8f1222a1ee0017aea25f6eb9cc06c52
+        (0x01,
+            "0xcdb105706bdff6ace9f83660d23426d4446a2de553e6242e77e95986ed5c5a19000000000000000000000000000000000000000000000000000000000000001c7e7336310133aa4b366048e07e7a07ad0b1a13849cef6bd2776c241412eb1d9452afc2116b335785a7ab4dcb56a8fb8639cf0aed92cfdf17ba5b15103e90a764",
+            3000,
+            "0x0000000000000000000000002089e860d447152902a95b8427bcb18626060c59"),
+        // sha256("terranova")
+        (0x02, "0x74657272616e6f7661", 72, "0x193b743322bc660e799f08e1defafae2289a0b538a33924536b2b1c87d6bc1d2"),
+        // ripemd160("terranova")
+        (0x03, "0x74657272616e6f7661", 720, "0x000000000000000000000000f9d4317a2047436184b0d2fb2a200c4e45af00db"),
+        // identity("terranova")
+        (0x04, "0x74657272616e6f7661", 18, "0x74657272616e6f7661"),
+        // 3 ^ (p - 1) mod p, first example of EIP-198
+        (0x05,
+            "0x00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000002003fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2efffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+            13056,
+            "0x0000000000000000000000000000000000000000000000000000000000000001"),
+        // G1 + G1 = 2 * G1
+        (0x06,
+            "0x0000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000002",
+            150,
+            "0x030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd315ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4"),
+        // 3 * G1
+        (0x07,
+            "0x000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000003",
+            6000,
+            "0x0769bf9ac56bea3ff40232bcb1b6bd159315d84715b8e679f2d355961915abf02ab799bee0489429554fdb7c8d086475319e63b40b9c5b57cdf1ff3dd9fe2261"),
+        // e(G1, G2) * e(-G1, G2) = 1
+        (0x08,
+            "0x00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000002198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c21800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa000000000000000000000000000000000000000000000000000000000000000130644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd45198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c21800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa",
+            113000,
+            "0x0000000000000000000000000000000000000000000000000000000000000001"),
+        // 12 rounds of F on the single block "abc", fifth example of EIP-152 (blake2b-512("abc"))
+        (0x09,
+            "0x0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+            12,
+            "0xba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"),
+    ];
+
+    for (precompile, input, cost, output) in precompiles {
+        let input = parse_hex(input);
+
+        // Exactly enough gas
+        assert_eq!(parse_hex(output), query_precompile(deps.as_ref(), proxy, precompile, cost, &input));
+
+        // One gas short, the precompile call fails
+        assert!(query_precompile(deps.as_ref(), proxy, precompile, cost - 1, &input).is_empty());
+    }
+
+    // The pairing of G1 and G2 with itself is not one
+    let input = parse_hex("0x00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000002198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c21800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000002198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c21800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa");
+    assert_eq!(H256::zero().as_bytes(), &query_precompile(deps.as_ref(), proxy, 0x08, 113000, &input)[..]);
+
+    // An invalid signature recovers no address
+    let mut input = parse_hex("0xcdb105706bdff6ace9f83660d23426d4446a2de553e6242e77e95986ed5c5a19000000000000000000000000000000000000000000000000000000000000001c7e7336310133aa4b366048e07e7a07ad0b1a13849cef6bd2776c241412eb1d9452afc2116b335785a7ab4dcb56a8fb8639cf0aed92cfdf17ba5b15103e90a764");
+    input[63] = 29;
+    assert!(query_precompile(deps.as_ref(), proxy, 0x01, 3000, &input).is_empty());
+
+    // ModExp is priced by EIP-2565 from Berlin on
+    let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg { hardfork: Some(Hardfork::Berlin) }).unwrap();
+    let input = parse_hex("0x00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000002003fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2efffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
+    assert_eq!(H256::from_low_u64_be(1).as_bytes(), &query_precompile(deps.as_ref(), proxy, 0x05, 1360, &input)[..]);
+    assert!(query_precompile(deps.as_ref(), proxy, 0x05, 1359, &input).is_empty());
+}
+
+#[test]
+fn create2_address_derivation() {
+    // Examples from EIP-1014: (caller, salt, init_code, address)
+    let examples = vec![
+        ("0x0000000000000000000000000000000000000000", "0x0000000000000000000000000000000000000000000000000000000000000000", "0x00", "0x4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38"),
+        ("0xdeadbeef00000000000000000000000000000000", "0x0000000000000000000000000000000000000000000000000000000000000000", "0x00", "0xB928f69Bb1D91Cd65274e3c79d8986362984fDA3"),
+        ("0xdeadbeef00000000000000000000000000000000", "0x000000000000000000000000feed000000000000000000000000000000000000", "0x00", "0xD04116cDd17beBE565EB2422F2497E06cC1C9833"),
+        ("0x0000000000000000000000000000000000000000", "0x0000000000000000000000000000000000000000000000000000000000000000", "0xdeadbeef", "0x70f2b2914A2a4b783FaEFb75f459A580616Fcb5e"),
+        ("0x00000000000000000000000000000000deadbeef", "0x00000000000000000000000000000000000000000000000000000000cafebabe", "0xdeadbeef", "0x60f3f640a8508fC6a86d45DF051962668E1e8AC7"),
+        ("0x00000000000000000000000000000000deadbeef", "0x00000000000000000000000000000000000000000000000000000000cafebabe", "0xdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef", "0x1d8bfDC5D46DC4f61D6b6115972536eBE6A8854C"),
+        ("0x0000000000000000000000000000000000000000", "0x0000000000000000000000000000000000000000000000000000000000000000", "0x", "0xE33C0C7F7df4809055C3ebA6c09CFe4BaF1BD9e0"),
+    ];
+
+    for (caller, salt, init_code, address) in examples {
+        let caller = parse_h160(caller);
+        let salt = parse_hex(salt);
+        let code_hash = keccak256_h256(&parse_hex(init_code));
+
+        let derived: H160 = keccak256_h256_v(&[&[0xff], &caller[..], &salt[..], &code_hash[..]]).into();
+        assert_eq!(parse_h160(address), derived);
+    }
+}
+
+#[test]
+fn create2_contract_deploy() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+
+    // Factory contract, the call data is a salt word followed by init code.
+    // It CREATE2s the init code with the salt and returns the created address (0 if the creation failed)
+    // 
+    // CALLDATASIZE PUSH1 0x20 SWAP1 SUB DUP1 PUSH1 0x20 PUSH1 0 CALLDATACOPY 
+    // PUSH1 0 CALLDATALOAD SWAP1 PUSH1 0 PUSH1 0 CREATE2 PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let factory: H160 = parse_h160("0x000000000000000000000000000000000000c2ea");
+    let code = parse_hex("0x36602090038060206000376000359060006000f560005260206000f3");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), factory, code);
+
+    // Salt 0xcafebabe, init code PUSH1 1 PUSH1 0 PUSH1 0 RETURN, which deploys the single byte 0x00 as code
+    let trx_hex = "0xf8448001830f424094000000000000000000000000000000000000c2ea80a700000000000000000000000000000000000000000000000000000000cafebabe600160006000f3";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // keccak256(0xff ++ factory ++ salt ++ keccak256(init_code))[12..]
+    let child: H160 = parse_h160("0x593a1e8946edd3dcc92bd298378a18fcdc365669");
+    assert_eq!(H256::from(child).as_bytes(), &res.data.unwrap().0[..]);
+    assert_eq!(vec![0x00], CONTRACTS.load(deps.as_ref().storage, &child).unwrap().code);
+    assert_eq!(1, ACCOUNTS.load(deps.as_ref().storage, &child).unwrap().trx_count);
+
+    // Same salt and init code, the address collides with the created contract
+    let trx_hex = "0xf8440101830f424094000000000000000000000000000000000000c2ea80a700000000000000000000000000000000000000000000000000000000cafebabe600160006000f3";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(H256::zero().as_bytes(), &res.data.unwrap().0[..]);
+
+    // Salt 0xcafebabf
+    let trx_hex = "0xf8440201830f424094000000000000000000000000000000000000c2ea80a700000000000000000000000000000000000000000000000000000000cafebabf600160006000f3";
+    let msg = ExecuteMsg::ExecuteRawEthereumTx { 
+        caller_evm_address: sender_addr.to_fixed_bytes(), 
+        unsigned_tx: parse_hex(&trx_hex)
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let child: H160 = parse_h160("0xa647b438726b43eb1a358add6cd3969637a84daa");
+    assert_eq!(H256::from(child).as_bytes(), &res.data.unwrap().0[..]);
+    assert!(CONTRACTS.has(deps.as_ref().storage, &child));
+}
+
+#[test]
+fn block_hash_history() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let env_at = |height: u64| {
+        let mut env = mock_env();
+        env.block.height = height;
+        env.block.time = env.block.time.plus_seconds(height * 6);
+        env
+    };
+
+    // Returns BLOCKHASH of the block number in the call data
+    // PUSH1 0 CALLDATALOAD BLOCKHASH PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let contract: H160 = parse_h160("0x00000000000000000000000000000000000b10c4");
+    airdrop_deploy_contract(deps.as_mut(), env_at(100), contract, parse_hex("0x60003540600052602060006000f3"));
+
+    let block_hash = |deps: Deps, height: u64, number: u64| {
+        let msg = QueryMsg::RawEthereumQuery {
+            caller_evm_address: contract.to_fixed_bytes(),
+            unsigned_tx: raw_tx(deps, contract, Some(contract), 0, 0, H256::from_low_u64_be(number).as_bytes()),
+        };
+
+        let res: RawEthereumQueryResponse = from_binary(&query(deps, env_at(height), msg).unwrap()).unwrap();
+        H256::from_slice(&res.result)
+    };
+
+    // Any mutable access to the EVM state records the block hash
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    airdrop_write_balance(deps.as_mut(), env_at(101), sender_addr);
+    airdrop_write_balance(deps.as_mut(), env_at(103), sender_addr);
+
+    let hash_100 = block_hash(deps.as_ref(), 104, 100);
+    let hash_101 = block_hash(deps.as_ref(), 104, 101);
+    let hash_103 = block_hash(deps.as_ref(), 104, 103);
+    assert_ne!(H256::zero(), hash_100);
+    assert_ne!(H256::zero(), hash_101);
+    assert_ne!(hash_100, hash_101);
+    assert_ne!(hash_101, hash_103);
+
+    // No hash was recorded at height 102, and the hash of the current block is unknown
+    assert_eq!(H256::zero(), block_hash(deps.as_ref(), 104, 102));
+    assert_eq!(H256::zero(), block_hash(deps.as_ref(), 103, 103));
+    assert_eq!(H256::zero(), block_hash(deps.as_ref(), 104, 1_000));
+
+    // Block 100 is available for the next 256 blocks only
+    assert_eq!(hash_100, block_hash(deps.as_ref(), 356, 100));
+    assert_eq!(H256::zero(), block_hash(deps.as_ref(), 357, 100));
+
+    // Block 357 takes the ring buffer slot of block 101
+    airdrop_write_balance(deps.as_mut(), env_at(357), sender_addr);
+    assert_eq!(H256::zero(), block_hash(deps.as_ref(), 358, 101));
+    assert_ne!(H256::zero(), block_hash(deps.as_ref(), 358, 357));
+    assert_ne!(hash_101, block_hash(deps.as_ref(), 358, 357));
+}
+
+#[test]
+fn signed_user_user_transfer() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Address of the private key 0x5b96acee476c4bafe03924b5423be759b8f1222a1ee0017aea25f6eb9cc06c52
+    let sender_addr: H160 = parse_h160("0x2089e860d447152902a95b8427bcb18626060c59");
+    let receiver_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31755fcA6F1B4FA5bB6bE1");
+
+    airdrop_write_balance(deps.as_mut(), mock_env(), sender_addr);
+
+    // Same transfer as in simple_user_user_transfer (nonce 0), signed with EIP-155 for chain ID 789789789
+    let trx_hex = "0xf8678001830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080845e2678dea076ca7d57f92ab3da115aa745fe9e3a3fe8f22715b24173cf66dd4ce4d406483ea02257d08daa5acfcf41cc2adc707bfec95a72a3af6dd1779e1c76af724a5263fa";
+    let trx = parse_hex(&trx_hex);
+
+    let msg = ExecuteMsg::ExecuteSignedEthereumTx { signed_tx: trx };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    assert_eq!(123456, ACCOUNTS.load(deps.as_ref().storage, &receiver_addr).unwrap().balance.as_u128());
+    assert_eq!(1, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().trx_count);
+}
+
+#[test]
+fn signed_transaction_rejected() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0x2089e860d447152902a95b8427bcb18626060c59");
+    airdrop_write_balance(deps.as_mut(), mock_env(), sender_addr);
+
+    // The same transfer signed for chain ID 1
+    let trx_hex = "0xf8638001830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e2408025a0997a263e7b521d5daf211480c1cd4fcb51dc068800060c1ab593f4f4e1cac529a04f19c84784f01c5e5fd7b530cb119614d4719772f27b847159ae81a704fdf062";
+    let msg = ExecuteMsg::ExecuteSignedEthereumTx { signed_tx: parse_hex(&trx_hex) };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidChainId));
+
+    // Flip a bit of s, the recovered sender is no longer sender_addr and has no funds to transfer
+    let mut trx = parse_hex("0xf8678001830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080845e2678dea076ca7d57f92ab3da115aa745fe9e3a3fe8f22715b24173cf66dd4ce4d406483ea02257d08daa5acfcf41cc2adc707bfec95a72a3af6dd1779e1c76af724a5263fa");
+    let last = trx.len() - 1;
+    trx[last] ^= 1;
+    let msg = ExecuteMsg::ExecuteSignedEthereumTx { signed_tx: trx };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InsufficientFunds));
+
+    assert_eq!(100_000_000, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().balance.as_u128());
+    assert_eq!(0, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().trx_count);
+}
+
+#[test]
+fn signed_typed_transactions() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Address of the private key 0x5b96acee476c4bafe03924b5423be759b8f1222a1ee0017aea25f6eb9cc06c52
+    let sender_addr: H160 = parse_h160("0x2089e860d447152902a95b8427bcb18626060c59");
+    let receiver_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31755fcA6F1B4FA5bB6bE1");
+
+    airdrop_write_balance(deps.as_mut(), mock_env(), sender_addr);
+
+    // EIP-1559 transfer of 123456 (nonce 0), max priority fee 1, max fee 2, with the storage slot 0 of the receiver in its access list
+    let trx_hex = "0x02f8a3842f133c5d800102830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080f838f794b34e2213751c5d8e9a31755fca6f1b4fa5bb6be1e1a0000000000000000000000000000000000000000000000000000000000000000080a03a66298f13d8d044dcd821354a9d984f3ddb9a2834fb7ad9cac7b5ca08b9920ca005581f6baa27f676d62de257dbd4e24adabc1022aa14b5f136d929d8cee2c83d";
+    let msg = ExecuteMsg::ExecuteSignedEthereumTx { signed_tx: parse_hex(&trx_hex) };
+
+    // Typed transactions are rejected before the hardforks introducing them, EIP-2930 in Berlin and EIP-1559 in London
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTransactionData));
+
+    let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg { hardfork: Some(Hardfork::Berlin) }).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTransactionData));
+
+    let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg { hardfork: Some(Hardfork::London) }).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // The access list costs 2400 for the address and 1900 for the storage key, the effective gas price is the priority fee
+    let gas_used = 21_000 + 2_400 + 1_900;
+    assert!(res.attributes.contains(&attr("gas_used", gas_used.to_string())));
+    assert_eq!(123456, ACCOUNTS.load(deps.as_ref().storage, &receiver_addr).unwrap().balance.as_u128());
+    assert_eq!(100_000_000 - 123456 - gas_used, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().balance.as_u128());
+
+    // EIP-2930 transfer of 1 (nonce 1), gas price 1, with an empty access list
+    let trx_hex = "0x01f866842f133c5d0101830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be10180c080a0bb11c96355fa56e7231667175c7391e4aedacf0f8140c7f682389755acf8a0a5a040126f5d437f81a47eef9ba3c430857f93912f835ec92a5c9ad185c1eae4f0af";
+    let msg = ExecuteMsg::ExecuteSignedEthereumTx { signed_tx: parse_hex(&trx_hex) };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    assert!(res.attributes.contains(&attr("gas_used", "21000")));
+    assert_eq!(123457, ACCOUNTS.load(deps.as_ref().storage, &receiver_addr).unwrap().balance.as_u128());
+    assert_eq!(2, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().trx_count);
+
+    // EIP-1559 transaction (nonce 2) whose max priority fee 3 is higher than its max fee 2
+    let trx_hex = "0x02f867842f133c5d020302830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be10180c080a0e6cde8df68c0526d40b917cc82e83db678f0567fe983881d58e14594ca3e23eca02578c48634c785b4442005c45857c4b6f4607ffdd91046f39a9c2dcc07f234d5";
+    let msg = ExecuteMsg::ExecuteSignedEthereumTx { signed_tx: parse_hex(&trx_hex) };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTransactionData));
+
+    // Unknown transaction type
+    let msg = ExecuteMsg::ExecuteSignedEthereumTx { signed_tx: parse_hex(&trx_hex.replacen("0x02", "0x03", 1)) };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTransactionData));
+}
+
+#[test]
+fn configurable_chain_id() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg { chain_id: Some(1), ..instantiate_msg() };
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let config = CONTRACT_CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(1, config.chain_id);
+    assert_eq!("uluna", config.denom);
+
+    // Returns CHAINID
+    // CHAINID PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let contract: H160 = parse_h160("0x00000000000000000000000000000000000c4a11");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), contract, parse_hex("0x4660005260206000f3"));
+
+    assert_eq!(U256::from(1), U256::from_big_endian(&raw_query(deps.as_ref(), contract, contract, &[])));
+
+    let sender_addr: H160 = parse_h160("0x2089e860d447152902a95b8427bcb18626060c59");
+    let receiver_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31755fcA6F1B4FA5bB6bE1");
+    airdrop_write_balance(deps.as_mut(), mock_env(), sender_addr);
+
+    // The transfer signed for the default chain ID 789789789 is rejected
+    let trx_hex = "0xf8678001830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080845e2678dea076ca7d57f92ab3da115aa745fe9e3a3fe8f22715b24173cf66dd4ce4d406483ea02257d08daa5acfcf41cc2adc707bfec95a72a3af6dd1779e1c76af724a5263fa";
+    let msg = ExecuteMsg::ExecuteSignedEthereumTx { signed_tx: parse_hex(&trx_hex) };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidChainId));
+
+    // The same transfer signed for chain ID 1 goes through
+    let trx_hex = "0xf8638001830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e2408025a0997a263e7b521d5daf211480c1cd4fcb51dc068800060c1ab593f4f4e1cac529a04f19c84784f01c5e5fd7b530cb119614d4719772f27b847159ae81a704fdf062";
+    let msg = ExecuteMsg::ExecuteSignedEthereumTx { signed_tx: parse_hex(&trx_hex) };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    assert_eq!(123456, ACCOUNTS.load(deps.as_ref().storage, &receiver_addr).unwrap().balance.as_u128());
+    assert_eq!(1, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().trx_count);
+}
+
+#[test]
+fn hardfork_migration() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(Hardfork::Istanbul, CONTRACT_CONFIG.load(deps.as_ref().storage).unwrap().hardfork);
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+
+    let call = |deps: DepsMut, to: Option<H160>, call_data: Vec<u8>| raw_call(deps, sender_addr, to, 0, &call_data);
+    let migrate_to = |deps: DepsMut, hardfork: Option<Hardfork>| migrate(deps, mock_env(), MigrateMsg { hardfork });
+
+    // Returns SLOAD(0)
+    // PUSH1 0 SLOAD PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let sload: H160 = parse_h160("0x000000000000000000000000000000000005104d");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), sload, parse_hex("0x60005460005260206000f3"));
+
+    // Returns 1, using PUSH0 for the offsets
+    // PUSH1 1 PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+    let push0: H160 = parse_h160("0x000000000000000000000000000000000000505f");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), push0, parse_hex("0x60015f5260205ff3"));
+
+    // Returns the base fee
+    // BASEFEE PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let basefee: H160 = parse_h160("0x0000000000000000000000000000000000000048");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), basefee, parse_hex("0x4860005260206000f3"));
+
+    // Reads the balance of the coinbase
+    // COINBASE BALANCE POP STOP
+    let coinbase: H160 = parse_h160("0x0000000000000000000000000000000000000c0b");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), coinbase, parse_hex("0x41315000"));
+
+    // Creates a contract from 0xC001 bytes of zeros, one more than the init code limit of EIP-3860
+    // PUSH2 0xc001 PUSH1 0 PUSH1 0 CREATE STOP
+    let creator: H160 = parse_h160("0x00000000000000000000000000000000000c4ea7");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), creator, parse_hex("0x61c00160006000f000"));
+
+    // The SLOAD costs 800 under Istanbul
+    let res = call(deps.as_mut(), Some(sload), vec![]);
+    assert!(res.attributes.contains(&attr("gas_used", "21818")));
+
+    let res = migrate_to(deps.as_mut(), Some(Hardfork::Berlin)).unwrap();
+    assert_eq!(vec![attr("action", "migrate"), attr("hardfork", "berlin")], res.attributes);
+
+    // The first SLOAD of the slot in the transaction is cold under Berlin and costs 2100
+    let res = call(deps.as_mut(), Some(sload), vec![]);
+    assert!(res.attributes.contains(&attr("gas_used", "23118")));
+
+    // BASEFEE is undefined before London
+    let res = call(deps.as_mut(), Some(basefee), vec![]);
+    assert!(res.attributes.contains(&attr("evm_exit_reason", "Error(OutOfGas)")));
+
+    let res = migrate_to(deps.as_mut(), Some(Hardfork::London)).unwrap();
+    assert_eq!(vec![attr("action", "migrate"), attr("hardfork", "london")], res.attributes);
+
+    // Deploying code starting with 0xEF fails under London
+    // PUSH1 0xef PUSH1 0 MSTORE8 PUSH1 1 PUSH1 0 RETURN
+    let res = call(deps.as_mut(), None, parse_hex("0x60ef60005360016000f3"));
+    assert!(res.attributes.contains(&attr("evm_exit_reason", "Error(InvalidCode)")));
+
+    // Terranova has no base fee
+    let res = call(deps.as_mut(), Some(basefee), vec![]);
+    assert_eq!(H256::zero().as_bytes(), &res.data.unwrap().0[..]);
+
+    // The coinbase is cold, its BALANCE costs 2600
+    let res = call(deps.as_mut(), Some(coinbase), vec![]);
+    assert!(res.attributes.contains(&attr("gas_used", "23604")));
+
+    // Init code isn't limited nor charged per word before Shanghai
+    let res = call(deps.as_mut(), Some(creator), vec![]);
+    assert!(res.attributes.contains(&attr("evm_exit_reason", "Succeed(Stopped)")));
+
+    // STOP
+    let res = call(deps.as_mut(), None, parse_hex("0x00"));
+    assert!(res.attributes.contains(&attr("gas_used", "53004")));
+
+    // PUSH0 is undefined before Shanghai
+    let res = call(deps.as_mut(), Some(push0), vec![]);
+    assert!(res.attributes.contains(&attr("evm_exit_reason", "Error(OutOfGas)")));
+
+    // The chain can't go back to an earlier hardfork, and keeps its hardfork if none is given
+    let err = migrate_to(deps.as_mut(), Some(Hardfork::Berlin)).unwrap_err();
+    assert!(matches!(err, ContractError::HardforkDowngrade));
+
+    let res = migrate_to(deps.as_mut(), None).unwrap();
+    assert_eq!(vec![attr("action", "migrate"), attr("hardfork", "london")], res.attributes);
+
+    let _res = migrate_to(deps.as_mut(), Some(Hardfork::Shanghai)).unwrap();
+    assert_eq!(Hardfork::Shanghai, CONTRACT_CONFIG.load(deps.as_ref().storage).unwrap().hardfork);
+
+    let res = call(deps.as_mut(), Some(push0), vec![]);
+    assert_eq!(H256::from_low_u64_be(1).as_bytes(), &res.data.unwrap().0[..]);
+
+    // The coinbase is warm from the start of the transaction under Shanghai, its BALANCE costs 100
+    let res = call(deps.as_mut(), Some(coinbase), vec![]);
+    assert!(res.attributes.contains(&attr("gas_used", "21104")));
+
+    // Every word of the init code costs 2, and init code over the limit fails the creation
+    let res = call(deps.as_mut(), None, parse_hex("0x00"));
+    assert!(res.attributes.contains(&attr("gas_used", "53006")));
+
+    let res = call(deps.as_mut(), Some(creator), vec![]);
+    assert!(res.attributes.contains(&attr("evm_exit_reason", "Error(CreateContractLimit)")));
+
+    let msg = ExecuteMsg::ExecuteRawEthereumTx {
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        unsigned_tx: raw_tx(deps.as_ref(), sender_addr, None, 0, 0, &[0; 0xc001]),
+    };
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTransactionData));
+}
+
+#[test]
+fn legacy_storage_migration() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // PUSH1 0 SLOAD PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let sload: H160 = parse_h160("0x00000000000000000000000000000000005104d0");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), sload, parse_hex("0x60005460005260206000f3"));
+
+    // Storage saved before the generations were part of the key
+    let index = U256::zero().to_bytes();
+    let other_index = U256::one().to_bytes();
+    LEGACY_CONTRACT_STORAGE.save(deps.as_mut().storage, (&sload, &index), &U256::from(0x2a)).unwrap();
+    LEGACY_CONTRACT_STORAGE.save(deps.as_mut().storage, (&sload, &other_index), &U256::from(0x2b)).unwrap();
+
+    // The contract upgrade doesn't move anything, the entries are read from the legacy storage until they are moved
+    let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg { hardfork: None }).unwrap();
+    assert_eq!(None, CONTRACT_STORAGE.may_load(deps.as_ref().storage, (&sload, U64Key::new(0), &index)).unwrap());
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let result = |res: &Response| res.attributes.iter().find(|attr| attr.key == "result").unwrap().value.clone();
+    let res = raw_call(deps.as_mut(), sender_addr, Some(sload), 0, &[]);
+    assert_eq!(hex::encode(H256::from_low_u64_be(0x2a)), result(&res));
+
+    // Only operators move the entries, up to the limit in every message
+    let migrate_legacy = |deps: DepsMut, sender: &str| {
+        execute(deps, mock_env(), mock_info(sender, &[]), ExecuteMsg::MigrateLegacyStorage { limit: 1 })
+    };
+    let err = migrate_legacy(deps.as_mut(), "anyone").unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+
+    for migrated in ["1", "1", "0"] {
+        let res = migrate_legacy(deps.as_mut(), "creator").unwrap();
+        assert!(res.attributes.contains(&attr("migrated", migrated)));
+    }
+    assert_eq!(Some(U256::from(0x2a)), CONTRACT_STORAGE.may_load(deps.as_ref().storage, (&sload, U64Key::new(0), &index)).unwrap());
+    assert_eq!(Some(U256::from(0x2b)), CONTRACT_STORAGE.may_load(deps.as_ref().storage, (&sload, U64Key::new(0), &other_index)).unwrap());
+    assert!(!LEGACY_CONTRACT_STORAGE.has(deps.as_ref().storage, (&sload, &index)));
+
+    let res = raw_call(deps.as_mut(), sender_addr, Some(sload), 0, &[]);
+    assert_eq!(hex::encode(H256::from_low_u64_be(0x2a)), result(&res));
+}
+
+#[test]
+fn evm_event_logs() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Emits a log with topics 0xaa, 0xbb and data 0x2a
+    // PUSH1 0x2a PUSH1 0 MSTORE PUSH1 0xbb PUSH1 0xaa PUSH1 0x20 PUSH1 0 LOG2 STOP
+    let contract: H160 = parse_h160("0x00000000000000000000000000000000000e7e17");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), contract, parse_hex("0x602a60005260bb60aa60206000a200"));
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+
+    let trx = raw_tx(deps.as_ref(), sender_addr, Some(contract), 1, 0, &[]);
+    let tx_hash = unsigned_tx_hash(&sender_addr, &trx);
+
+    let msg = ExecuteMsg::ExecuteRawEthereumTx {
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        unsigned_tx: trx
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let topic_a = H256::from_low_u64_be(0xaa);
+    let topic_b = H256::from_low_u64_be(0xbb);
+    let data = H256::from_low_u64_be(0x2a);
+
+    assert_eq!(1, res.events.len());
+    let event = &res.events[0];
+    assert_eq!("evm_log", event.ty);
+    let attribute = |key: &str| event.attributes.iter().find(|attr| attr.key == key).unwrap().value.clone();
+    assert_eq!(hex::encode(tx_hash), attribute("tx_hash"));
+    assert_eq!("0", attribute("log_index"));
+    assert_eq!(hex::encode(contract), attribute("address"));
+    assert_eq!(format!("{},{}", hex::encode(topic_a), hex::encode(topic_b)), attribute("topics"));
+    assert_eq!(hex::encode(data), attribute("data"));
+
+    let tx_logs = |deps: Deps, tx_hash: H256, address: Option<H160>, topics: Vec<Option<H256>>| {
+        let msg = QueryMsg::QueryTxLogs {
+            tx_hash: tx_hash.to_fixed_bytes(),
+            address: address.map(|address| address.to_fixed_bytes()),
+            topics: topics.iter().map(|topic| topic.map(|topic| topic.to_fixed_bytes())).collect(),
+        };
+        let res: TxLogsResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        res.logs
+    };
+
+    let logs = tx_logs(deps.as_ref(), tx_hash, None, vec![]);
+    assert_eq!(vec![EvmLogResponse {
+        address: contract.to_fixed_bytes(),
+        topics: vec![topic_a.to_fixed_bytes(), topic_b.to_fixed_bytes()],
+        data: data.as_bytes().to_vec(),
+    }], logs);
+
+    assert_eq!(logs, tx_logs(deps.as_ref(), tx_hash, Some(contract), vec![Some(topic_a), Some(topic_b)]));
+    assert_eq!(logs, tx_logs(deps.as_ref(), tx_hash, None, vec![None, Some(topic_b)]));
+    assert!(tx_logs(deps.as_ref(), tx_hash, Some(sender_addr), vec![]).is_empty());
+
+    // Topics only match at their position, and a log needs at least as many topics as the filter
+    assert!(tx_logs(deps.as_ref(), tx_hash, None, vec![Some(topic_b)]).is_empty());
+    assert!(tx_logs(deps.as_ref(), tx_hash, None, vec![None, None, None]).is_empty());
+    assert!(tx_logs(deps.as_ref(), tx_hash, None, vec![Some(H256::from_low_u64_be(0xcc))]).is_empty());
+    assert!(tx_logs(deps.as_ref(), H256::zero(), None, vec![]).is_empty());
+}
+
+#[test]
+fn transaction_receipts() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Emits a log with topics 0xaa, 0xbb and data 0x2a
+    // PUSH1 0x2a PUSH1 0 MSTORE PUSH1 0xbb PUSH1 0xaa PUSH1 0x20 PUSH1 0 LOG2 STOP
+    let log_contract: H160 = parse_h160("0x00000000000000000000000000000000000e7e17");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), log_contract, parse_hex("0x602a60005260bb60aa60206000a200"));
+
+    // Reverts with 0x2a
+    // PUSH1 0x2a PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 REVERT
+    let revert_contract: H160 = parse_h160("0x00000000000000000000000000000000000dead1");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), revert_contract, parse_hex("0x602a60005260206000fd"));
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+
+    let env_at = |height: u64| {
+        let mut env = mock_env();
+        env.block.height = height;
+        env
+    };
+
+    let send = |deps: DepsMut, height: u64, to: Option<H160>| {
+        let trx = raw_tx(deps.as_ref(), sender_addr, to, 1, 0, &[]);
+
+        let msg = ExecuteMsg::ExecuteRawEthereumTx {
+            caller_evm_address: sender_addr.to_fixed_bytes(),
+            unsigned_tx: trx.clone()
+        };
+        let res = execute(deps, env_at(height), mock_info("creator", &[]), msg).unwrap();
+        (unsigned_tx_hash(&sender_addr, &trx), res)
+    };
+
+    let receipt = |deps: Deps, tx_hash: H256| {
+        let msg = QueryMsg::GetTransactionReceipt { tx_hash: tx_hash.to_fixed_bytes() };
+        let res: TransactionReceiptResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        res
+    };
+
+    // Contract creation with empty init code
+    let (create_hash, res) = send(deps.as_mut(), 100, None);
+    let create_receipt = receipt(deps.as_ref(), create_hash);
+    assert_eq!(create_hash.to_fixed_bytes(), create_receipt.tx_hash);
+    assert_eq!(100, create_receipt.block_height);
+    assert_eq!(sender_addr.to_fixed_bytes(), create_receipt.from);
+    assert_eq!(None, create_receipt.to);
+    assert_eq!(1, create_receipt.status);
+    assert_eq!(Some(parse_h160(&res.attributes[2].value).to_fixed_bytes()), create_receipt.contract_address);
+    assert_eq!(create_receipt.gas_used, create_receipt.cumulative_gas_used);
+    assert!(create_receipt.logs.is_empty());
+    assert_eq!(vec![0_u8; 256], create_receipt.logs_bloom);
+
+    // Logs and their bloom filter, in the same block
+    let (log_hash, _) = send(deps.as_mut(), 100, Some(log_contract));
+    let log_receipt = receipt(deps.as_ref(), log_hash);
+    assert_eq!(1, log_receipt.status);
+    assert_eq!(Some(log_contract.to_fixed_bytes()), log_receipt.to);
+    assert_eq!(None, log_receipt.contract_address);
+    assert_eq!(create_receipt.gas_used + log_receipt.gas_used, log_receipt.cumulative_gas_used);
+    assert_eq!(vec![EvmLogResponse {
+        address: log_contract.to_fixed_bytes(),
+        topics: vec![H256::from_low_u64_be(0xaa).to_fixed_bytes(), H256::from_low_u64_be(0xbb).to_fixed_bytes()],
+        data: H256::from_low_u64_be(0x2a).as_bytes().to_vec(),
+    }], log_receipt.logs);
+
+    let mut bloom = vec![0_u8; 256];
+    for (index, byte) in [(15, 32), (66, 2), (79, 32), (94, 32), (104, 128), (114, 64), (131, 1), (210, 8), (234, 128)] {
+        bloom[index] = byte;
+    }
+    assert_eq!(bloom, log_receipt.logs_bloom);
+
+    // Reverted transactions have a receipt as well, the cumulative gas used restarts in a new block
+    let (revert_hash, _) = send(deps.as_mut(), 101, Some(revert_contract));
+    let revert_receipt = receipt(deps.as_ref(), revert_hash);
+    assert_eq!(0, revert_receipt.status);
+    assert_eq!(101, revert_receipt.block_height);
+    assert_eq!(revert_receipt.gas_used, revert_receipt.cumulative_gas_used);
+    assert_eq!(H256::from_low_u64_be(0x2a).as_bytes().to_vec(), revert_receipt.revert_data);
+    assert!(revert_receipt.logs.is_empty());
+
+    let msg = QueryMsg::GetTransactionReceipt { tx_hash: [0_u8; 32] };
+    assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+}
+
+#[test]
+fn same_unsigned_transaction_from_two_callers() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // PUSH1 1 PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let contract: H160 = parse_h160("0x000000000000000000000000000000000000c0de");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), contract, parse_hex("0x600160005260206000f3"));
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let other_addr: H160 = parse_h160("0x00000000000000000000000000000000000a11ce");
+
+    let trx = raw_tx(deps.as_ref(), sender_addr, Some(contract), 0, 0, &[]);
+    assert_eq!(trx, raw_tx(deps.as_ref(), other_addr, Some(contract), 0, 0, &[]));
+
+    // The hashes of the same bytes sent by different callers don't collide
+    let sender_hash = unsigned_tx_hash(&sender_addr, &trx);
+    let other_hash = unsigned_tx_hash(&other_addr, &trx);
+    assert_ne!(sender_hash, other_hash);
+
+    // Both callers can have the same transaction in progress
+    for caller in [sender_addr, other_addr] {
+        let msg = ExecuteMsg::ExecuteIterativeEthereumTx {
+            caller_evm_address: caller.to_fixed_bytes(),
+            unsigned_tx: trx.clone(),
+            max_steps: 2,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert!(res.attributes.contains(&attr("tx_status", "pending")));
+    }
+
+    for tx_hash in [sender_hash, other_hash] {
+        let msg = ExecuteMsg::ContinueTx { tx_hash: tx_hash.to_fixed_bytes(), max_steps: 100 };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert!(res.attributes.contains(&attr("tx_status", "completed")));
+        assert!(res.attributes.contains(&attr("tx_hash", hex::encode(tx_hash))));
+    }
+
+    // Each caller keeps its own receipt
+    for (caller, tx_hash) in [(sender_addr, sender_hash), (other_addr, other_hash)] {
+        let msg = QueryMsg::GetTransactionReceipt { tx_hash: tx_hash.to_fixed_bytes() };
+        let receipt: TransactionReceiptResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(tx_hash.to_fixed_bytes(), receipt.tx_hash);
+        assert_eq!(caller.to_fixed_bytes(), receipt.from);
+        assert_eq!(1, receipt.status);
+    }
+}
+
+#[test]
+fn operator_authorization() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let operators = |deps: Deps| {
+        let res: OperatorsResponse = from_binary(&query(deps, mock_env(), QueryMsg::QueryOperators {}).unwrap()).unwrap();
+        res
+    };
+    assert_eq!(OperatorsResponse { admin: String::from("creator"), operators: vec![String::from("creator")] }, operators(deps.as_ref()));
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let transfer = || ExecuteMsg::ExecuteRawEthereumTx {
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        unsigned_tx: parse_hex("0xe08001830186a094b34e2213751c5d8e9a31755fca6f1b4fa5bb6be18301e24080")
+    };
+
+    // Anyone else can't submit transactions, nor manage operators
+    let stranger = mock_info("stranger", &[]);
+    let err = execute(deps.as_mut(), mock_env(), stranger.clone(), transfer()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+    let msg = ExecuteMsg::StoreTxChunk { caller_evm_address: sender_addr.to_fixed_bytes(), full_tx_hash: [0; 32], chunk_index: 0, chunk_data: vec![0] };
+    let err = execute(deps.as_mut(), mock_env(), stranger.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+    let msg = ExecuteMsg::AddOperator { address: String::from("stranger") };
+    let err = execute(deps.as_mut(), mock_env(), stranger.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+    assert_eq!(0, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().trx_count);
+
+    // The admin adds a new operator
+    let msg = ExecuteMsg::AddOperator { address: String::from("operator") };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert_eq!(vec![String::from("creator"), String::from("operator")], operators(deps.as_ref()).operators);
+
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), transfer()).unwrap();
+    assert_eq!(1, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().trx_count);
+
+    // Operators can't manage operators
+    let msg = ExecuteMsg::RemoveOperator { address: String::from("creator") };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+
+    // The admin hands the admin role over, and the new admin removes the operator
+    let msg = ExecuteMsg::UpdateAdmin { admin: String::from("new_admin") };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let msg = ExecuteMsg::AddOperator { address: String::from("stranger") };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+
+    let msg = ExecuteMsg::RemoveOperator { address: String::from("operator") };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("new_admin", &[]), msg).unwrap();
+    assert_eq!(OperatorsResponse { admin: String::from("new_admin"), operators: vec![String::from("creator")] }, operators(deps.as_ref()));
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), transfer()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+}
+
+#[test]
+fn native_deposit_withdraw() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let evm_addr: H160 = parse_h160("0x2e36b2970ab7A4C955eADD836585c21A087Ab904");
+    let balance = |deps: Deps| ACCOUNTS.load(deps.storage, &evm_addr).unwrap().balance;
+    let deposit = || ExecuteMsg::Deposit { evm_address: evm_addr.to_fixed_bytes() };
+
+    // Only positive amounts of the configured denom are accepted
+    for funds in [vec![], coins(5, "earth"), coins(0, "uluna"), vec![Coin::new(5, "uluna"), Coin::new(5, "uusd")]] {
+        let err = execute(deps.as_mut(), mock_env(), mock_info("depositor", &funds), deposit()).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidFunds));
+    }
+
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("depositor", &coins(5, "uluna")), deposit()).unwrap();
+    assert_eq!(U256::from(5_000_000_000_000_u64), balance(deps.as_ref()));
+
+    let withdraw = |amount: u128| ExecuteMsg::Withdraw {
+        caller_evm_address: evm_addr.to_fixed_bytes(),
+        recipient: String::from("recipient"),
+        amount: Uint128::from(amount),
+    };
+
+    // Only operators can withdraw on behalf of an EVM address
+    let err = execute(deps.as_mut(), mock_env(), mock_info("depositor", &[]), withdraw(2)).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), withdraw(2)).unwrap();
+    assert_eq!(1, res.messages.len());
+    assert_eq!(CosmosMsg::Bank(BankMsg::Send { to_address: String::from("recipient"), amount: coins(2, "uluna") }), res.messages[0].msg);
+    assert_eq!(U256::from(3_000_000_000_000_u64), balance(deps.as_ref()));
+
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), withdraw(4)).unwrap_err();
+    assert!(matches!(err, ContractError::InsufficientFunds));
+    assert_eq!(U256::from(3_000_000_000_000_u64), balance(deps.as_ref()));
+
+    // A balance that wasn't deposited can't withdraw more than the deposited supply
+    ACCOUNTS.update(deps.as_mut().storage, &evm_addr, |account| -> StdResult<_> {
+        let mut account = account.unwrap();
+        account.balance = U256::from(10_000_000_000_000_u64);
+        Ok(account)
+    }).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), withdraw(4)).unwrap_err();
+    assert!(matches!(err, ContractError::InsufficientSupply));
+
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), withdraw(3)).unwrap();
+    assert_eq!(U256::from(7_000_000_000_000_u64), balance(deps.as_ref()));
+}
+
+#[test]
+fn withdraw_precompile() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Calls the address in the first word of the call data with the rest of the call data as input, reverts if the call fails
+    let forwarder = "0x36602090038060206000376000600082600060006000355af160215760006000fd5b00";
+    // Same, but always reverts after the call
+    let reverter = "0x36602090038060206000376000600082600060006000355af160006000fd";
+
+    let contract: H160 = parse_h160("0x00000000000000000000000000000000000f0a2d");
+    let reverting_contract: H160 = parse_h160("0x00000000000000000000000000000000000f0a2e");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), contract, parse_hex(forwarder));
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), reverting_contract, parse_hex(reverter));
+
+    for address in [contract, reverting_contract] {
+        let msg = ExecuteMsg::Deposit { evm_address: address.to_fixed_bytes() };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("depositor", &coins(5, "uluna")), msg).unwrap();
+    }
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let recipient = "terra1x46rqay4d3cssq8gxxvqz8xt6nwlz4td20k38v";
+    let withdraw = |deps: DepsMut, to: H160, amount: u64, recipient: &str| {
+        let mut call_data = H256::from(withdraw_address()).as_bytes().to_vec();
+        call_data.extend_from_slice(H256::from_low_u64_be(amount).as_bytes());
+        call_data.extend_from_slice(recipient.as_bytes());
+
+        raw_call(deps, sender_addr, Some(to), 0, &call_data)
+    };
+    let balance = |deps: Deps, address: H160| ACCOUNTS.load(deps.storage, &address).unwrap().balance;
+
+    let res = withdraw(deps.as_mut(), contract, 2, recipient);
+    assert_eq!(vec![SubMsg::new(BankMsg::Send { to_address: String::from(recipient), amount: coins(2, "uluna") })], res.messages);
+    assert_eq!(U256::from(3_000_000_000_000_u64), balance(deps.as_ref(), contract));
+
+    // Withdrawing more than the balance, or to an invalid Terra address, fails the call
+    for (amount, recipient) in [(4, recipient), (1, "x")] {
+        let res = withdraw(deps.as_mut(), contract, amount, recipient);
+        assert!(res.messages.is_empty());
+        assert!(res.attributes.iter().any(|attr| attr.key == "evm_exit_reason" && attr.value.starts_with("Revert")));
+        assert_eq!(U256::from(3_000_000_000_000_u64), balance(deps.as_ref(), contract));
+    }
+
+    // The withdrawal is discarded when the calling contract reverts
+    let res = withdraw(deps.as_mut(), reverting_contract, 2, recipient);
+    assert!(res.messages.is_empty());
+    assert_eq!(U256::from(5_000_000_000_000_u64), balance(deps.as_ref(), reverting_contract));
+
+    // A balance that wasn't deposited can't withdraw more than the deposited supply, the transaction still executes
+    ACCOUNTS.update(deps.as_mut().storage, &contract, |account| -> StdResult<_> {
+        let mut account = account.unwrap();
+        account.balance = U256::from(20_000_000_000_000_u64);
+        Ok(account)
+    }).unwrap();
+    let nonce = |deps: Deps| ACCOUNTS.load(deps.storage, &sender_addr).unwrap().trx_count;
+    let sender_nonce = nonce(deps.as_ref());
+
+    let res = withdraw(deps.as_mut(), contract, 9, recipient);
+    assert!(res.messages.is_empty());
+    assert!(res.attributes.iter().any(|attr| attr.key == "evm_exit_reason" && attr.value.starts_with("Revert")));
+    assert_eq!(U256::from(20_000_000_000_000_u64), balance(deps.as_ref(), contract));
+    assert_eq!(sender_nonce + 1, nonce(deps.as_ref()));
+
+    let res = withdraw(deps.as_mut(), contract, 8, recipient);
+    assert_eq!(vec![SubMsg::new(BankMsg::Send { to_address: String::from(recipient), amount: coins(8, "uluna") })], res.messages);
+    assert_eq!(U256::from(12_000_000_000_000_u64), balance(deps.as_ref(), contract));
+}
+
+/// MockQuerier has no wasm support, this answers the TokenInfo query of any CW20 contract
+struct Cw20Querier {
+    base: MockQuerier,
+}
+
+impl Querier for Cw20Querier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        match from_slice(bin_request) {
+            Ok(QueryRequest::<Empty>::Wasm(WasmQuery::Smart { .. })) => {
+                let token_info = TokenInfoResponse {
+                    name: String::from("Wrapped Token"),
+                    symbol: String::from("WTKN"),
+                    decimals: 6,
+                    total_supply: Uint128::from(1_000_000_u64),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&token_info).unwrap()))
+            }
+            _ => self.base.raw_query(bin_request),
+        }
+    }
+}
+
+#[test]
+fn cw20_bridge() {
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: Cw20Querier { base: MockQuerier::new(&[]) },
+    };
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Only the admin registers CW20 tokens, and only once
+    let register = || ExecuteMsg::RegisterCw20 { cw20_address: String::from("cw20token") };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), register()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), register()).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), register()).unwrap_err();
+    assert!(matches!(err, ContractError::Cw20AlreadyRegistered));
+
+    let facade = facade_address(&Addr::unchecked("cw20token"));
+    let res: Cw20FacadeResponse = from_binary(
+        &query(deps.as_ref(), mock_env(), QueryMsg::QueryCw20Facade { cw20_address: String::from("cw20token") }).unwrap()
+    ).unwrap();
+    assert_eq!(facade.to_fixed_bytes(), res.evm_address);
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let receiver_addr: H160 = parse_h160("0x2e36b2970ab7A4C955eADD836585c21A087Ab904");
+
+    // Tokens are deposited with a CW20 Send, only registered CW20 contracts are accepted
+    let deposit = || ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("holder"),
+        amount: Uint128::from(1000_u64),
+        msg: to_binary(&Cw20HookMsg::Deposit { evm_address: sender_addr.to_fixed_bytes() }).unwrap(),
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info("othertoken", &[]), deposit()).unwrap_err();
+    assert!(matches!(err, ContractError::UnknownCw20Token));
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("cw20token", &[]), deposit()).unwrap();
+
+    let cw20_balance = |deps: Deps, owner: H160| CW20_BALANCES.may_load(deps.storage, (&facade, &owner)).unwrap().unwrap_or_default();
+    assert_eq!(Uint128::from(1000_u64), cw20_balance(deps.as_ref(), sender_addr));
+
+    let word = |address: H160| H256::from(address).as_bytes().to_vec();
+    let amount = |amount: u64| H256::from_low_u64_be(amount).as_bytes().to_vec();
+    let call = |deps: DepsMut, caller: H160, to: H160, call_data: Vec<u8>| raw_call(deps, caller, Some(to), 0, &call_data);
+    let succeeded = |res: &Response| res.attributes.iter().any(|attr| attr.key == "evm_exit_reason" && attr.value.starts_with("Succeed"));
+
+    // transfer(receiver, 300)
+    let res = call(deps.as_mut(), sender_addr, facade, [parse_hex("0xa9059cbb"), word(receiver_addr), amount(300)].concat());
+    assert!(succeeded(&res));
+    assert_eq!(Uint128::from(700_u64), cw20_balance(deps.as_ref(), sender_addr));
+    assert_eq!(Uint128::from(300_u64), cw20_balance(deps.as_ref(), receiver_addr));
+
+    // Transferring more than the balance fails
+    let res = call(deps.as_mut(), sender_addr, facade, [parse_hex("0xa9059cbb"), word(receiver_addr), amount(701)].concat());
+    assert!(!succeeded(&res));
+    assert_eq!(Uint128::from(700_u64), cw20_balance(deps.as_ref(), sender_addr));
+
+    // approve(receiver, 200) then transferFrom(sender, receiver, 150) by the receiver
+    let res = call(deps.as_mut(), sender_addr, facade, [parse_hex("0x095ea7b3"), word(receiver_addr), amount(200)].concat());
+    assert!(succeeded(&res));
+    let res = call(deps.as_mut(), receiver_addr, facade, [parse_hex("0x23b872dd"), word(sender_addr), word(receiver_addr), amount(150)].concat());
+    assert!(succeeded(&res));
+    assert_eq!(Uint128::from(550_u64), cw20_balance(deps.as_ref(), sender_addr));
+    assert_eq!(Uint128::from(450_u64), cw20_balance(deps.as_ref(), receiver_addr));
+    assert_eq!(Some(Uint128::from(50_u64)), CW20_ALLOWANCES.may_load(deps.as_ref().storage, (&facade, &sender_addr, &receiver_addr)).unwrap());
+
+    // The allowance left is too low
+    let res = call(deps.as_mut(), receiver_addr, facade, [parse_hex("0x23b872dd"), word(sender_addr), word(receiver_addr), amount(51)].concat());
+    assert!(!succeeded(&res));
+
+    // transferToTerra(recipient, 100) sends the tokens out with a CW20 transfer
+    let recipient = "terra1x46rqay4d3cssq8gxxvqz8xt6nwlz4td20k38v";
+    let mut recipient_bytes = recipient.as_bytes().to_vec();
+    recipient_bytes.resize(64, 0);
+    let call_data = [parse_hex("0x327d3770"), amount(0x40), amount(100), amount(recipient.len() as u64), recipient_bytes].concat();
+    let res = call(deps.as_mut(), receiver_addr, facade, call_data);
+    assert!(succeeded(&res));
+    assert_eq!(vec![SubMsg::new(WasmMsg::Execute {
+        contract_addr: String::from("cw20token"),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer { recipient: String::from(recipient), amount: Uint128::from(100_u64) }).unwrap(),
+        funds: vec![],
+    })], res.messages);
+    assert_eq!(Uint128::from(350_u64), cw20_balance(deps.as_ref(), receiver_addr));
+    assert_eq!(Some(Uint128::from(900_u64)), CW20_SUPPLIES.may_load(deps.as_ref().storage, &facade).unwrap());
+
+    // A contract can't DELEGATECALL the facade to move the tokens of its caller
+    let delegate_forwarder = "0x3660209003806020600037600060008260006000355af4601f5760006000fd5b00";
+    let contract: H160 = parse_h160("0x00000000000000000000000000000000000f0a2f");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), contract, parse_hex(delegate_forwarder));
+
+    let call_data = [word(facade), parse_hex("0xa9059cbb"), word(receiver_addr), amount(10)].concat();
+    let res = call(deps.as_mut(), sender_addr, contract, call_data);
+    assert!(!succeeded(&res));
+    assert_eq!(Uint128::from(550_u64), cw20_balance(deps.as_ref(), sender_addr));
+}
+
+/// abi.encode(string contract, bytes msg, uint256 funds), the input of the wasm execute precompile
+fn wasm_execute_input(contract: &str, msg: &[u8], funds: u64) -> Vec<u8> {
+    let padded = |bytes: &[u8]| {
+        let mut padded = H256::from_low_u64_be(bytes.len() as u64).as_bytes().to_vec();
+        padded.extend_from_slice(bytes);
+        padded.resize(32 + (bytes.len() + 31) / 32 * 32, 0);
+        padded
+    };
+    let contract = padded(contract.as_bytes());
+    let msg = padded(msg);
+
+    [
+        H256::from_low_u64_be(0x60).as_bytes().to_vec(),
+        H256::from_low_u64_be(0x60 + contract.len() as u64).as_bytes().to_vec(),
+        H256::from_low_u64_be(funds).as_bytes().to_vec(),
+        contract,
+        msg,
+    ].concat()
+}
+
+#[test]
+fn wasm_execute_precompile() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    // Always reverts after calling the address in the first word of the call data with the rest of the call data as input
+    let reverter = "0x36602090038060206000376000600082600060006000355af160006000fd";
+    let reverting_contract: H160 = parse_h160("0x00000000000000000000000000000000000f0a30");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), reverting_contract, parse_hex(reverter));
+
+    for address in [sender_addr, reverting_contract] {
+        let msg = ExecuteMsg::Deposit { evm_address: address.to_fixed_bytes() };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("depositor", &coins(5, "uluna")), msg).unwrap();
+    }
+
+    let call = |deps: DepsMut, caller: H160, to: H160, call_data: Vec<u8>| raw_call(deps, caller, Some(to), 0, &call_data);
+    let status = |deps: Deps, id: u64| {
+        U256::from_big_endian(&raw_query(deps, sender_addr, wasm_call_status_address(), H256::from_low_u64_be(id).as_bytes()))
+    };
+    let balance = |deps: Deps, address: H160| ACCOUNTS.load(deps.storage, &address).unwrap().balance;
+
+    // Only the contracts allowed by the admin can be called
+    let wasm_msg = br#"{"ping":{}}"#;
+    let res = call(deps.as_mut(), sender_addr, wasm_execute_address(), wasm_execute_input("othercontract", wasm_msg, 2));
+    assert!(res.messages.is_empty());
+    assert_eq!(U256::from(5_000_000_000_000_u64), balance(deps.as_ref(), sender_addr));
+
+    let allow = |contract: &str| ExecuteMsg::AllowWasmExecuteTarget { contract: String::from(contract) };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), allow("othercontract")).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), allow(mock_env().contract.address.as_str())).unwrap_err();
+    assert!(matches!(err, ContractError::WasmExecuteTargetNotAllowed));
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), allow("othercontract")).unwrap();
+
+    let res: WasmExecuteTargetsResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::QueryWasmExecuteTargets {}).unwrap()).unwrap();
+    assert_eq!(vec![String::from("othercontract")], res.contracts);
+
+    let res = call(deps.as_mut(), sender_addr, wasm_execute_address(), wasm_execute_input("othercontract", wasm_msg, 2));
+    assert_eq!(vec![SubMsg::reply_always(WasmMsg::Execute {
+        contract_addr: String::from("othercontract"),
+        msg: Binary::from(&wasm_msg[..]),
+        funds: coins(2, "uluna"),
+    }, 0)], res.messages);
+    assert_eq!(U256::from(3_000_000_000_000_u64), balance(deps.as_ref(), sender_addr));
+    assert_eq!(U256::from(1), status(deps.as_ref(), 0));
+    assert_eq!(U256::zero(), status(deps.as_ref(), 1));
+
+    // A failed call is reported to the EVM and its funds are refunded, even while a transaction in progress locks the source
+    let unsigned_tx = raw_tx(deps.as_ref(), sender_addr, Some(reverting_contract), 0, 0, &[]);
+    let tx_hash = unsigned_tx_hash(&sender_addr, &unsigned_tx);
+    let msg = ExecuteMsg::ExecuteIterativeEthereumTx { caller_evm_address: sender_addr.to_fixed_bytes(), unsigned_tx, max_steps: 1 };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert!(ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().rw_blocked);
+
+    let reply_msg = |id: u64| Reply { id, result: ContractResult::Err(String::from("ping failed")) };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg(0)).unwrap();
+    assert_eq!(U256::from(3), status(deps.as_ref(), 0));
+    assert_eq!(U256::from(5_000_000_000_000_u64), balance(deps.as_ref(), sender_addr));
+    assert!(reply(deps.as_mut(), mock_env(), reply_msg(0)).is_err());
+
+    let msg = ExecuteMsg::ContinueTx { tx_hash: tx_hash.to_fixed_bytes(), max_steps: 100 };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert!(res.attributes.contains(&attr("tx_status", "completed")));
+    assert_eq!(U256::from(5_000_000_000_000_u64), balance(deps.as_ref(), sender_addr));
+
+    // The next call gets the next id, its success is reported as well
+    let res = call(deps.as_mut(), sender_addr, wasm_execute_address(), wasm_execute_input("othercontract", wasm_msg, 0));
+    assert_eq!(vec![SubMsg::reply_always(WasmMsg::Execute {
+        contract_addr: String::from("othercontract"),
+        msg: Binary::from(&wasm_msg[..]),
+        funds: vec![],
+    }, 1)], res.messages);
+    let _res = reply(deps.as_mut(), mock_env(), Reply { id: 1, result: ContractResult::Ok(SubMsgExecutionResponse { events: vec![], data: None }) }).unwrap();
+    assert_eq!(U256::from(2), status(deps.as_ref(), 1));
+
+    // The Terranova contract can't be called, and calls are discarded when the calling contract reverts
+    let res = call(deps.as_mut(), sender_addr, wasm_execute_address(), wasm_execute_input(mock_env().contract.address.as_str(), wasm_msg, 0));
+    assert!(res.messages.is_empty());
+
+    let call_data = [H256::from(wasm_execute_address()).as_bytes().to_vec(), wasm_execute_input("othercontract", wasm_msg, 1)].concat();
+    let res = call(deps.as_mut(), sender_addr, reverting_contract, call_data);
+    assert!(res.messages.is_empty());
+    assert_eq!(U256::from(5_000_000_000_000_u64), balance(deps.as_ref(), reverting_contract));
+
+    // A disallowed contract can't be called anymore
+    let msg = ExecuteMsg::DisallowWasmExecuteTarget { contract: String::from("othercontract") };
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let res = call(deps.as_mut(), sender_addr, wasm_execute_address(), wasm_execute_input("othercontract", wasm_msg, 0));
+    assert!(res.messages.is_empty());
+}
+
+#[test]
+fn wasm_execute_cw20_custody() {
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: Cw20Querier { base: MockQuerier::new(&[]) },
+    };
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Allowed before its registration, the token is disallowed when it is registered
+    let allow = || ExecuteMsg::AllowWasmExecuteTarget { contract: String::from("cw20token") };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), allow()).unwrap();
+    let msg = ExecuteMsg::RegisterCw20 { cw20_address: String::from("cw20token") };
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info, allow()).unwrap_err();
+    assert!(matches!(err, ContractError::WasmExecuteTargetNotAllowed));
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let deposit = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("holder"),
+        amount: Uint128::from(1000_u64),
+        msg: to_binary(&Cw20HookMsg::Deposit { evm_address: sender_addr.to_fixed_bytes() }).unwrap(),
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("cw20token", &[]), deposit).unwrap();
+
+    // The tokens held for EVM accounts can't be sent with a CW20 transfer from the EVM
+    let facade = facade_address(&Addr::unchecked("cw20token"));
+    let transfer = br#"{"transfer":{"recipient":"thief","amount":"1000"}}"#;
+    let res = raw_call(deps.as_mut(), sender_addr, Some(wasm_execute_address()), 0, &wasm_execute_input("cw20token", transfer, 0));
+    assert!(res.messages.is_empty());
+    assert!(res.attributes.iter().any(|attr| attr.key == "evm_exit_reason" && !attr.value.starts_with("Succeed")));
+    assert_eq!(Some(Uint128::from(1000_u64)), CW20_BALANCES.may_load(deps.as_ref().storage, (&facade, &sender_addr)).unwrap());
+}
+
+#[test]
+fn wasm_query_precompile() {
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: Cw20Querier { base: MockQuerier::new(&[("holder", &coins(7, "uluna"))]) },
+    };
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let query_precompile = |deps: Deps, request: &QueryRequest<Empty>| {
+        raw_query(deps, sender_addr, wasm_query_address(), &to_vec(request).unwrap())
+    };
+
+    // Bank queries
+    let request = QueryRequest::Bank(BankQuery::Balance { address: String::from("holder"), denom: String::from("uluna") });
+    let res: BalanceResponse = from_slice(&query_precompile(deps.as_ref(), &request)).unwrap();
+    assert_eq!(coin(7, "uluna"), res.amount);
+
+    // Smart queries return the JSON response of the contract
+    let request = QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: String::from("cw20token"),
+        msg: to_binary(&Cw20QueryMsg::TokenInfo {}).unwrap(),
+    });
+    let res: TokenInfoResponse = from_slice(&query_precompile(deps.as_ref(), &request)).unwrap();
+    assert_eq!(String::from("WTKN"), res.symbol);
+
+    // Failed queries and queries to the Terranova contract fail the call
+    let request = QueryRequest::Wasm(WasmQuery::Raw {
+        contract_addr: String::from("othercontract"),
+        key: Binary::from(b"config"),
+    });
+    assert!(query_precompile(deps.as_ref(), &request).is_empty());
+
+    let request = QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: mock_env().contract.address.into_string(),
+        msg: to_binary(&QueryMsg::QueryOperators {}).unwrap(),
+    });
+    assert!(query_precompile(deps.as_ref(), &request).is_empty());
+}
+
+#[test]
+fn selfdestruct() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+
+    // Same CREATE2 factory as in create2_contract_deploy
+    let factory: H160 = parse_h160("0x000000000000000000000000000000000000c2ea");
+    let code = parse_hex("0x36602090038060206000376000359060006000f560005260206000f3");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), factory, code);
+
+    let call = |deps: DepsMut, to: Option<H160>, value: u64, call_data: Vec<u8>| raw_call(deps, sender_addr, to, value, &call_data);
+    let balance = |deps: Deps, address: H160| ACCOUNTS.load(deps.storage, &address).unwrap().balance;
+
+    // Init code: SSTORE(0, SLOAD(0) + 1), then deploys the runtime code.
+    // Runtime code: returns SLOAD(0) without call data, otherwise SELFDESTRUCTs in favor of the caller
+    //
+    // CALLDATASIZE ISZERO PUSH1 7 JUMPI CALLER SELFDESTRUCT JUMPDEST PUSH1 0 SLOAD PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let init_code = parse_hex("0x60016000540160005560138060146000396000f3");
+    let runtime_code = parse_hex("0x361560075733ff5b60005460005260206000f3");
+    let create2_data = [H256::from_low_u64_be(0xdead).as_bytes().to_vec(), init_code, runtime_code.clone()].concat();
+
+    let res = call(deps.as_mut(), Some(factory), 0, create2_data.clone());
+    let child = H160::from(H256::from_slice(&res.data.unwrap().0));
+    assert!(!child.is_zero());
+    assert_eq!(runtime_code, CONTRACTS.load(deps.as_ref().storage, &child).unwrap().code);
+
+    let res = call(deps.as_mut(), Some(child), 0, vec![]);
+    assert_eq!(H256::from_low_u64_be(1).as_bytes(), &res.data.unwrap().0[..]);
+
+    // The whole balance goes to the beneficiary, the account, its code and its storage are removed
+    let sender_balance = balance(deps.as_ref(), sender_addr);
+    let _res = call(deps.as_mut(), Some(child), 1000, vec![0x01]);
+    assert_eq!(sender_balance, balance(deps.as_ref(), sender_addr));
+    assert!(!ACCOUNTS.has(deps.as_ref().storage, &child));
+    assert!(!CONTRACTS.has(deps.as_ref().storage, &child));
+    assert!(STALE_STORAGE.has(deps.as_ref().storage, (&child, U64Key::new(0))));
+
+    // A contract re-created at the same address starts with an empty storage, in the next storage generation
+    let res = call(deps.as_mut(), Some(factory), 0, create2_data);
+    assert_eq!(H256::from(child).as_bytes(), &res.data.unwrap().0[..]);
+    assert_eq!(1, ACCOUNTS.load(deps.as_ref().storage, &child).unwrap().storage_generation);
+
+    let res = call(deps.as_mut(), Some(child), 0, vec![]);
+    assert_eq!(H256::from_low_u64_be(1).as_bytes(), &res.data.unwrap().0[..]);
+
+    // Only operators prune the entries of stale generations
+    let prune = || ExecuteMsg::PruneStorage { limit: 10 };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), prune()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), prune()).unwrap();
+    assert_eq!(vec![attr("action", "prune_storage"), attr("pruned", "1")], res.attributes);
+    assert!(!STALE_STORAGE.has(deps.as_ref().storage, (&child, U64Key::new(0))));
+    assert_eq!(1, CONTRACT_STORAGE.sub_prefix(&child).range(deps.as_ref().storage, None, None, Order::Ascending).count());
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), prune()).unwrap();
+    assert_eq!(vec![attr("action", "prune_storage"), attr("pruned", "0")], res.attributes);
+
+    // A contract destroyed by its own init code is never deployed, the value sent to it goes back to the caller
+    //
+    // CALLER SELFDESTRUCT
+    let sender_balance = balance(deps.as_ref(), sender_addr);
+    let _res = call(deps.as_mut(), None, 5, parse_hex("0x33ff"));
+    assert_eq!(sender_balance, balance(deps.as_ref(), sender_addr));
+
+    // A contract destroyed in favor of itself burns its balance, the native coins backing it leave the supply
+    //
+    // ADDRESS SELFDESTRUCT
+    let burner: H160 = parse_h160("0x000000000000000000000000000000000000b072");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), burner, parse_hex("0x30ff"));
+    let msg = ExecuteMsg::Deposit { evm_address: burner.to_fixed_bytes() };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("depositor", &coins(3, "uluna")), msg).unwrap();
+    assert_eq!(Uint128::new(3), NATIVE_SUPPLY.load(deps.as_ref().storage).unwrap());
+
+    let _res = call(deps.as_mut(), Some(burner), 0, vec![]);
+    assert!(!ACCOUNTS.has(deps.as_ref().storage, &burner));
+    assert_eq!(Uint128::zero(), NATIVE_SUPPLY.load(deps.as_ref().storage).unwrap());
+}
+
+#[test]
+fn iterative_transaction() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let other_addr: H160 = parse_h160("0x00000000000000000000000000000000000a11ce");
+
+    // Stores 1 at slot 0 and returns SLOAD(0)
+    // PUSH1 1 PUSH1 0 SSTORE PUSH1 0 SLOAD PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let contract: H160 = parse_h160("0x000000000000000000000000000000000005104d");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), contract, parse_hex("0x600160005560005460005260206000f3"));
+
+    let unsigned_tx = raw_tx(deps.as_ref(), sender_addr, Some(contract), 0, 0, &[]);
+    let tx_hash = unsigned_tx_hash(&sender_addr, &unsigned_tx);
+
+    let msg = ExecuteMsg::ExecuteIterativeEthereumTx {
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        unsigned_tx: unsigned_tx.clone(),
+        max_steps: 3,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    assert!(res.attributes.contains(&attr("tx_status", "pending")));
+    assert!(res.attributes.contains(&attr("steps_executed", "3")));
+
+    // Nothing is applied before the transaction completes
+    assert_eq!(0, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().trx_count);
+
+    // The same transaction can't be started twice
+    let msg = ExecuteMsg::ExecuteIterativeEthereumTx {
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        unsigned_tx,
+        max_steps: 3,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::TxAlreadyPending));
+
+    let continue_tx = |deps: DepsMut, tx_hash: H256, max_steps: u64| {
+        let msg = ExecuteMsg::ContinueTx { tx_hash: tx_hash.to_fixed_bytes(), max_steps };
+        execute(deps, mock_env(), mock_info("creator", &[]), msg)
+    };
+
+    let res = continue_tx(deps.as_mut(), tx_hash, 3).unwrap();
+    assert!(res.attributes.contains(&attr("tx_status", "pending")));
+    assert!(res.attributes.contains(&attr("steps_executed", "6")));
+
+    // The contract is locked by the transaction in progress
+    let msg = ExecuteMsg::ExecuteRawEthereumTx {
+        caller_evm_address: other_addr.to_fixed_bytes(),
+        unsigned_tx: raw_tx(deps.as_ref(), other_addr, Some(contract), 0, 0, &[]),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::AccountLocked));
+
+    let res = continue_tx(deps.as_mut(), tx_hash, 100).unwrap();
+    assert!(res.attributes.contains(&attr("tx_status", "completed")));
+    assert!(res.attributes.contains(&attr("evm_exit_reason", "Succeed(Returned)")));
+    assert_eq!(H256::from_low_u64_be(1).as_bytes(), &res.data.unwrap().0[..]);
+    assert_eq!(1, ACCOUNTS.load(deps.as_ref().storage, &sender_addr).unwrap().trx_count);
+
+    let err = continue_tx(deps.as_mut(), tx_hash, 100).unwrap_err();
+    assert!(matches!(err, ContractError::UnknownPendingTx));
+
+    // The locks are released once the transaction completes
+    let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    assert!(res.attributes.contains(&attr("evm_exit_reason", "Succeed(Returned)")));
+}
+
+#[test]
+fn pending_transaction_locks() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let sender_addr: H160 = parse_h160("0xB34e2213751c5d8e9a31355fcA6F1B4FA5bB6bE1");
+    let other_addr: H160 = parse_h160("0x00000000000000000000000000000000000a11ce");
+    airdrop_write_balance(deps.as_mut(), mock_env(), other_addr);
+
+    // Returns SLOAD(0)
+    // PUSH1 0 SLOAD PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let sload: H160 = parse_h160("0x000000000000000000000000000000000005104d");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), sload, parse_hex("0x60005460005260206000f3"));
+
+    let account = |deps: Deps, address: H160| ACCOUNTS.load(deps.storage, &address).unwrap();
+
+    // The transaction pauses after reading the storage of the contract
+    let unsigned_tx = raw_tx(deps.as_ref(), sender_addr, Some(sload), 1, 0, &[]);
+    let tx_hash = unsigned_tx_hash(&sender_addr, &unsigned_tx);
+    let msg = ExecuteMsg::ExecuteIterativeEthereumTx {
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        unsigned_tx,
+        max_steps: 2,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    assert!(res.attributes.contains(&attr("tx_status", "pending")));
+
+    // The caller paid the gas fee so it is write locked, the contract was only read
+    assert!(account(deps.as_ref(), sender_addr).rw_blocked);
+    assert_eq!(U256::from(100_000_000 - 1_000_000), account(deps.as_ref(), sender_addr).balance);
+    assert!(!account(deps.as_ref(), sload).rw_blocked);
+    assert_eq!(1, account(deps.as_ref(), sload).ro_blocked_count);
+
+    let other_tx = |deps: DepsMut, value: u64| {
+        let msg = ExecuteMsg::ExecuteRawEthereumTx {
+            caller_evm_address: other_addr.to_fixed_bytes(),
+            unsigned_tx: raw_tx(deps.as_ref(), other_addr, Some(sload), 0, value, &[]),
+        };
+        execute(deps, mock_env(), mock_info("creator", &[]), msg)
+    };
+
+    // Other transactions can read the contract but not change it
+    let res = other_tx(deps.as_mut(), 0).unwrap();
+    assert!(res.attributes.contains(&attr("evm_exit_reason", "Succeed(Returned)")));
+
+    let err = other_tx(deps.as_mut(), 1).unwrap_err();
+    assert!(matches!(err, ContractError::AccountLocked));
+
+    // The caller can't be read by other transactions
+    let msg = ExecuteMsg::Withdraw {
+        caller_evm_address: sender_addr.to_fixed_bytes(),
+        recipient: String::from("recipient"),
+        amount: Uint128::new(1),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::AccountLocked));
+
+    // Only the admin can cancel the transaction, once it wasn't continued for PENDING_TX_TIMEOUT blocks
+    let cancel = |deps: DepsMut, sender: &str, height: u64| {
+        let mut env = mock_env();
+        env.block.height = height;
+        let msg = ExecuteMsg::CancelPendingTx { tx_hash: tx_hash.to_fixed_bytes() };
+        execute(deps, env, mock_info(sender, &[]), msg)
+    };
+    let height = mock_env().block.height;
+
+    let err = cancel(deps.as_mut(), "anyone", height + PENDING_TX_TIMEOUT).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized));
+
+    let err = cancel(deps.as_mut(), "creator", height + PENDING_TX_TIMEOUT - 1).unwrap_err();
+    assert!(matches!(err, ContractError::PendingTxNotExpired));
+
+    let res = cancel(deps.as_mut(), "creator", height + PENDING_TX_TIMEOUT).unwrap();
+    assert_eq!(vec![attr("action", "cancel_pending_tx"), attr("tx_hash", hex::encode(tx_hash))], res.attributes);
+
+    // The locks are released, the gas fee is refunded and the nonce wasn't used
+    assert!(!account(deps.as_ref(), sender_addr).rw_blocked);
+    assert_eq!(U256::from(100_000_000), account(deps.as_ref(), sender_addr).balance);
+    assert_eq!(0, account(deps.as_ref(), sender_addr).trx_count);
+    assert_eq!(0, account(deps.as_ref(), sload).ro_blocked_count);
+
+    let msg = ExecuteMsg::ContinueTx { tx_hash: tx_hash.to_fixed_bytes(), max_steps: 100 };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::UnknownPendingTx));
+
+    let res = other_tx(deps.as_mut(), 1).unwrap();
+    assert!(res.attributes.contains(&attr("evm_exit_reason", "Succeed(Returned)")));
+}
+
+#[test]
+fn eth_call_query() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = instantiate_msg();
+    let info = mock_info("creator", &coins(1000, "earth"));
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Returns SLOAD(0) + NUMBER
+    // PUSH1 0 SLOAD NUMBER ADD PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN
+    let contract: H160 = parse_h160("0x000000000000000000000000000000000005104d");
+    airdrop_deploy_contract(deps.as_mut(), mock_env(), contract, parse_hex("0x600054430160005260206000f3"));
+
+    let call = |deps: Deps, msg: EthCallMsg| -> Result<EthCallResponse, ContractError> {
+        query(deps, mock_env(), QueryMsg::EthCall(msg)).map(|res| from_binary(&res).unwrap())
+    };
+    let call_msg = |to: H160| EthCallMsg {
+        from: None,
+        to: Some(to.to_fixed_bytes()),
+        data: vec![],
+        value: Uint256::zero(),
+        gas_limit: None,
+        block: None,
+        state_overrides: vec![],
+    };
+
+    let res = call(deps.as_ref(), call_msg(contract)).unwrap();
+    assert_eq!("Succeed(Returned)", res.exit_reason);
+    assert!(res.success);
+    assert_eq!(H256::from_low_u64_be(mock_env().block.height).as_bytes(), &res.result[..]);
+    assert_eq!(None, res.revert_reason);
+
+    // The block number and the storage of the contract are replaced, the caller gets the balance to send value
+    let msg = EthCallMsg {
+        value: Uint256::from(7_u64),
+        block: Some(BlockContext { number: Some(100), timestamp: None }),
+        state_overrides: vec![
+            StateOverride {
+                address: contract.to_fixed_bytes(),
+                balance: None,
+                code: None,
+                storage: vec![StorageSlot { key: [0; 32], value: H256::from_low_u64_be(5).to_fixed_bytes() }],
+            },
+            StateOverride {
+                address: [0; 20],
+                balance: Some(Uint256::from(10_u64)),
+                code: None,
+                storage: vec![],
+            },
+        ],
+        ..call_msg(contract)
+    };
+    let res = call(deps.as_ref(), msg.clone()).unwrap();
+    assert_eq!(H256::from_low_u64_be(105).as_bytes(), &res.result[..]);
+
+    let err = call(deps.as_ref(), EthCallMsg { state_overrides: msg.state_overrides[..1].to_vec(), ..msg }).unwrap_err();
+    assert!(matches!(err, ContractError::InsufficientFunds));
+
+    // Contracts given by code overrides revert with Error("nope") and Panic(0x11)
+    let error: H160 = parse_h160("0x00000000000000000000000000000000000e7707");
+    let panic: H160 = parse_h160("0x00000000000000000000000000000000000a7171");
+    let code_override = |address: H160, code: &str| StateOverride {
+        address: address.to_fixed_bytes(),
+        balance: None,
+        code: Some(parse_hex(code)),
+        storage: vec![],
+    };
+    let state_overrides = vec![
+        code_override(error, "0x6064600c60003960646000fd08c379a0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000046e6f706500000000000000000000000000000000000000000000000000000000"),
+        code_override(panic, "0x6024600c60003960246000fd4e487b710000000000000000000000000000000000000000000000000000000000000011"),
+    ];
+
+    let res = call(deps.as_ref(), EthCallMsg { state_overrides: state_overrides.clone(), ..call_msg(error) }).unwrap();
+    assert_eq!("Revert(Reverted)", res.exit_reason);
+    assert!(!res.success);
+    assert_eq!(100, res.result.len());
+    assert_eq!(Some(RevertReason::Error(String::from("nope"))), res.revert_reason);
+
+    let res = call(deps.as_ref(), EthCallMsg { state_overrides, ..call_msg(panic) }).unwrap();
+    assert_eq!(Some(RevertReason::Panic(Uint256::from(0x11_u64))), res.revert_reason);
+
+    // Without the overrides there is no code to run
+    let res = call(deps.as_ref(), call_msg(error)).unwrap();
+    assert!(res.success);
+    assert!(res.result.is_empty());
+}
+
 #[test]
 fn uniswap_v1() {
 
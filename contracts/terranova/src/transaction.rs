@@ -1,25 +1,95 @@
-use evm::{H160, U256};
+use cosmwasm_std::Api;
+use evm::{H160, H256, U256};
 
 use crate::ContractError;
+use crate::hardfork::HardforkConfig;
+use crate::utils::{keccak256_h256, keccak256_h256_v};
+
+/// Hash of an unsigned transaction submitted on behalf of `caller`, under which its receipt, logs and pending state are saved.\ 
+/// Unlike a signed transaction, the unsigned encoding doesn't include the sender and different callers can submit the same bytes,
+/// so the caller address is hashed along with it. Signed transactions are identified by the keccak_h256 hash of their encoding
+pub fn unsigned_tx_hash(caller: &H160, unsigned_tx: &[u8]) -> H256 {
+    keccak256_h256_v(&[caller.as_bytes(), unsigned_tx])
+}
+
+/// Transaction types of EIP-2718, a typed transaction is its type byte followed by the RLP list of its fields
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransactionType {
+    Legacy,
+    /// EIP-2930, a legacy transaction with a chain id and an access list
+    AccessList,
+    /// EIP-1559, the gas price is replaced by a max fee and a priority fee per gas
+    DynamicFee,
+}
+
+impl TransactionType {
+    /// Type of the encoded transaction, a legacy transaction starts with the header of an RLP list, 0xc0 or more
+    fn of(encoded: &[u8]) -> Result<Self, ContractError> {
+        match encoded.first() {
+            Some(0x01) => Ok(Self::AccessList),
+            Some(0x02) => Ok(Self::DynamicFee),
+            Some(byte) if *byte >= 0xc0 => Ok(Self::Legacy),
+            byte => Err!(ContractError::InvalidTransactionData; "Unsupported transaction type {:?}", byte),
+        }
+    }
+
+    /// Typed transactions are only accepted from the hardfork which introduced them
+    #[must_use]
+    pub fn is_enabled(self, config: &HardforkConfig) -> bool {
+        match self {
+            Self::Legacy => true,
+            Self::AccessList => config.has_access_list_tx,
+            Self::DynamicFee => config.has_dynamic_fee_tx,
+        }
+    }
+}
+
+/// Addresses and storage keys a transaction declares it will access (EIP-2930)
+pub type AccessList = Vec<(H160, Vec<H256>)>;
 
 #[derive(Debug)]
 pub struct UnsignedTransaction {
+    pub tx_type: TransactionType,
     pub nonce: u64,
+    /// Gas price of legacy and access list transactions, max fee per gas of dynamic fee transactions
     pub gas_price: U256,
+    /// Same as `gas_price` except for dynamic fee transactions
+    pub max_priority_fee_per_gas: U256,
     pub gas_limit: U256,
     pub to: Option<H160>,
     pub value: U256,
     pub call_data: Vec<u8>,
+    /// Empty for legacy transactions
+    pub access_list: AccessList,
     pub chain_id: Option<U256>,
     pub rlp_len: usize,
 }
 
 impl UnsignedTransaction {
+    /// Legacy transactions are the RLP list of their fields, typed transactions are detected by their leading type byte
     pub fn from_rlp(unsigned_msg: &[u8]) -> Result<Self, ContractError> {
-        let trx = rlp::decode(unsigned_msg)
-            .map_err(|e| E!(ContractError::InvalidTransactionData; "RLP DecoderError={}", e))?;
+        let trx = match TransactionType::of(unsigned_msg)? {
+            TransactionType::Legacy => rlp::decode(unsigned_msg),
+            tx_type => {
+                let rlp = rlp::Rlp::new(&unsigned_msg[1..]);
+                decode_typed_fields(tx_type, &rlp).and_then(|(trx, field_count)| {
+                    if rlp.item_count()? == field_count {
+                        Ok(trx)
+                    } else {
+                        Err(rlp::DecoderError::RlpIncorrectListLen)
+                    }
+                })
+            }
+        };
+
+        trx.map_err(|e| E!(ContractError::InvalidTransactionData; "RLP DecoderError={}", e))
+    }
 
-        Ok(trx)
+    /// Price paid for each unit of gas.\ 
+    /// Terranova has no base fee, so a dynamic fee transaction pays its priority fee, capped by its max fee
+    #[must_use]
+    pub fn effective_gas_price(&self) -> U256 {
+        self.gas_price.min(self.max_priority_fee_per_gas)
     }
 }
 
@@ -33,25 +103,18 @@ impl rlp::Decodable for UnsignedTransaction {
 
         let info = rlp.payload_info()?;
         let payload_size = info.header_len + info.value_len;
+        let gas_price = rlp.val_at(1)?;
 
         let tx = Self {
+            tx_type: TransactionType::Legacy,
             nonce: rlp.val_at(0)?,
-            gas_price: rlp.val_at(1)?,
+            gas_price,
+            max_priority_fee_per_gas: gas_price,
             gas_limit: rlp.val_at(2)?,
-            to: {
-                let to = rlp.at(3)?;
-                if to.is_empty() {
-                    if to.is_data() {
-                        None
-                    } else {
-                        return Err(rlp::DecoderError::RlpExpectedToBeData);
-                    }
-                } else {
-                    Some(to.as_val()?)
-                }
-            },
+            to: decode_to(&rlp.at(3)?)?,
             value: rlp.val_at(4)?,
             call_data: rlp.val_at(5)?,
+            access_list: Vec::new(),
             chain_id: if field_count == 6 {
                 None
             } else {
@@ -65,3 +128,207 @@ impl rlp::Decodable for UnsignedTransaction {
         Ok(tx)
     }
 }
+
+/// secp256k1 curve order divided by two, signatures with a higher `s` value are malleable (EIP-2)
+const SECP256K1_HALF_N: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// An Ethereum transaction together with its signature.\ 
+/// Legacy transactions decode in both the original `v = 27/28` form and the replay protected EIP-155 form `v = chain_id * 2 + 35/36`,
+/// but only the EIP-155 form is executed, the original one has no chain id and is rejected with InvalidChainId.
+/// Typed transactions always carry their chain id, `v` is the parity of the signature point, 0 or 1.
+#[derive(Debug)]
+pub struct SignedTransaction {
+    /// The signed fields of the transaction, `chain_id` is derived from `v` for EIP-155 transactions
+    pub unsigned: UnsignedTransaction,
+    pub v: U256,
+    pub r: U256,
+    pub s: U256,
+    /// Keccak hash of the RLP encoded payload that was signed by the sender
+    pub signing_hash: H256,
+}
+
+impl SignedTransaction {
+    pub fn from_rlp(signed_msg: &[u8]) -> Result<Self, ContractError> {
+        let trx = match TransactionType::of(signed_msg)? {
+            TransactionType::Legacy => rlp::decode(signed_msg),
+            tx_type => Self::decode_typed(tx_type, &rlp::Rlp::new(&signed_msg[1..])),
+        };
+
+        trx.map_err(|e| E!(ContractError::InvalidTransactionData; "RLP DecoderError={}", e))
+    }
+
+    /// The signature follows the fields of the transaction, the signed payload is the type byte followed by the RLP list of the fields
+    fn decode_typed(tx_type: TransactionType, rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        let (unsigned, field_count) = decode_typed_fields(tx_type, rlp)?;
+        if rlp.item_count()? != field_count + 3 {
+            return Err(rlp::DecoderError::RlpIncorrectListLen);
+        }
+
+        let mut stream = rlp::RlpStream::new_list(field_count);
+        for i in 0..field_count {
+            stream.append_raw(rlp.at(i)?.as_raw(), 1);
+        }
+
+        let mut payload = vec![tx_type as u8];
+        payload.extend_from_slice(&stream.out());
+
+        Ok(Self {
+            unsigned,
+            v: rlp.val_at(field_count)?,
+            r: rlp.val_at(field_count + 1)?,
+            s: rlp.val_at(field_count + 2)?,
+            signing_hash: keccak256_h256(&payload),
+        })
+    }
+
+    /// Recover the address of the account that signed this transaction
+    pub fn recover_caller(&self, api: &dyn Api) -> Result<H160, ContractError> {
+        if self.r.is_zero() || self.s.is_zero() || self.s > U256::from_big_endian(&SECP256K1_HALF_N) {
+            return Err!(ContractError::InvalidSignature; "Signature values out of range r={} s={}", self.r, self.s);
+        }
+
+        let mut signature = [0_u8; 64];
+        self.r.to_big_endian(&mut signature[..32]);
+        self.s.to_big_endian(&mut signature[32..]);
+
+        let public_key = api.secp256k1_recover_pubkey(self.signing_hash.as_bytes(), &signature, self.recovery_id()?)
+            .map_err(|e| E!(ContractError::InvalidSignature; "RecoverPubkeyError={}", e))?;
+
+        // Uncompressed SEC1 public key: 0x04 prefix followed by the 64 bytes of the point
+        if public_key.len() != 65 {
+            return Err!(ContractError::InvalidSignature; "Unexpected public key length {}", public_key.len());
+        }
+
+        Ok(keccak256_h256(&public_key[1..]).into())
+    }
+
+    fn recovery_id(&self) -> Result<u8, ContractError> {
+        let offset = match (self.unsigned.tx_type, self.unsigned.chain_id) {
+            (TransactionType::Legacy, Some(chain_id)) => chain_id * 2 + 35,
+            (TransactionType::Legacy, None) => U256::from(27),
+            _ => U256::zero(),
+        };
+
+        match self.v.checked_sub(offset) {
+            Some(id) if id <= U256::one() => Ok(id.as_u32() as u8),
+            _ => Err!(ContractError::InvalidSignature; "Invalid signature v={}", self.v),
+        }
+    }
+}
+
+impl rlp::Decodable for SignedTransaction {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        if rlp.item_count()? != 9 {
+            return Err(rlp::DecoderError::RlpIncorrectListLen);
+        }
+
+        let v: U256 = rlp.val_at(6)?;
+        let chain_id = if v >= U256::from(35) {
+            Some((v - 35) / 2)
+        } else {
+            None
+        };
+
+        // The signed payload is the list of the first six fields, followed by (chain_id, 0, 0) for EIP-155
+        let mut stream = rlp::RlpStream::new_list(if chain_id.is_some() { 9 } else { 6 });
+        for i in 0..6 {
+            stream.append_raw(rlp.at(i)?.as_raw(), 1);
+        }
+        if let Some(chain_id) = chain_id {
+            stream.append(&chain_id);
+            stream.append_empty_data();
+            stream.append_empty_data();
+        }
+
+        let info = rlp.payload_info()?;
+        let gas_price = rlp.val_at(1)?;
+
+        let unsigned = UnsignedTransaction {
+            tx_type: TransactionType::Legacy,
+            nonce: rlp.val_at(0)?,
+            gas_price,
+            max_priority_fee_per_gas: gas_price,
+            gas_limit: rlp.val_at(2)?,
+            to: decode_to(&rlp.at(3)?)?,
+            value: rlp.val_at(4)?,
+            call_data: rlp.val_at(5)?,
+            access_list: Vec::new(),
+            chain_id,
+            rlp_len: info.header_len + info.value_len,
+        };
+
+        Ok(Self {
+            unsigned,
+            v,
+            r: rlp.val_at(7)?,
+            s: rlp.val_at(8)?,
+            signing_hash: keccak256_h256(&stream.out()),
+        })
+    }
+}
+
+/// Decodes the fields of a typed transaction, whose list starts with the chain id and nonce, followed by the gas price
+/// or the priority and max fees, then the fields of a legacy transaction and the access list.
+/// Returns the transaction and its number of fields, which the signature follows in a signed transaction
+fn decode_typed_fields(tx_type: TransactionType, rlp: &rlp::Rlp) -> Result<(UnsignedTransaction, usize), rlp::DecoderError> {
+    let (gas_price, max_priority_fee_per_gas, offset) = match tx_type {
+        TransactionType::AccessList => {
+            let gas_price = rlp.val_at(2)?;
+            (gas_price, gas_price, 3)
+        }
+        TransactionType::DynamicFee => (rlp.val_at(3)?, rlp.val_at(2)?, 4),
+        TransactionType::Legacy => unreachable!("legacy transactions have no type byte"),
+    };
+
+    let info = rlp.payload_info()?;
+
+    let trx = UnsignedTransaction {
+        tx_type,
+        nonce: rlp.val_at(1)?,
+        gas_price,
+        max_priority_fee_per_gas,
+        gas_limit: rlp.val_at(offset)?,
+        to: decode_to(&rlp.at(offset + 1)?)?,
+        value: rlp.val_at(offset + 2)?,
+        call_data: rlp.val_at(offset + 3)?,
+        access_list: decode_access_list(&rlp.at(offset + 4)?)?,
+        chain_id: Some(rlp.val_at(0)?),
+        // Including the type byte
+        rlp_len: 1 + info.header_len + info.value_len,
+    };
+
+    Ok((trx, offset + 5))
+}
+
+/// A list of (address, list of storage keys) pairs
+fn decode_access_list(rlp: &rlp::Rlp) -> Result<AccessList, rlp::DecoderError> {
+    if !rlp.is_list() {
+        return Err(rlp::DecoderError::RlpExpectedToBeList);
+    }
+
+    rlp.iter()
+        .map(|item| {
+            if item.item_count()? != 2 {
+                return Err(rlp::DecoderError::RlpIncorrectListLen);
+            }
+
+            Ok((item.val_at(0)?, item.list_at(1)?))
+        })
+        .collect()
+}
+
+/// An empty `to` field denotes a contract deployment
+fn decode_to(to: &rlp::Rlp) -> Result<Option<H160>, rlp::DecoderError> {
+    if to.is_empty() {
+        if to.is_data() {
+            Ok(None)
+        } else {
+            Err(rlp::DecoderError::RlpExpectedToBeData)
+        }
+    } else {
+        Ok(Some(to.as_val()?))
+    }
+}
@@ -1,21 +1,65 @@
-use cosmwasm_std::{Deps, DepsMut, StdResult};
+use cosmwasm_std::{Deps, DepsMut, Order, StdResult, Storage};
 use cw_storage_plus::Map;
 use evm::{H160, H256};
+use serde::{Deserialize, Serialize};
 
 use crate::ContractError;
+use crate::config::CONTRACT_CONFIG;
+
+/// Number of blocks after the first chunk of a transaction was stored from which anyone can purge its chunks
+pub const CHUNK_EXPIRY_BLOCKS: u64 = 1000;
 
 /// Key: (caller_address, tx_hash, chunk_index)
 /// Value: The chunk data
 /// The third key in the tuple should just be one u8, it has to be wrapped in a byte slice to function as a PrimaryKey
 const CHUNK_STORAGE: Map<(&H160, &H256, &[u8]), Vec<u8>> = Map::new("chunk_storage");
 
-/// If a chunk has already been stored at this address/hash/index tuple, it will be overwritten without warning
-pub fn write_chunk(deps: DepsMut, caller_address: H160, tx_hash: H256, chunk_index: u8, chunk_data: Vec<u8>) -> StdResult<()> {
+/// Key: (caller_address, tx_hash)
+/// Value: a ChunkedTx struct, see its documentation
+const CHUNKED_TXS: Map<(&H160, &H256), ChunkedTx> = Map::new("chunked_txs");
+
+/// The chunks stored so far for a transaction, removed with them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChunkedTx {
+    /// Total size in bytes of the stored chunks, limited by Config::max_chunked_tx_size
+    pub total_size: u32,
+    /// Height from which anyone can purge the chunks with CancelChunkedTx
+    pub expiry_height: u64,
+}
+
+/// A chunk can only be stored once, the chunks must be cancelled to store different ones.\ 
+/// No chunk can be added once the chunks expired
+pub fn write_chunk(deps: DepsMut, height: u64, caller_address: H160, tx_hash: H256, chunk_index: u8, chunk_data: Vec<u8>) -> Result<(), ContractError> {
+    let config = CONTRACT_CONFIG.load(deps.storage)?;
+
+    if chunk_data.len() > config.max_chunk_size as usize {
+        return Err!(ContractError::ChunkTooLarge; "Chunk of {} bytes exceeds the maximum of {} bytes", chunk_data.len(), config.max_chunk_size);
+    }
+
+    if CHUNK_STORAGE.has(deps.storage, (&caller_address, &tx_hash, &[chunk_index])) {
+        return Err!(ContractError::ChunkAlreadyStored; "Chunk {} of transaction {} is already stored", chunk_index, tx_hash);
+    }
+
+    let mut chunked_tx = CHUNKED_TXS.may_load(deps.storage, (&caller_address, &tx_hash))?
+        .unwrap_or(ChunkedTx {
+            total_size: 0,
+            expiry_height: height.saturating_add(CHUNK_EXPIRY_BLOCKS),
+        });
+    check_not_expired(&chunked_tx, height, tx_hash)?;
+
+    // The length is at most max_chunk_size, it fits in a u32
+    chunked_tx.total_size = chunked_tx.total_size.checked_add(chunk_data.len() as u32)
+        .filter(|total_size| *total_size <= config.max_chunked_tx_size)
+        .ok_or_else(|| E!(ContractError::ChunkedTxTooLarge; "Chunks of transaction {} exceed the maximum of {} bytes", tx_hash, config.max_chunked_tx_size))?;
+
+    CHUNKED_TXS.save(deps.storage, (&caller_address, &tx_hash), &chunked_tx)?;
     CHUNK_STORAGE.save(
         deps.storage,
         (&caller_address, &tx_hash, &[chunk_index]),
         &chunk_data
-    )
+    )?;
+
+    Ok(())
 }
 
 pub fn collect_chunks(deps: Deps, caller_address: H160, tx_hash: H256, chunk_count: u8) -> Result<Vec<u8>, ContractError> {
@@ -29,4 +73,39 @@ pub fn collect_chunks(deps: Deps, caller_address: H160, tx_hash: H256, chunk_cou
     }
 
     Ok(collected_bytes)
-}
\ No newline at end of file
+}
+
+/// The stored chunks of the transaction and their index, in ascending order of index
+pub fn stored_chunks(storage: &dyn Storage, caller_address: H160, tx_hash: H256) -> StdResult<Vec<(u8, Vec<u8>)>> {
+    CHUNK_STORAGE
+        .prefix((&caller_address, &tx_hash))
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(index, chunk)| (index[0], chunk)))
+        .collect()
+}
+
+pub fn chunked_tx(storage: &dyn Storage, caller_address: H160, tx_hash: H256) -> StdResult<Option<ChunkedTx>> {
+    CHUNKED_TXS.may_load(storage, (&caller_address, &tx_hash))
+}
+
+/// Expired chunks can be purged by anyone, so they can neither be added to nor executed
+pub fn check_not_expired(chunked_tx: &ChunkedTx, height: u64, tx_hash: H256) -> Result<(), ContractError> {
+    if height >= chunked_tx.expiry_height {
+        return Err!(ContractError::ChunksExpired; "Chunks of transaction {} expired at height {}", tx_hash, chunked_tx.expiry_height);
+    }
+
+    Ok(())
+}
+
+/// Removes all the stored chunks of the transaction, including chunks beyond the chunk count it was executed with
+pub fn remove_chunks(storage: &mut dyn Storage, caller_address: H160, tx_hash: H256) {
+    let indexes: Vec<Vec<u8>> = CHUNK_STORAGE
+        .prefix((&caller_address, &tx_hash))
+        .keys(storage, None, None, Order::Ascending)
+        .collect();
+
+    for index in indexes {
+        CHUNK_STORAGE.remove(storage, (&caller_address, &tx_hash, &index));
+    }
+    CHUNKED_TXS.remove(storage, (&caller_address, &tx_hash));
+}
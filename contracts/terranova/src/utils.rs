@@ -1,4 +1,4 @@
-use tiny_keccak::{keccak256};
+use tiny_keccak::{keccak256, Keccak};
 
 use evm::{U256, H256, H160};
 
@@ -8,12 +8,19 @@ pub fn keccak256_h256(data: &[u8]) -> H256 {
     H256::from_slice(&keccak256(data))
 }
 
-/// Get Keccak256 hash as `H256` from several slices/ 
-/// This function is only required for V2 address derivation/ 
-/// Not needed by Terranova (for now) since our EVM is using the Legacy schema for address derivation
+/// Get Keccak256 hash as `H256` of the concatenation of the slices in order, without copying them into one buffer\ 
+/// Used for CREATE2 address derivation (EIP-1014), which hashes `0xff ++ sender ++ salt ++ keccak256(init_code)`
 #[must_use]
 pub fn keccak256_h256_v(data: &[&[u8]]) -> H256 {
-    unimplemented!();
+    let mut hasher = Keccak::new_keccak256();
+    for slice in data {
+        hasher.update(slice);
+    }
+
+    let mut hash = [0_u8; 32];
+    hasher.finalize(&mut hash);
+
+    H256::from(hash)
 }
 
 /// Get Keccak256 hash as Vec<u8>
@@ -0,0 +1,36 @@
+use cosmwasm_std::Uint128;
+use evm::H160;
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a CosmWasm message sent by an EVM contract through the wasm execute precompile.\
+/// The message runs as a submessage after the EVM transaction, so its outcome is only known in later transactions
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum WasmCallStatus {
+    /// The transaction succeeded, the reply to the submessage hasn't been received yet
+    Pending,
+    Succeeded,
+    /// The submessage failed, its funds were credited back to the EVM account that sent it
+    Failed,
+}
+
+impl WasmCallStatus {
+    /// Status word returned by the wasm call status precompile, 0 is for unknown calls
+    #[must_use]
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Pending => 1,
+            Self::Succeeded => 2,
+            Self::Failed => 3,
+        }
+    }
+}
+
+/// Saved to WASM_CALLS under the call id when the submessage is sent
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WasmCallRecord {
+    /// EVM account that called the precompile, refunded if the submessage fails
+    pub source: H160,
+    /// Amount of the native denom sent with the message
+    pub funds: Uint128,
+    pub status: WasmCallStatus,
+}